@@ -58,23 +58,11 @@ fn print_size_of_full_vs_reduced_names(hierarchy: &Hierarchy) {
     )
 }
 
-const VCD_OPTS: vcd::LoadOptions = vcd::LoadOptions {
-    multi_thread: true,
-    remove_scopes_with_empty_name: false,
-};
-
 fn main() {
     let args = Args::parse();
-    let ext = args.filename.split('.').last().unwrap();
     let start = std::time::Instant::now();
-    let mut wave = match ext {
-        "fst" => wellen::fst::read(&args.filename).expect("Failed to load FST."),
-        "vcd" => {
-            wellen::vcd::read_with_options(&args.filename, VCD_OPTS).expect("Failed to load VCD.")
-        }
-        "ghw" => wellen::ghw::read(&args.filename).expect("Failed to load GHW."),
-        other => panic!("Unsupported file extension: {other}"),
-    };
+    let mut wave =
+        wellen::open(&args.filename, LoadOptions::default()).expect("Failed to load waveform.");
     let load_duration = start.elapsed();
     println!("It took {:?} to load {}", load_duration, args.filename);
     wave.print_backend_statistics();
@@ -97,7 +85,7 @@ fn main() {
         let _signal_name: String = var.full_name(wave.hierarchy());
         let ids = [var.signal_ref(); 1];
         let start = std::time::Instant::now();
-        wave.load_signals(&ids);
+        wave.load_signals(&ids).expect("Failed to load signals.");
         let load_time = start.elapsed();
         let bytes_in_mem = wave.get_signal(var.signal_ref()).unwrap().size_in_memory();
         signal_load_times.push(load_time);
@@ -0,0 +1,124 @@
+// Copyright 2024 The Regents of the University of California
+// released under BSD 3-Clause License
+// author: Kevin Laeufer <laeufer@berkeley.edu>
+//
+// Keeps track of how recently loaded signals were used, such that a waveform can stay within
+// a memory budget by unloading the signals that were not used for the longest time.
+
+use crate::hierarchy::SignalRef;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Reported by [`crate::Waveform::cache_statistics`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStatistics {
+    /// Number of signal accesses that were served from memory. Accesses through
+    /// [`crate::Waveform::get_signal`] are only counted while a memory budget is set.
+    pub hits: u64,
+    /// Number of signals that had to be loaded from the file.
+    pub misses: u64,
+    /// Number of signals that were unloaded in order to stay within the memory budget.
+    pub evictions: u64,
+    /// Number of signals that are currently loaded.
+    pub loaded_signals: usize,
+    /// Memory used by all loaded signals and their cached summaries in bytes.
+    pub bytes_in_memory: usize,
+}
+
+/// Accesses are recorded through a shared reference, since signals are
+/// read through [`crate::Waveform::get_signal`].
+#[derive(Debug, Default)]
+pub(crate) struct SignalCache {
+    budget: Option<usize>,
+    clock: AtomicU64,
+    last_access: HashMap<SignalRef, AtomicU64>,
+    hits: AtomicU64,
+    misses: u64,
+    evictions: u64,
+}
+
+impl SignalCache {
+    pub(crate) fn budget(&self) -> Option<usize> {
+        self.budget
+    }
+
+    pub(crate) fn set_budget(&mut self, budget: Option<usize>) {
+        self.budget = budget;
+    }
+
+    fn tick(&self) -> u64 {
+        self.clock.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// Records an access to a loaded signal.
+    pub(crate) fn hit(&self, id: SignalRef) {
+        if let Some(last_access) = self.last_access.get(&id) {
+            last_access.store(self.tick(), Ordering::Relaxed);
+            self.hits.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Records that a signal had to be loaded.
+    pub(crate) fn miss(&mut self, id: SignalRef) {
+        self.misses += 1;
+        let now = self.tick();
+        self.last_access.insert(id, AtomicU64::new(now));
+    }
+
+    pub(crate) fn remove(&mut self, id: SignalRef) {
+        self.last_access.remove(&id);
+    }
+
+    pub(crate) fn evict(&mut self, id: SignalRef) {
+        self.remove(id);
+        self.evictions += 1;
+    }
+
+    /// Returns all loaded signals, starting with the least recently used one.
+    pub(crate) fn lru_order(&self) -> Vec<SignalRef> {
+        let mut ids: Vec<_> = self
+            .last_access
+            .iter()
+            .map(|(id, last_access)| (last_access.load(Ordering::Relaxed), *id))
+            .collect();
+        ids.sort_unstable();
+        ids.into_iter().map(|(_, id)| id).collect()
+    }
+
+    pub(crate) fn statistics(
+        &self,
+        loaded_signals: usize,
+        bytes_in_memory: usize,
+    ) -> CacheStatistics {
+        CacheStatistics {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses,
+            evictions: self.evictions,
+            loaded_signals,
+            bytes_in_memory,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lru_order() {
+        let ids: Vec<_> = (0..3)
+            .map(|ii| SignalRef::from_index(ii).unwrap())
+            .collect();
+        let mut cache = SignalCache::default();
+        ids.iter().for_each(|id| cache.miss(*id));
+        assert_eq!(cache.lru_order(), ids);
+        cache.hit(ids[0]);
+        assert_eq!(cache.lru_order(), [ids[1], ids[2], ids[0]]);
+        cache.evict(ids[1]);
+        // accessing a signal that is not loaded is not a hit
+        cache.hit(ids[1]);
+        assert_eq!(cache.lru_order(), [ids[2], ids[0]]);
+        let stats = cache.statistics(2, 0);
+        assert_eq!((stats.hits, stats.misses, stats.evictions), (1, 3, 1));
+    }
+}
@@ -0,0 +1,727 @@
+// Copyright 2024 The Regents of the University of California
+// released under BSD 3-Clause License
+// author: Kevin Laeufer <laeufer@berkeley.edu>
+//
+// Derived signals that are computed from other signals through bit-level expressions.
+
+use crate::hierarchy::{GetItem, Hierarchy, SignalRef};
+use crate::signals::{bit_value, BitVectorBuilder, Signal, SignalValue, TimeTableIdx, Waveform};
+use crate::wavemem::{bit_char_to_num, check_states, write_n_state, States};
+use crate::WellenError;
+use std::cmp::Ordering;
+
+pub type Result<T> = std::result::Result<T, WellenError>;
+
+/// A bit-level expression over signals. Operands of different widths are zero extended and
+/// unknown bits (`x`, `z`, `u`, ...) propagate like in Verilog.
+/// Weak states (`l` and `h`) are treated like their strong counterparts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr {
+    Signal(SignalRef),
+    /// A constant in bit string format, msb first, e.g., `"10x1"`.
+    Const(String),
+    Unary(UnaryOp, Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    /// Selects bits `msb` down to `lsb` (inclusive).
+    Slice(Box<Expr>, u32, u32),
+    /// Concatenates values with the most significant part first, like `{hi, lo}` in Verilog.
+    Concat(Vec<Expr>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOp {
+    /// Bitwise negation: `~a`
+    Not,
+    /// True iff all bits are zero: `!a`
+    LogicalNot,
+    /// `&a`
+    ReduceAnd,
+    /// `|a`
+    ReduceOr,
+    /// `^a`
+    ReduceXor,
+}
+
+/// Comparisons treat both operands as unsigned numbers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    And,
+    Or,
+    Xor,
+    LogicalAnd,
+    LogicalOr,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl BinaryOp {
+    fn is_bitwise(self) -> bool {
+        matches!(self, BinaryOp::And | BinaryOp::Or | BinaryOp::Xor)
+    }
+}
+
+impl Expr {
+    /// Parses an expression in Verilog like syntax, e.g., `a & ~b`, `bus[7:4]`, `{hi, lo}`,
+    /// `valid && ready` or `count == 3`. Signals are referred to by their full hierarchical
+    /// name, e.g., `top.cpu.valid`. Constants are either decimal numbers or sized literals
+    /// like `4'b10x1` and `8'hff`.
+    pub fn parse(text: &str, hierarchy: &Hierarchy) -> Result<Self> {
+        let mut parser = Parser {
+            text,
+            tokens: tokenize(text)?,
+            pos: 0,
+            hierarchy,
+        };
+        let expr = parser.parse_binary(0)?;
+        match parser.tokens.get(parser.pos) {
+            None => Ok(expr),
+            Some(token) => Err(parser.error(format!("unexpected {token:?}"))),
+        }
+    }
+
+    /// Returns all signals that the expression depends on.
+    pub fn signals(&self) -> Vec<SignalRef> {
+        let mut out = vec![];
+        self.collect_signals(&mut out);
+        out.sort_unstable();
+        out.dedup();
+        out
+    }
+
+    fn collect_signals(&self, out: &mut Vec<SignalRef>) {
+        match self {
+            Expr::Signal(id) => out.push(*id),
+            Expr::Const(_) => {}
+            Expr::Unary(_, e) | Expr::Slice(e, _, _) => e.collect_signals(out),
+            Expr::Binary(_, a, b) => {
+                a.collect_signals(out);
+                b.collect_signals(out);
+            }
+            Expr::Concat(parts) => parts.iter().for_each(|e| e.collect_signals(out)),
+        }
+    }
+
+    /// Computes the number of bits of the result and checks that all constants and
+    /// slices are valid.
+    fn width(&self, env: &Env) -> Result<u32> {
+        let width = match self {
+            Expr::Signal(id) => env.get(*id).len() as u32,
+            Expr::Const(bits) => {
+                if bits.is_empty() || bits.bytes().any(|b| bit_char_to_num(b).is_none()) {
+                    return Err(WellenError::InvalidExpr(format!(
+                        "`{bits}` is not a valid constant"
+                    )));
+                }
+                bits.len() as u32
+            }
+            Expr::Unary(UnaryOp::Not, e) => e.width(env)?,
+            Expr::Unary(_, e) => {
+                e.width(env)?;
+                1
+            }
+            Expr::Binary(op, a, b) => {
+                let (a, b) = (a.width(env)?, b.width(env)?);
+                if op.is_bitwise() {
+                    a.max(b)
+                } else {
+                    1
+                }
+            }
+            Expr::Slice(e, msb, lsb) => {
+                let width = e.width(env)?;
+                if lsb > msb || *msb >= width {
+                    return Err(WellenError::InvalidExpr(format!(
+                        "[{msb}:{lsb}] is out of range for a {width}-bit value"
+                    )));
+                }
+                msb - lsb + 1
+            }
+            Expr::Concat(parts) => {
+                if parts.is_empty() {
+                    return Err(WellenError::InvalidExpr("empty concatenation".to_string()));
+                }
+                let mut width = 0;
+                for part in parts.iter() {
+                    width += part.width(env)?;
+                }
+                width
+            }
+        };
+        Ok(width)
+    }
+
+    /// Returns the bits of the result, lsb first.
+    fn eval(&self, env: &Env) -> Vec<char> {
+        match self {
+            Expr::Signal(id) => env.get(*id).to_vec(),
+            Expr::Const(bits) => bits.chars().rev().map(|c| c.to_ascii_lowercase()).collect(),
+            Expr::Unary(op, e) => {
+                let value = e.eval(env);
+                match op {
+                    UnaryOp::Not => value.into_iter().map(not).collect(),
+                    UnaryOp::LogicalNot => vec![not(reduce(&value, or))],
+                    UnaryOp::ReduceAnd => vec![reduce(&value, and)],
+                    UnaryOp::ReduceOr => vec![reduce(&value, or)],
+                    UnaryOp::ReduceXor => vec![reduce(&value, xor)],
+                }
+            }
+            Expr::Binary(op, a, b) => {
+                let (mut a, mut b) = (a.eval(env), b.eval(env));
+                let width = a.len().max(b.len());
+                a.resize(width, '0');
+                b.resize(width, '0');
+                let bitwise = |f: fn(char, char) -> char| {
+                    a.iter().zip(b.iter()).map(|(a, b)| f(*a, *b)).collect()
+                };
+                let logical = |f: fn(char, char) -> char| vec![f(reduce(&a, or), reduce(&b, or))];
+                let comparison = |f: fn(Ordering) -> bool| match compare(&a, &b) {
+                    Some(ordering) if f(ordering) => vec!['1'],
+                    Some(_) => vec!['0'],
+                    None => vec!['x'],
+                };
+                match op {
+                    BinaryOp::And => bitwise(and),
+                    BinaryOp::Or => bitwise(or),
+                    BinaryOp::Xor => bitwise(xor),
+                    BinaryOp::LogicalAnd => logical(and),
+                    BinaryOp::LogicalOr => logical(or),
+                    BinaryOp::Eq => comparison(|o| o.is_eq()),
+                    BinaryOp::Ne => comparison(|o| o.is_ne()),
+                    BinaryOp::Lt => comparison(|o| o.is_lt()),
+                    BinaryOp::Le => comparison(|o| o.is_le()),
+                    BinaryOp::Gt => comparison(|o| o.is_gt()),
+                    BinaryOp::Ge => comparison(|o| o.is_ge()),
+                }
+            }
+            Expr::Slice(e, msb, lsb) => e.eval(env)[*lsb as usize..=*msb as usize].to_vec(),
+            Expr::Concat(parts) => parts.iter().rev().flat_map(|e| e.eval(env)).collect(),
+        }
+    }
+}
+
+#[inline]
+fn not(a: char) -> char {
+    match bit_value(a) {
+        Some(true) => '0',
+        Some(false) => '1',
+        None => 'x',
+    }
+}
+
+#[inline]
+fn and(a: char, b: char) -> char {
+    match (bit_value(a), bit_value(b)) {
+        (Some(false), _) | (_, Some(false)) => '0',
+        (Some(true), Some(true)) => '1',
+        _ => 'x',
+    }
+}
+
+#[inline]
+fn or(a: char, b: char) -> char {
+    match (bit_value(a), bit_value(b)) {
+        (Some(true), _) | (_, Some(true)) => '1',
+        (Some(false), Some(false)) => '0',
+        _ => 'x',
+    }
+}
+
+#[inline]
+fn xor(a: char, b: char) -> char {
+    match (bit_value(a), bit_value(b)) {
+        (Some(a), Some(b)) if a != b => '1',
+        (Some(_), Some(_)) => '0',
+        _ => 'x',
+    }
+}
+
+fn reduce(value: &[char], op: fn(char, char) -> char) -> char {
+    let mut bits = value.iter().copied();
+    // normalize the first bit, such that e.g. `l` turns into `0`
+    let first = bits.next().map(|b| not(not(b))).unwrap_or('0');
+    bits.fold(first, op)
+}
+
+/// Compares two values of the same width. Returns `None` if any bit is unknown.
+fn compare(a: &[char], b: &[char]) -> Option<Ordering> {
+    let mut result = Ordering::Equal;
+    for (a, b) in a.iter().zip(b.iter()).rev() {
+        let (a, b) = (bit_value(*a)?, bit_value(*b)?);
+        if result.is_eq() {
+            result = a.cmp(&b);
+        }
+    }
+    Some(result)
+}
+
+/// Values of all signals at the current time step, lsb first.
+struct Env {
+    ids: Vec<SignalRef>,
+    values: Vec<Vec<char>>,
+}
+
+impl Env {
+    fn get(&self, id: SignalRef) -> &[char] {
+        let idx = self.ids.binary_search(&id).expect("unknown signal");
+        &self.values[idx]
+    }
+}
+
+/// Evaluates the expression whenever one of its inputs changes. All inputs need to be loaded.
+pub(crate) fn derive_signal(waveform: &Waveform, expr: &Expr) -> Result<Signal> {
+    let ids = expr.signals();
+    let mut signals = Vec::with_capacity(ids.len());
+    let mut values = Vec::with_capacity(ids.len());
+    for id in ids.iter() {
+        let name = || signal_name(waveform, *id);
+        let signal = waveform
+            .get_signal(*id)
+            .ok_or_else(|| WellenError::SignalNotLoaded(name()))?;
+        let bits = signal
+            .bits()
+            .ok_or_else(|| WellenError::InvalidExpr(format!("{} is not a bit vector", name())))?;
+        signals.push(signal);
+        values.push(vec!['x'; bits as usize]);
+    }
+    let mut env = Env { ids, values };
+    let width = expr.width(&env)?;
+
+    // the derived signal may change whenever one of its inputs changes
+    let mut time_indices: Vec<TimeTableIdx> = signals
+        .iter()
+        .flat_map(|s| s.time_indices().iter().copied())
+        .collect();
+    time_indices.sort_unstable();
+    time_indices.dedup();
+    if signals.is_empty() && !waveform.time_table().is_empty() {
+        time_indices.push(0);
+    }
+
+    let mut changes = Vec::with_capacity(time_indices.len());
+    let mut max_states = States::Two;
+    for time_idx in time_indices {
+        for (signal, value) in signals.iter().zip(env.values.iter_mut()) {
+            if let Some(v) = signal.value_at_idx(time_idx) {
+                for (bit, out) in value.iter_mut().enumerate() {
+                    *out = v.bit_char(bit as u32).unwrap();
+                }
+            }
+        }
+        let bits: Vec<u8> = expr.eval(&env).into_iter().rev().map(|c| c as u8).collect();
+        let states = check_states(&bits).unwrap();
+        max_states = States::join(max_states, states);
+        changes.push((time_idx, states, bits));
+    }
+
+    let mut builder = BitVectorBuilder::new(max_states, width);
+    let mut data = vec![];
+    for (time_idx, states, bits) in changes {
+        data.clear();
+        write_n_state(states, &bits, &mut data, None);
+        let value = match states {
+            States::Two => SignalValue::Binary(&data, width),
+            States::Four => SignalValue::FourValue(&data, width),
+            States::Nine => SignalValue::NineValue(&data, width),
+        };
+        builder.add_change(time_idx, value);
+    }
+    // derived signals are not part of the hierarchy
+    let id = SignalRef::from_index(waveform.hierarchy().num_unique_signals()).unwrap();
+    Ok(builder.finish(id))
+}
+
+fn signal_name(waveform: &Waveform, id: SignalRef) -> String {
+    let h = waveform.hierarchy();
+    match h.iter_vars().find(|v| v.signal_ref() == id) {
+        Some(var) => var.full_name(h),
+        None => format!("{id:?}"),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Ident(String),
+    /// Unsized decimal number.
+    Int(u64),
+    /// Sized literal converted into a bit string, msb first.
+    Literal(String),
+    Op(&'static str),
+}
+
+/// Sorted such that longer operators are matched first.
+const OPERATORS: [&str; 21] = [
+    "||", "&&", "==", "!=", "<=", ">=", "~", "!", "&", "|", "^", "<", ">", "(", ")", "[", "]", "{",
+    "}", ":", ",",
+];
+
+fn tokenize(text: &str) -> Result<Vec<Token>> {
+    let error = |msg: String| WellenError::ExprParse(text.to_string(), msg);
+    let mut tokens = vec![];
+    let mut rest = text.trim_start();
+    while !rest.is_empty() {
+        let first = rest.chars().next().unwrap();
+        let len = if first.is_ascii_alphabetic() || first == '_' {
+            let len = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '$')))
+                .unwrap_or(rest.len());
+            tokens.push(Token::Ident(rest[..len].to_string()));
+            len
+        } else if first.is_ascii_digit() {
+            let digits = rest
+                .find(|c: char| !(c.is_ascii_digit() || c == '_'))
+                .unwrap_or(rest.len());
+            let value: u64 = rest[..digits]
+                .replace('_', "")
+                .parse()
+                .map_err(|_| error(format!("`{}` is too large", &rest[..digits])))?;
+            if rest[digits..].starts_with('\'') {
+                let literal = &rest[digits + 1..];
+                let len = literal
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '_' | '?')))
+                    .unwrap_or(literal.len());
+                let bits = parse_literal(value, &literal[..len]).ok_or_else(|| {
+                    error(format!("invalid literal `{}`", &rest[..digits + 1 + len]))
+                })?;
+                tokens.push(Token::Literal(bits));
+                digits + 1 + len
+            } else {
+                tokens.push(Token::Int(value));
+                digits
+            }
+        } else if let Some(op) = OPERATORS.iter().find(|op| rest.starts_with(**op)) {
+            tokens.push(Token::Op(op));
+            op.len()
+        } else {
+            return Err(error(format!("unexpected character `{first}`")));
+        };
+        rest = rest[len..].trim_start();
+    }
+    Ok(tokens)
+}
+
+/// Converts a literal like `b10x1` or `hff` into a bit string of `width` bits.
+fn parse_literal(width: u64, literal: &str) -> Option<String> {
+    if width == 0 || width > u32::MAX as u64 {
+        return None;
+    }
+    let mut chars = literal.chars();
+    let base = chars.next()?.to_ascii_lowercase();
+    let digits: String = chars
+        .filter(|c| *c != '_')
+        .map(|c| match c {
+            '?' => 'z',
+            c => c.to_ascii_lowercase(),
+        })
+        .collect();
+    if digits.is_empty() {
+        return None;
+    }
+    let bits_per_digit = match base {
+        'b' => 1,
+        'o' => 3,
+        'h' => 4,
+        'd' => {
+            let value: u64 = digits.parse().ok()?;
+            return Some(resize_bits(format!("{value:b}"), width as usize));
+        }
+        _ => return None,
+    };
+    let mut bits = String::with_capacity(digits.len() * bits_per_digit);
+    for c in digits.chars() {
+        match c.to_digit(1 << bits_per_digit) {
+            Some(value) => bits.push_str(&format!("{value:0bits_per_digit$b}")),
+            None if matches!(c, 'x' | 'z') => (0..bits_per_digit).for_each(|_| bits.push(c)),
+            None => return None,
+        }
+    }
+    Some(resize_bits(bits, width as usize))
+}
+
+/// Truncates or extends a bit string. Like in Verilog, a value is extended with `x` or `z`
+/// if that is its most significant bit and with `0` otherwise.
+fn resize_bits(bits: String, width: usize) -> String {
+    match bits.len().cmp(&width) {
+        Ordering::Greater => bits[bits.len() - width..].to_string(),
+        Ordering::Equal => bits,
+        Ordering::Less => {
+            let pad = bits
+                .chars()
+                .next()
+                .filter(|c| matches!(c, 'x' | 'z'))
+                .unwrap_or('0');
+            std::iter::repeat_n(pad, width - bits.len())
+                .chain(bits.chars())
+                .collect()
+        }
+    }
+}
+
+/// Binary operators from lowest to highest precedence.
+const BINARY_OPS: [(&str, BinaryOp, u8); 11] = [
+    ("||", BinaryOp::LogicalOr, 1),
+    ("&&", BinaryOp::LogicalAnd, 2),
+    ("|", BinaryOp::Or, 3),
+    ("^", BinaryOp::Xor, 4),
+    ("&", BinaryOp::And, 5),
+    ("==", BinaryOp::Eq, 6),
+    ("!=", BinaryOp::Ne, 6),
+    ("<", BinaryOp::Lt, 7),
+    ("<=", BinaryOp::Le, 7),
+    (">", BinaryOp::Gt, 7),
+    (">=", BinaryOp::Ge, 7),
+];
+
+struct Parser<'a> {
+    text: &'a str,
+    tokens: Vec<Token>,
+    pos: usize,
+    hierarchy: &'a Hierarchy,
+}
+
+impl Parser<'_> {
+    fn error(&self, msg: String) -> WellenError {
+        WellenError::ExprParse(self.text.to_string(), msg)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn peek_op(&self) -> Option<&'static str> {
+        match self.tokens.get(self.pos) {
+            Some(Token::Op(op)) => Some(op),
+            _ => None,
+        }
+    }
+
+    fn expect(&mut self, op: &str) -> Result<()> {
+        match self.next() {
+            Some(Token::Op(o)) if o == op => Ok(()),
+            Some(other) => Err(self.error(format!("expected `{op}`, not {other:?}"))),
+            None => Err(self.error(format!("expected `{op}`"))),
+        }
+    }
+
+    fn parse_binary(&mut self, min_precedence: u8) -> Result<Expr> {
+        let mut lhs = self.parse_unary()?;
+        while let Some((_, op, precedence)) = self
+            .peek_op()
+            .and_then(|o| BINARY_OPS.iter().find(|(name, _, _)| *name == o))
+        {
+            if *precedence < min_precedence {
+                break;
+            }
+            self.pos += 1;
+            let rhs = self.parse_binary(precedence + 1)?;
+            lhs = Expr::Binary(*op, Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr> {
+        let op = match self.peek_op() {
+            Some("~") => UnaryOp::Not,
+            Some("!") => UnaryOp::LogicalNot,
+            Some("&") => UnaryOp::ReduceAnd,
+            Some("|") => UnaryOp::ReduceOr,
+            Some("^") => UnaryOp::ReduceXor,
+            _ => return self.parse_postfix(),
+        };
+        self.pos += 1;
+        Ok(Expr::Unary(op, Box::new(self.parse_unary()?)))
+    }
+
+    fn parse_postfix(&mut self) -> Result<Expr> {
+        let mut expr = self.parse_primary()?;
+        while self.peek_op() == Some("[") {
+            self.pos += 1;
+            let msb = self.parse_index()?;
+            let lsb = if self.peek_op() == Some(":") {
+                self.pos += 1;
+                self.parse_index()?
+            } else {
+                msb
+            };
+            self.expect("]")?;
+            expr = Expr::Slice(Box::new(expr), msb, lsb);
+        }
+        Ok(expr)
+    }
+
+    fn parse_index(&mut self) -> Result<u32> {
+        match self.next() {
+            Some(Token::Int(value)) if value <= u32::MAX as u64 => Ok(value as u32),
+            Some(other) => Err(self.error(format!("expected a bit index, not {other:?}"))),
+            None => Err(self.error("expected a bit index".to_string())),
+        }
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr> {
+        match self.next() {
+            Some(Token::Ident(name)) => self.resolve(&name),
+            Some(Token::Int(value)) => Ok(Expr::Const(format!("{value:b}"))),
+            Some(Token::Literal(bits)) => Ok(Expr::Const(bits)),
+            Some(Token::Op("(")) => {
+                let expr = self.parse_binary(0)?;
+                self.expect(")")?;
+                Ok(expr)
+            }
+            Some(Token::Op("{")) => {
+                let mut parts = vec![self.parse_binary(0)?];
+                while self.peek_op() == Some(",") {
+                    self.pos += 1;
+                    parts.push(self.parse_binary(0)?);
+                }
+                self.expect("}")?;
+                Ok(Expr::Concat(parts))
+            }
+            Some(other) => Err(self.error(format!("unexpected {other:?}"))),
+            None => Err(self.error("unexpected end of expression".to_string())),
+        }
+    }
+
+    fn resolve(&self, name: &str) -> Result<Expr> {
+        let mut path: Vec<&str> = name.split('.').collect();
+        let var_name = path.pop().unwrap();
+        let var = self
+            .hierarchy
+            .lookup_var(&path, &var_name)
+            .ok_or_else(|| self.error(format!("unknown signal `{name}`")))?;
+        Ok(Expr::Signal(self.hierarchy.get(var).signal_ref()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VCD: &[u8] = b"$scope module top $end\n\
+        $var wire 1 ! a $end\n$var wire 1 \" b $end\n$var wire 4 # bus $end\n\
+        $upscope $end\n$enddefinitions $end\n\
+        #0\n0!\n1\"\nb0011 #\n#1\n1!\nb10x1 #\n#2\n0\"\nb1100 #\n#3\nz!\n";
+
+    fn derive(text: &str) -> Vec<(TimeTableIdx, String)> {
+        let mut waves = crate::vcd::read_from_bytes(VCD).unwrap();
+        let ids: Vec<_> = waves
+            .hierarchy()
+            .iter_vars()
+            .map(|v| v.signal_ref())
+            .collect();
+        waves.load_signals(&ids).unwrap();
+        let expr = Expr::parse(text, waves.hierarchy()).unwrap();
+        let signal = waves.derive_signal(&expr).unwrap();
+        signal
+            .iter_changes()
+            .map(|(t, v)| (t, v.to_bit_string().unwrap()))
+            .collect()
+    }
+
+    fn changes(values: &[(TimeTableIdx, &str)]) -> Vec<(TimeTableIdx, String)> {
+        values.iter().map(|(t, v)| (*t, v.to_string())).collect()
+    }
+
+    #[test]
+    fn test_parse_literals() {
+        assert_eq!(parse_literal(4, "b10x1").unwrap(), "10x1");
+        assert_eq!(parse_literal(8, "hf").unwrap(), "00001111");
+        assert_eq!(parse_literal(8, "hx").unwrap(), "xxxxxxxx");
+        assert_eq!(parse_literal(3, "d3").unwrap(), "011");
+        assert_eq!(parse_literal(2, "o7").unwrap(), "11");
+        assert!(parse_literal(4, "b102").is_none());
+        assert!(parse_literal(0, "b1").is_none());
+    }
+
+    #[test]
+    fn test_bitwise() {
+        assert_eq!(
+            derive("top.a & ~top.b"),
+            changes(&[(0, "0"), (2, "1"), (3, "x")])
+        );
+        assert_eq!(derive("top.a | top.b"), changes(&[(0, "1"), (3, "x")]));
+        // narrower operands are zero extended
+        assert_eq!(
+            derive("top.bus ^ top.a"),
+            changes(&[(0, "0011"), (1, "10x0"), (2, "1101"), (3, "110x")])
+        );
+    }
+
+    #[test]
+    fn test_slice_and_concat() {
+        assert_eq!(
+            derive("top.bus[3:2]"),
+            changes(&[(0, "00"), (1, "10"), (2, "11")])
+        );
+        assert_eq!(
+            derive("top.bus[1]"),
+            changes(&[(0, "1"), (1, "x"), (2, "0")])
+        );
+        assert_eq!(
+            derive("{top.a, top.bus[1:0], 1'b0}"),
+            changes(&[(0, "0110"), (1, "1x10"), (2, "1000"), (3, "z000")])
+        );
+    }
+
+    #[test]
+    fn test_compare_and_reduce() {
+        assert_eq!(
+            derive("top.bus == 3"),
+            changes(&[(0, "1"), (1, "x"), (2, "0")])
+        );
+        assert_eq!(
+            derive("top.bus > 4'd2"),
+            changes(&[(0, "1"), (1, "x"), (2, "1")])
+        );
+        assert_eq!(
+            derive("top.a && top.b"),
+            changes(&[(0, "0"), (1, "1"), (2, "0")])
+        );
+        assert_eq!(derive("|top.bus"), changes(&[(0, "1")]));
+        assert_eq!(derive("&top.bus[3:2]"), changes(&[(0, "0"), (2, "1")]));
+        assert_eq!(derive("^top.bus"), changes(&[(0, "0"), (1, "x"), (2, "0")]));
+        assert_eq!(derive("!top.bus"), changes(&[(0, "0")]));
+    }
+
+    #[test]
+    fn test_precedence() {
+        let h = crate::vcd::read_from_bytes(VCD).unwrap();
+        let h = h.hierarchy();
+        let a = || Box::new(Expr::parse("top.a", h).unwrap());
+        let b = || Box::new(Expr::parse("top.b", h).unwrap());
+        assert_eq!(
+            Expr::parse("top.a | top.b & ~top.a", h).unwrap(),
+            Expr::Binary(
+                BinaryOp::Or,
+                a(),
+                Box::new(Expr::Binary(
+                    BinaryOp::And,
+                    b(),
+                    Box::new(Expr::Unary(UnaryOp::Not, a()))
+                ))
+            )
+        );
+        assert!(matches!(
+            Expr::parse("top.c", h),
+            Err(WellenError::ExprParse(..))
+        ));
+        assert!(matches!(
+            Expr::parse("(top.a", h),
+            Err(WellenError::ExprParse(..))
+        ));
+    }
+
+    #[test]
+    fn test_invalid_slice() {
+        let mut waves = crate::vcd::read_from_bytes(VCD).unwrap();
+        let expr = Expr::parse("top.bus[4:0]", waves.hierarchy()).unwrap();
+        assert!(matches!(
+            waves.derive_signal(&expr),
+            Err(WellenError::InvalidExpr(_))
+        ));
+    }
+}
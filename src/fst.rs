@@ -3,91 +3,318 @@
 // author: Kevin Laeufer <laeufer@berkeley.edu>
 
 use crate::hierarchy::*;
-use crate::signals::{Signal, SignalEncoding, SignalSource, Time, TimeTableIdx};
-use crate::vcd::{extract_index_from_name, parse_index};
+use crate::loader::{BodyLoader, BodyLoaderKind, SharedBytes};
+use crate::progress::ProgressReader;
+use crate::signals::{Signal, SignalEncoding, SignalSource, SignalValue, Time, TimeTableIdx};
+use crate::vcd::{
+    default_vhdl_type_name, extract_index_from_name, parse_index, var_tpe_to_vhdl_data_type,
+    FST_SUP_VAR_DATA_TYPE_BITS,
+};
 use crate::wavemem::{check_if_changed_and_truncate, check_states, write_n_state, States};
-use crate::{FileFormat, Waveform, WellenError};
+use crate::{FileFormat, LoadOptions, LoadPhase, Waveform, WellenError};
 use fst_native::*;
+use rayon::prelude::*;
 use std::collections::HashMap;
-use std::io::{BufRead, Seek};
+use std::io::{BufRead, Cursor, Seek, SeekFrom, Write};
+use std::sync::{Arc, Mutex};
 
 pub type Result<T> = std::result::Result<T, WellenError>;
 
 pub fn read(filename: &str) -> Result<Waveform> {
-    let input = std::fs::File::open(filename).expect("failed to open input file!");
-    let mut reader = FstReader::open_and_read_time_table(std::io::BufReader::new(input)).unwrap();
-    let hierarchy = read_hierarchy(&mut reader);
-    let db = Box::new(FstWaveDatabase::new(reader));
-    Ok(Waveform::new(hierarchy, db))
+    read_with_options(filename, LoadOptions::default())
 }
 
 pub fn read_from_bytes(bytes: Vec<u8>) -> Result<Waveform> {
-    let mut reader = FstReader::open_and_read_time_table(std::io::Cursor::new(bytes)).unwrap();
-    let hierarchy = read_hierarchy(&mut reader);
-    let db = Box::new(FstWaveDatabase::new(reader));
+    read_from_bytes_with_options(bytes, LoadOptions::default())
+}
+
+pub fn read_with_options(filename: &str, options: LoadOptions) -> Result<Waveform> {
+    let (hierarchy, db) = read_internal(SharedBytes::map_file(filename)?, options)?;
+    Ok(Waveform::new(hierarchy, db))
+}
+
+pub fn read_from_bytes_with_options(bytes: Vec<u8>, options: LoadOptions) -> Result<Waveform> {
+    let (hierarchy, db) = read_internal(SharedBytes::new(bytes), options)?;
+    Ok(Waveform::new(hierarchy, db))
+}
+
+/// Only reads the hierarchy and the time table. Signal values are always loaded on demand,
+/// thus the returned [`BodyLoader`] is cheap to run.
+pub fn read_header(filename: &str, options: LoadOptions) -> Result<(Hierarchy, BodyLoader)> {
+    let (hierarchy, db) = read_internal(SharedBytes::map_file(filename)?, options)?;
+    Ok((hierarchy, BodyLoader::new(BodyLoaderKind::Fst(db))))
+}
+
+fn read_internal(
+    bytes: SharedBytes,
+    options: LoadOptions,
+) -> Result<(Hierarchy, Box<dyn SignalSource + Send + Sync>)> {
+    // additional readers are only cheap to create, if the file does not need to be uncompressed
+    let is_gzip_wrapped = bytes.as_ref().first() == Some(&FST_GZIP_WRAPPER_BLOCK);
+    let len = bytes.as_ref().len() as u64;
+    let shared = (!is_gzip_wrapped).then_some(bytes.clone());
+    open_with_progress(Cursor::new(bytes), len, shared, options)
+}
+
+pub(crate) fn read_from_reader<R: BufRead + Seek + Send + Sync + 'static>(
+    mut input: R,
+    options: LoadOptions,
+) -> Result<Waveform> {
+    let start = input.stream_position()?;
+    let len = input.seek(SeekFrom::End(0))? - start;
+    input.seek(SeekFrom::Start(start))?;
+    let (hierarchy, db) = open_with_progress(input, len, None, options)?;
     Ok(Waveform::new(hierarchy, db))
 }
 
+fn open_with_progress<R: BufRead + Seek + Send + Sync + 'static>(
+    input: R,
+    len: u64,
+    shared: Option<SharedBytes>,
+    options: LoadOptions,
+) -> Result<(Hierarchy, Box<dyn SignalSource + Send + Sync>)> {
+    let progress = options.progress();
+    progress.start_phase(LoadPhase::Header, len)?;
+    progress.map_result(open_internal(input, shared, &options))
+}
+
+fn open_internal<R: BufRead + Seek + Send + Sync + 'static>(
+    input: R,
+    shared: Option<SharedBytes>,
+    options: &LoadOptions,
+) -> Result<(Hierarchy, Box<dyn SignalSource + Send + Sync>)> {
+    let input = ProgressReader::new(input, options.progress.clone());
+    let detach_progress = input.detach_flag();
+    let mut reader = FstReader::open_and_read_time_table(input).map_err(convert_error)?;
+    let hierarchy = read_hierarchy(&mut reader, options)?;
+    let mask = options.signal_mask(&hierarchy);
+    let db = FstWaveDatabase::new(reader, shared, mask)?;
+    // signals are loaded long after opening the file, thus they are not part of the progress
+    detach_progress.store(true, std::sync::atomic::Ordering::Relaxed);
+    Ok((hierarchy, Box::new(db)))
+}
+
+/// Block type of a FST file that was compressed as a whole.
+const FST_GZIP_WRAPPER_BLOCK: u8 = 254;
+
+/// Converts errors from the `fst_native` library. Unfortunately, the error type
+/// is not public, thus we need to inspect the debug output in order to
+/// detect truncated files.
+fn convert_error(e: impl std::fmt::Debug) -> WellenError {
+    let msg = format!("{e:?}");
+    if msg.contains("UnexpectedEof") {
+        WellenError::TruncatedFile(FileFormat::Fst)
+    } else {
+        WellenError::FstParse(msg)
+    }
+}
+
 struct FstWaveDatabase<R: BufRead + Seek> {
-    reader: FstReader<R>,
-    time_table: Vec<u64>,
+    reader: Mutex<FstReader<R>>,
+    /// Allows us to open additional readers in order to load signals in parallel
+    /// and to serve multiple threads at the same time.
+    bytes: Option<SharedBytes>,
+    time_table: Arc<[Time]>,
+    /// Signals that are not marked were filtered out and cannot be loaded.
+    mask: Option<Vec<bool>>,
+    /// Value change blocks, only known if we can open additional readers.
+    sections: Vec<DataSection>,
+}
+
+/// Opens an additional reader in order to load signals in parallel.
+fn open_reader(bytes: &SharedBytes) -> Result<FstReader<impl BufRead + Seek>> {
+    FstReader::open(Cursor::new(bytes.clone())).map_err(convert_error)
 }
 
 impl<R: BufRead + Seek> FstWaveDatabase<R> {
-    fn new(reader: FstReader<R>) -> Self {
-        let time_table = reader.get_time_table().unwrap().to_vec();
-        FstWaveDatabase { reader, time_table }
+    fn new(
+        reader: FstReader<R>,
+        bytes: Option<SharedBytes>,
+        mask: Option<Vec<bool>>,
+    ) -> Result<Self> {
+        let time_table: Arc<[Time]> = reader
+            .get_time_table()
+            .ok_or_else(|| WellenError::FstParse("failed to read time table".to_string()))?
+            .into();
+        let sections = match &bytes {
+            Some(bytes) => find_data_sections(bytes.as_ref(), time_table.len()),
+            None => vec![],
+        };
+        Ok(FstWaveDatabase {
+            reader: Mutex::new(reader),
+            sections,
+            bytes,
+            time_table,
+            mask,
+        })
+    }
+
+    fn load_signals_internal(
+        &self,
+        ids: &[SignalRef],
+        types: &[SignalType],
+        multi_threaded: bool,
+    ) -> Result<Vec<Signal>> {
+        let time_table = &self.time_table;
+        let groups = split_sections(&self.sections, rayon::current_num_threads());
+        let bytes = match &self.bytes {
+            Some(bytes) if multi_threaded && (groups.len() > 1 || ids.len() > 1) => bytes,
+            // if another thread is using the main reader, we try to open our own
+            bytes => {
+                return match (self.reader.try_lock().ok(), bytes) {
+                    (Some(mut reader), _) => read_signals(&mut reader, time_table, ids, types),
+                    (None, Some(bytes)) => {
+                        read_signals(&mut open_reader(bytes)?, time_table, ids, types)
+                    }
+                    (None, None) => {
+                        let mut reader = self.reader.lock().unwrap();
+                        read_signals(&mut reader, time_table, ids, types)
+                    }
+                }
+            }
+        };
+
+        if groups.len() <= 1 {
+            // every thread decodes the only value change block for a subset of the signals
+            let chunk_len = ids.len().div_ceil(rayon::current_num_threads());
+            let chunks = ids
+                .chunks(chunk_len)
+                .zip(types.chunks(chunk_len))
+                .collect::<Vec<_>>();
+            let signals = chunks
+                .into_par_iter()
+                .map(|(ids, types)| read_signals(&mut open_reader(bytes)?, time_table, ids, types))
+                .collect::<Result<Vec<_>>>()?;
+            return Ok(signals.into_iter().flatten().collect());
+        }
+
+        // every thread decodes a group of consecutive value change blocks for all signals
+        let parts = groups
+            .into_par_iter()
+            .map(|sections| {
+                let (first, last) = (sections.first().unwrap(), sections.last().unwrap());
+                let filter = FstFilter::new(first.start_time, last.end_time, to_fst_handles(ids));
+                let mut reader = open_reader(bytes)?;
+                let first_time_idx = first.first_time_idx;
+                read_signal_changes(&mut reader, time_table, ids, types, &filter, first_time_idx)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        // combine the changes of every signal
+        let mut parts = parts.into_iter();
+        let mut signals = parts.next().unwrap();
+        for part in parts {
+            for (signal, other) in signals.iter_mut().zip(part) {
+                signal.append(other);
+            }
+        }
+        Ok(signals.into_iter().map(|w| w.finish()).collect())
     }
 }
 
+/// Time range and position in the time table of a value change block.
+#[derive(Debug, Clone, Copy)]
+struct DataSection {
+    start_time: Time,
+    end_time: Time,
+    /// Index of the first time step of the block in the time table.
+    first_time_idx: usize,
+    /// Size in bytes, used to distribute the blocks over several threads.
+    len: u64,
+}
+
+/// Finds all value change blocks by walking over the block headers. Returns an empty list
+/// if the file does not look like we expect it to, e.g., because it is truncated.
+fn find_data_sections(bytes: &[u8], time_table_len: usize) -> Vec<DataSection> {
+    let read_u64 = |pos: usize| {
+        let b = bytes.get(pos..pos.checked_add(8)?)?;
+        Some(u64::from_be_bytes(b.try_into().unwrap()))
+    };
+    let find = || {
+        let mut sections = Vec::new();
+        let mut num_time_steps = 0usize;
+        let mut pos = 0usize;
+        while pos < bytes.len() {
+            let block_tpe = bytes[pos];
+            let section_length = read_u64(pos + 1)?;
+            // every section starts with its length
+            if section_length < 8 {
+                return None;
+            }
+            let end = pos.checked_add(1)?.checked_add(section_length as usize)?;
+            if matches!(
+                block_tpe,
+                BLOCK_VC_DATA | BLOCK_VC_DATA_DYNAMIC_ALIAS | BLOCK_VC_DATA_DYNAMIC_ALIAS2
+            ) {
+                sections.push(DataSection {
+                    start_time: read_u64(pos + 9)?,
+                    end_time: read_u64(pos + 17)?,
+                    first_time_idx: num_time_steps,
+                    len: section_length,
+                });
+                // the number of time steps is stored in the last 8 bytes of the section
+                num_time_steps += read_u64(end.checked_sub(8)?)? as usize;
+            }
+            pos = end;
+        }
+        // the time table might start with the start time of the first block
+        let offset = time_table_len.checked_sub(num_time_steps)?;
+        if offset > 1 {
+            return None;
+        }
+        sections.iter_mut().for_each(|s| s.first_time_idx += offset);
+        // the start time belongs to the first block
+        if let Some(first) = sections.first_mut() {
+            first.first_time_idx = 0;
+        }
+        Some(sections)
+    };
+    find().unwrap_or_default()
+}
+
+/// Splits the value change blocks into up to `n` groups of a similar size. A group never
+/// starts with a block that shares a time step with the previous block.
+fn split_sections(sections: &[DataSection], n: usize) -> Vec<&[DataSection]> {
+    let total: u64 = sections.iter().map(|s| s.len).sum();
+    let target = total.div_ceil(n.max(1) as u64);
+    let mut groups = Vec::with_capacity(n);
+    let (mut start, mut size) = (0, 0);
+    for (ii, section) in sections.iter().enumerate() {
+        size += section.len;
+        let next = sections.get(ii + 1);
+        if size >= target && next.is_some_and(|next| next.start_time > section.end_time) {
+            groups.push(&sections[start..=ii]);
+            (start, size) = (ii + 1, 0);
+        }
+    }
+    if start < sections.len() {
+        groups.push(&sections[start..]);
+    }
+    groups
+}
+
 impl<R: BufRead + Seek> SignalSource for FstWaveDatabase<R> {
     fn load_signals(
-        &mut self,
+        &self,
         ids: &[SignalRef],
         types: &[SignalType],
-        _multi_threaded: bool,
-    ) -> Vec<Signal> {
-        // create a FST filter
-        let fst_ids = ids
-            .iter()
-            .zip(types.iter())
-            .map(|(ii, _)| FstSignalHandle::from_index(ii.index()))
-            .collect::<Vec<_>>();
-        let filter = FstFilter::filter_signals(fst_ids);
-
-        // lookup data structure for time table indices
-        let mut time_table = self.time_table.iter().enumerate();
-        let mut index_and_time = time_table.next().unwrap();
+        multi_threaded: bool,
+    ) -> Result<Vec<Signal>> {
+        self.load_signals_internal(ids, types, multi_threaded)
+    }
 
-        // store signals
-        let mut signals = ids
-            .iter()
-            .zip(types.iter())
-            .map(|(id, tpe)| SignalWriter::new(*id, *tpe))
-            .collect::<Vec<_>>();
-        let idx_to_pos: HashMap<usize, usize> = HashMap::from_iter(
-            ids.iter()
-                .zip(types.iter())
-                .map(|(r, _)| r.index())
-                .enumerate()
-                .map(|(pos, idx)| (idx, pos)),
-        );
-        let foo = |time: u64, handle: FstSignalHandle, value: FstSignalValue| {
-            // determine time index
-            while *(index_and_time.1) < time {
-                index_and_time = time_table.next().unwrap();
-            }
-            let time_idx = index_and_time.0 as TimeTableIdx;
-            debug_assert_eq!(*index_and_time.1, time);
-            let signal_pos = idx_to_pos[&handle.get_index()];
-            signals[signal_pos].add_change(time_idx, handle, value);
-        };
+    fn signal_mask(&self) -> Option<&[bool]> {
+        self.mask.as_deref()
+    }
 
-        self.reader.read_signals(&filter, foo).unwrap();
-        signals.into_iter().map(|w| w.finish()).collect()
+    fn append(&mut self, _other: crate::wavemem::Reader) -> Result<()> {
+        Err(WellenError::UnsupportedFeature(
+            FileFormat::Fst,
+            "appending value changes".to_string(),
+        ))
     }
 
-    fn get_time_table(&self) -> Vec<Time> {
+    fn get_time_table(&self) -> Arc<[Time]> {
         self.time_table.clone()
     }
 
@@ -96,6 +323,94 @@ impl<R: BufRead + Seek> SignalSource for FstWaveDatabase<R> {
     }
 }
 
+fn read_signals(
+    reader: &mut FstReader<impl BufRead + Seek>,
+    time_table: &[u64],
+    ids: &[SignalRef],
+    types: &[SignalType],
+) -> Result<Vec<Signal>> {
+    let filter = FstFilter::filter_signals(to_fst_handles(ids));
+    let signals = read_signal_changes(reader, time_table, ids, types, &filter, 0)?;
+    Ok(signals.into_iter().map(|w| w.finish()).collect())
+}
+
+fn to_fst_handles(ids: &[SignalRef]) -> Vec<FstSignalHandle> {
+    ids.iter()
+        .map(|ii| FstSignalHandle::from_index(ii.index()))
+        .collect()
+}
+
+/// Reads the value changes selected by the `filter`. Changes that happen before
+/// `first_time_idx` are ignored, since they were already read as part of earlier blocks.
+fn read_signal_changes(
+    reader: &mut FstReader<impl BufRead + Seek>,
+    time_table: &[u64],
+    ids: &[SignalRef],
+    types: &[SignalType],
+    filter: &FstFilter,
+    first_time_idx: usize,
+) -> Result<Vec<SignalWriter>> {
+    // the frame at the start of a block contains values that we already know
+    let skip_before = match first_time_idx {
+        0 => 0,
+        idx => time_table[idx],
+    };
+
+    // lookup data structure for time table indices
+    let mut time_table = time_table.iter().enumerate().skip(first_time_idx);
+    let mut index_and_time = time_table.next();
+
+    // store signals
+    let mut signals = ids
+        .iter()
+        .zip(types.iter())
+        .map(|(id, tpe)| SignalWriter::new(*id, *tpe))
+        .collect::<Vec<_>>();
+    let idx_to_pos: HashMap<usize, usize> = HashMap::from_iter(
+        ids.iter()
+            .zip(types.iter())
+            .map(|(r, _)| r.index())
+            .enumerate()
+            .map(|(pos, idx)| (idx, pos)),
+    );
+    // the callback cannot return an error, thus we remember the first one
+    let mut error = None;
+    let on_value_change = |time: u64, handle: FstSignalHandle, value: FstSignalValue| {
+        if error.is_some() || time < skip_before {
+            return;
+        }
+        // determine time index
+        while index_and_time.is_some_and(|(_, t)| *t < time) {
+            index_and_time = time_table.next();
+        }
+        let Some((time_idx, _)) = index_and_time else {
+            error = Some(WellenError::FstParse(format!(
+                "value change at time {time} is not part of the time table"
+            )));
+            return;
+        };
+        debug_assert_eq!(*index_and_time.unwrap().1, time);
+        let Some(signal_pos) = idx_to_pos.get(&handle.get_index()) else {
+            error = Some(WellenError::FstParse(format!(
+                "unexpected value change for signal {}",
+                handle.get_index()
+            )));
+            return;
+        };
+        if let Err(e) = signals[*signal_pos].add_change(time_idx as TimeTableIdx, handle, value) {
+            error = Some(e);
+        }
+    };
+
+    reader
+        .read_signals(filter, on_value_change)
+        .map_err(convert_error)?;
+    if let Some(e) = error {
+        return Err(e);
+    }
+    Ok(signals)
+}
+
 struct SignalWriter {
     tpe: SignalType,
     id: SignalRef,
@@ -125,7 +440,7 @@ impl SignalWriter {
         time_idx: TimeTableIdx,
         handle: FstSignalHandle,
         value: FstSignalValue,
-    ) {
+    ) -> Result<()> {
         debug_assert_eq!(handle, self.handle);
         if let Some(prev_idx) = self.time_indices.last() {
             debug_assert!(*prev_idx <= time_idx);
@@ -147,13 +462,18 @@ impl SignalWriter {
                 }
                 SignalType::BitVector(len, _) => {
                     let bits = len.get();
-                    debug_assert_eq!(value.len(), bits as usize);
-                    let local_encoding = check_states(value).unwrap_or_else(|| {
-                        panic!(
-                            "Unexpected signal value: {}",
+                    if value.len() != bits as usize {
+                        return Err(WellenError::FstParse(format!(
+                            "expected a value of {bits} bits, got: {}",
                             String::from_utf8_lossy(value)
-                        )
-                    });
+                        )));
+                    }
+                    let local_encoding = check_states(value).ok_or_else(|| {
+                        WellenError::FstParse(format!(
+                            "unexpected signal value: {}",
+                            String::from_utf8_lossy(value)
+                        ))
+                    })?;
 
                     let signal_states = States::join(self.max_states, local_encoding);
                     if signal_states != self.max_states {
@@ -205,19 +525,80 @@ impl SignalWriter {
                         self.time_indices.push(time_idx);
                     }
                 }
-                SignalType::Real => panic!(
-                    "Expecting reals, but go: {}",
-                    String::from_utf8_lossy(value)
-                ),
+                SignalType::Real => {
+                    return Err(WellenError::FstParse(format!(
+                        "expecting a real, but got: {}",
+                        String::from_utf8_lossy(value)
+                    )))
+                }
             },
             FstSignalValue::Real(value) => {
-                debug_assert_eq!(self.tpe, SignalType::Real);
+                if self.tpe != SignalType::Real {
+                    return Err(WellenError::FstParse(format!(
+                        "unexpected real value {value} for signal of type {:?}",
+                        self.tpe
+                    )));
+                }
                 self.data_bytes.extend_from_slice(&value.to_le_bytes());
                 if check_if_changed_and_truncate(8, &mut self.data_bytes) {
                     self.time_indices.push(time_idx);
                 }
             }
         }
+        Ok(())
+    }
+
+    /// Appends the changes that were read from later blocks of the file.
+    fn append(&mut self, other: SignalWriter) {
+        debug_assert_eq!(self.id, other.id);
+        let other_len = other.time_indices.len();
+        // the first change of `other` might just repeat our last value
+        let skip_first = match self.tpe {
+            SignalType::String => {
+                let is_same =
+                    self.strings.last().is_some() && self.strings.last() == other.strings.first();
+                self.strings
+                    .extend(other.strings.into_iter().skip(is_same as usize));
+                is_same
+            }
+            SignalType::Real => self.append_entries(&other.data_bytes, other_len, 8),
+            SignalType::BitVector(len, _) => {
+                let bits = len.get();
+                let states = States::join(self.max_states, other.max_states);
+                let expand = |from: States, data: Vec<u8>, entries: usize| {
+                    if from == states {
+                        data
+                    } else {
+                        expand_entries(from, states, &data, entries, bits)
+                    }
+                };
+                let entries = self.time_indices.len();
+                self.data_bytes = expand(
+                    self.max_states,
+                    std::mem::take(&mut self.data_bytes),
+                    entries,
+                );
+                self.max_states = states;
+                let other_data = expand(other.max_states, other.data_bytes, other_len);
+                let (len, has_meta) = get_len_and_meta(states, bits);
+                self.append_entries(&other_data, other_len, get_bytes_per_entry(len, has_meta))
+            }
+        };
+        self.time_indices
+            .extend(other.time_indices.into_iter().skip(skip_first as usize));
+    }
+
+    /// Appends fixed size entries and returns true if the first one was skipped because it
+    /// is the same as our last entry.
+    fn append_entries(&mut self, data: &[u8], entries: usize, bytes_per_entry: usize) -> bool {
+        debug_assert_eq!(data.len(), entries * bytes_per_entry);
+        let is_same = !self.data_bytes.is_empty()
+            && data.len() >= bytes_per_entry
+            && self.data_bytes[self.data_bytes.len() - bytes_per_entry..]
+                == data[..bytes_per_entry];
+        let start = if is_same { bytes_per_entry } else { 0 };
+        self.data_bytes.extend_from_slice(&data[start..]);
+        is_same
     }
 
     fn finish(self) -> Signal {
@@ -274,7 +655,13 @@ pub(crate) fn get_bytes_per_entry(len: usize, has_meta: bool) -> usize {
 
 const META_MASK: u8 = 3 << 6;
 
-fn expand_entries(from: States, to: States, old: &Vec<u8>, entries: usize, bits: u32) -> Vec<u8> {
+pub(crate) fn expand_entries(
+    from: States,
+    to: States,
+    old: &Vec<u8>,
+    entries: usize,
+    bits: u32,
+) -> Vec<u8> {
     let (from_len, from_meta) = get_len_and_meta(from, bits);
     let from_bytes_per_entry = get_bytes_per_entry(from_len, from_meta);
     let (to_len, to_meta) = get_len_and_meta(to, bits);
@@ -446,8 +833,8 @@ pub(crate) fn merge_vhdl_data_and_var_type(vcd: VarType, vhdl: FstVhdlDataType)
     }
 }
 
-fn convert_timescale(exponent: i8) -> Timescale {
-    if exponent >= 0 {
+fn convert_timescale(exponent: i8) -> Result<Timescale> {
+    let timescale = if exponent >= 0 {
         Timescale::new(10u32.pow(exponent as u32), TimescaleUnit::Seconds)
     } else if exponent >= -3 {
         Timescale::new(
@@ -472,8 +859,12 @@ fn convert_timescale(exponent: i8) -> Timescale {
             TimescaleUnit::FemtoSeconds,
         )
     } else {
-        panic!("Unexpected timescale exponent: {}", exponent);
-    }
+        return Err(WellenError::UnsupportedFeature(
+            FileFormat::Fst,
+            format!("timescale exponent {exponent}"),
+        ));
+    };
+    Ok(timescale)
 }
 
 #[derive(Debug)]
@@ -536,27 +927,31 @@ pub(crate) fn parse_scope_attributes(
     Ok((declaration_source, instance_source))
 }
 
-fn read_hierarchy<F: BufRead + Seek>(reader: &mut FstReader<F>) -> Hierarchy {
+fn read_hierarchy<F: BufRead + Seek>(
+    reader: &mut FstReader<F>,
+    options: &LoadOptions,
+) -> Result<Hierarchy> {
     let mut h = HierarchyBuilder::new(FileFormat::Fst);
     // load meta-data
     let fst_header = reader.get_header();
     h.set_version(fst_header.version.trim().to_string());
     h.set_date(fst_header.date.trim().to_string());
-    h.set_timescale(convert_timescale(fst_header.timescale_exponent));
+    h.set_timescale(convert_timescale(fst_header.timescale_exponent)?);
 
     let mut path_names = HashMap::new();
     let mut enums = HashMap::new();
     let mut attributes = Vec::new();
 
-    let cb = |entry: FstHierarchyEntry| {
+    let mut process_entry = |entry: FstHierarchyEntry| -> Result<()> {
         match entry {
             FstHierarchyEntry::Scope {
                 tpe,
                 name,
                 component,
             } => {
+                let flatten = options.remove_scopes_with_empty_name && name.is_empty();
                 let (declaration_source, instance_source) =
-                    parse_scope_attributes(&mut attributes, &mut h).unwrap();
+                    parse_scope_attributes(&mut attributes, &mut h)?;
                 let name_id = h.add_string(name);
                 let component_id = h.add_string(component);
                 h.add_scope(
@@ -565,7 +960,7 @@ fn read_hierarchy<F: BufRead + Seek>(reader: &mut FstReader<F>) -> Hierarchy {
                     convert_scope_tpe(tpe),
                     declaration_source,
                     instance_source,
-                    false,
+                    flatten,
                 );
             }
             FstHierarchyEntry::UpScope => h.pop_scope(),
@@ -585,7 +980,7 @@ fn read_hierarchy<F: BufRead + Seek>(reader: &mut FstReader<F>) -> Hierarchy {
                 };
 
                 let (type_name, var_type, enum_type) =
-                    parse_var_attributes(&mut attributes, convert_var_tpe(tpe), &var_name).unwrap();
+                    parse_var_attributes(&mut attributes, convert_var_tpe(tpe), &var_name)?;
                 let name_id = h.add_string(var_name);
                 let type_name = type_name.map(|s| h.add_string(s));
                 h.add_var(
@@ -608,7 +1003,9 @@ fn read_hierarchy<F: BufRead + Seek>(reader: &mut FstReader<F>) -> Hierarchy {
                 path_id,
                 line,
             } => {
-                let path = path_names[&path_id];
+                let path = *path_names.get(&path_id).ok_or_else(|| {
+                    WellenError::FstParse(format!("unknown source path id {path_id}"))
+                })?;
                 attributes.push(Attribute::SourceLoc(path, line, is_instantiation));
             }
             FstHierarchyEntry::Comment { .. } => {} // ignored
@@ -627,7 +1024,10 @@ fn read_hierarchy<F: BufRead + Seek>(reader: &mut FstReader<F>) -> Hierarchy {
                 enums.insert(handle, enum_ref);
             }
             FstHierarchyEntry::EnumTableRef { handle } => {
-                attributes.push(Attribute::Enum(enums[&handle]));
+                let enum_ref = *enums.get(&handle).ok_or_else(|| {
+                    WellenError::FstParse(format!("unknown enum table handle {handle}"))
+                })?;
+                attributes.push(Attribute::Enum(enum_ref));
             }
             FstHierarchyEntry::VhdlVarInfo {
                 type_name,
@@ -636,9 +1036,771 @@ fn read_hierarchy<F: BufRead + Seek>(reader: &mut FstReader<F>) -> Hierarchy {
             } => {
                 attributes.push(Attribute::VhdlTypeInfo(type_name, var_type, data_type));
             }
-            FstHierarchyEntry::AttributeEnd => todo!("{entry:?}"),
+            FstHierarchyEntry::AttributeEnd => {
+                return Err(WellenError::UnsupportedFeature(
+                    FileFormat::Fst,
+                    format!("{entry:?}"),
+                ))
+            }
+        };
+        Ok(())
+    };
+
+    // the callback cannot return an error, thus we remember the first one
+    let mut error = None;
+    let cb = |entry: FstHierarchyEntry| {
+        if error.is_none() {
+            error = process_entry(entry).err();
+        }
+    };
+    reader.read_hierarchy(cb).map_err(convert_error)?;
+    if let Some(e) = error {
+        return Err(e);
+    }
+    Ok(h.finish())
+}
+
+/// Options for the FST writer.
+#[derive(Debug, Copy, Clone)]
+pub struct WriteOptions {
+    /// Indicates that variables whose signal has not been loaded should be left out of the
+    /// output. By default, we return an error if a signal is missing.
+    pub skip_unloaded_signals: bool,
+    /// Value changes are split into independently compressed blocks. A new block is started
+    /// once the current one contains at least this many changes.
+    pub max_changes_per_block: usize,
+}
+
+impl Default for WriteOptions {
+    fn default() -> Self {
+        Self {
+            skip_unloaded_signals: false,
+            max_changes_per_block: 1 << 20,
+        }
+    }
+}
+
+// block types
+const BLOCK_HEADER: u8 = 0;
+const BLOCK_VC_DATA: u8 = 1;
+const BLOCK_GEOMETRY: u8 = 3;
+const BLOCK_VC_DATA_DYNAMIC_ALIAS: u8 = 5;
+const BLOCK_HIERARCHY_LZ4: u8 = 6;
+const BLOCK_VC_DATA_DYNAMIC_ALIAS2: u8 = 8;
+// hierarchy entries
+const HIERARCHY_SCOPE: u8 = 254;
+const HIERARCHY_UP_SCOPE: u8 = 255;
+const HIERARCHY_ATTRIBUTE_BEGIN: u8 = 252;
+const ATTRIBUTE_MISC: u8 = 0;
+const MISC_SUP_VAR: u8 = 2;
+const MISC_PATH_NAME: u8 = 3;
+const MISC_SOURCE_STEM: u8 = 4;
+const MISC_SOURCE_INSTANTIATION_STEM: u8 = 5;
+const MISC_ENUM_TABLE: u8 = 7;
+// header
+const HEADER_LENGTH: u64 = 329;
+const HEADER_VERSION_MAX_LEN: usize = 128;
+const HEADER_DATE_MAX_LEN: usize = 119;
+const DOUBLE_ENDIAN_TEST: f64 = std::f64::consts::E;
+const FILE_TYPE_VERILOG: u8 = 0;
+const FILE_TYPE_VHDL: u8 = 1;
+/// Value changes are packed with LZ4.
+const PACK_TYPE_LZ4: u8 = b'4';
+/// Time deltas inside a block need to fit into a 32-bit varint together with up to 4 bits of
+/// value information.
+const MAX_TIME_STEPS_PER_BLOCK: usize = 1 << 24;
+/// Encoding of 1-bit values that are not `0` or `1`.
+const ONE_BIT_NON_BINARY_VALUES: [u8; 8] = [b'x', b'z', b'h', b'u', b'w', b'l', b'-', b'?'];
+
+/// How the values of a signal are stored in the FST.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FstSignalKind {
+    BitVector(u32),
+    Real,
+    String,
+}
+
+impl FstSignalKind {
+    fn from_var(var: &Var) -> Self {
+        if var.is_real() {
+            FstSignalKind::Real
+        } else if var.is_string() {
+            FstSignalKind::String
+        } else {
+            FstSignalKind::BitVector(var.length().unwrap())
+        }
+    }
+
+    /// Encoding used in the geometry block.
+    fn to_geometry(self) -> u32 {
+        match self {
+            FstSignalKind::BitVector(len) => len,
+            FstSignalKind::Real => 0,
+            FstSignalKind::String => u32::MAX,
+        }
+    }
+
+    /// Value of the signal before it is first assigned.
+    fn initial_frame_value(self) -> Vec<u8> {
+        match self {
+            FstSignalKind::BitVector(len) => vec![b'x'; len as usize],
+            FstSignalKind::Real => 0.0f64.to_le_bytes().to_vec(),
+            FstSignalKind::String => vec![],
+        }
+    }
+}
+
+/// Serializes the hierarchy and all loaded signals of a waveform to FST.
+/// Signals need to be loaded with `Waveform::load_signals` before calling this function.
+pub fn write(waveform: &Waveform, output: impl Write, options: WriteOptions) -> Result<()> {
+    let hierarchy = waveform.hierarchy();
+    let mut out = std::io::BufWriter::new(output);
+
+    // the hierarchy determines the handle of every signal
+    let mut hw = HierarchyWriter {
+        waveform,
+        options,
+        bytes: Vec::new(),
+        handles: HashMap::new(),
+        signals: Vec::new(),
+        path_ids: HashMap::new(),
+        enum_ids: HashMap::new(),
+        scope_count: 0,
+        var_count: 0,
+    };
+    for item in hierarchy.items() {
+        hw.write_item(item)?;
+    }
+
+    let time_table = waveform.time_table();
+    let blocks = split_into_blocks(time_table.len(), &hw.signals, options);
+
+    // header
+    let start_time = time_table.first().copied().unwrap_or(0);
+    let end_time = time_table.last().copied().unwrap_or(0);
+    let file_type = match hierarchy.file_format() {
+        FileFormat::Ghw => FILE_TYPE_VHDL,
+        _ => FILE_TYPE_VERILOG,
+    };
+    out.write_all(&[BLOCK_HEADER])?;
+    out.write_all(&HEADER_LENGTH.to_be_bytes())?;
+    out.write_all(&start_time.to_be_bytes())?;
+    out.write_all(&end_time.to_be_bytes())?;
+    out.write_all(&DOUBLE_ENDIAN_TEST.to_le_bytes())?;
+    out.write_all(&0u64.to_be_bytes())?; // memory used by writer
+    out.write_all(&hw.scope_count.to_be_bytes())?;
+    out.write_all(&hw.var_count.to_be_bytes())?;
+    out.write_all(&(hw.signals.len() as u64).to_be_bytes())?;
+    out.write_all(&(blocks.len() as u64).to_be_bytes())?;
+    out.write_all(&timescale_to_exponent(hierarchy.timescale())?.to_be_bytes())?;
+    write_fixed_len_str(&mut out, hierarchy.version(), HEADER_VERSION_MAX_LEN)?;
+    write_fixed_len_str(&mut out, hierarchy.date(), HEADER_DATE_MAX_LEN)?;
+    out.write_all(&[file_type])?;
+    out.write_all(&0u64.to_be_bytes())?; // time zero
+
+    // value changes
+    let mut changes: Vec<_> = hw
+        .signals
+        .iter()
+        .map(|(signal, _)| signal.iter_changes().peekable())
+        .collect();
+    let mut frame: Vec<Vec<u8>> = hw
+        .signals
+        .iter()
+        .map(|(_, kind)| kind.initial_frame_value())
+        .collect();
+    let mut block_start = 0;
+    for block_end in blocks {
+        let mut signal_data = Vec::with_capacity(hw.signals.len());
+        // the frame contains the value of every signal at the start of the block
+        let frame_bytes = frame.concat();
+        for (handle, iter) in changes.iter_mut().enumerate() {
+            let kind = hw.signals[handle].1;
+            let mut data = Vec::new();
+            let mut prev_idx = block_start;
+            while let Some((time_idx, value)) =
+                iter.next_if(|(time_idx, _)| (*time_idx as usize) < block_end)
+            {
+                let time_idx = time_idx as usize;
+                let value = encode_value_change(&mut data, time_idx - prev_idx, value, kind)?;
+                prev_idx = time_idx;
+                if kind != FstSignalKind::String {
+                    frame[handle] = value;
+                }
+            }
+            signal_data.push(data);
+        }
+        let block = encode_value_change_block(
+            &time_table[block_start..block_end],
+            &frame_bytes,
+            &signal_data,
+        )?;
+        out.write_all(&[BLOCK_VC_DATA_DYNAMIC_ALIAS])?;
+        out.write_all(&block)?;
+        block_start = block_end;
+    }
+
+    // geometry
+    let mut geometry = Vec::with_capacity(hw.signals.len() * 2);
+    for (_, kind) in hw.signals.iter() {
+        leb128::write::unsigned(&mut geometry, kind.to_geometry() as u64)?;
+    }
+    out.write_all(&[BLOCK_GEOMETRY])?;
+    out.write_all(&(geometry.len() as u64 + 3 * 8).to_be_bytes())?;
+    out.write_all(&(geometry.len() as u64).to_be_bytes())?;
+    out.write_all(&(hw.signals.len() as u64).to_be_bytes())?;
+    out.write_all(&geometry)?;
+
+    // hierarchy
+    let compressed = lz4_flex::compress(&hw.bytes);
+    out.write_all(&[BLOCK_HIERARCHY_LZ4])?;
+    out.write_all(&(compressed.len() as u64 + 2 * 8).to_be_bytes())?;
+    out.write_all(&(hw.bytes.len() as u64).to_be_bytes())?;
+    out.write_all(&compressed)?;
+
+    out.flush()?;
+    Ok(())
+}
+
+/// Enum name and (value, name) pairs.
+type EnumTableKey<'a> = (&'a str, Vec<(&'a str, &'a str)>);
+
+struct HierarchyWriter<'a> {
+    waveform: &'a Waveform,
+    options: WriteOptions,
+    /// uncompressed hierarchy block
+    bytes: Vec<u8>,
+    /// handle index of every signal that has been declared
+    handles: HashMap<SignalRef, u32>,
+    /// signals in the order of their handles
+    signals: Vec<(&'a Signal, FstSignalKind)>,
+    path_ids: HashMap<&'a str, u64>,
+    enum_ids: HashMap<EnumTableKey<'a>, u64>,
+    scope_count: u64,
+    var_count: u64,
+}
+
+impl<'a> HierarchyWriter<'a> {
+    fn write_item(&mut self, item: HierarchyItem<'a>) -> Result<()> {
+        let hierarchy = self.waveform.hierarchy();
+        match item {
+            HierarchyItem::Scope(scope) => {
+                if let Some((path, line)) = scope.source_loc(hierarchy) {
+                    self.write_source_stem(path, line, false)?;
+                }
+                if let Some((path, line)) = scope.instantiation_source_loc(hierarchy) {
+                    self.write_source_stem(path, line, true)?;
+                }
+                self.bytes.push(HIERARCHY_SCOPE);
+                self.bytes.push(scope_tpe_to_fst(scope.scope_type()) as u8);
+                write_c_str(&mut self.bytes, scope.name(hierarchy));
+                write_c_str(&mut self.bytes, scope.component(hierarchy).unwrap_or(""));
+                self.scope_count += 1;
+                for child in scope.items(hierarchy) {
+                    self.write_item(child)?;
+                }
+                self.bytes.push(HIERARCHY_UP_SCOPE);
+            }
+            HierarchyItem::Var(var) => {
+                let signal_ref = var.signal_ref();
+                let alias = match self.handles.get(&signal_ref) {
+                    Some(index) => *index + 1,
+                    None => match self.waveform.get_signal(signal_ref) {
+                        Some(signal) => {
+                            self.handles.insert(signal_ref, self.signals.len() as u32);
+                            self.signals.push((signal, FstSignalKind::from_var(var)));
+                            0 // a new handle is implied by not being an alias
+                        }
+                        None if self.options.skip_unloaded_signals => return Ok(()),
+                        None => return Err(WellenError::SignalNotLoaded(var.full_name(hierarchy))),
+                    },
+                };
+                self.write_var_attributes(var)?;
+                let kind = FstSignalKind::from_var(var);
+                let (tpe, length) = match kind {
+                    FstSignalKind::Real => (FstVarType::Real, 8),
+                    FstSignalKind::String => (FstVarType::GenericString, 0),
+                    FstSignalKind::BitVector(len) => (var_tpe_to_fst(var.var_type()), len),
+                };
+                let name = match var.index() {
+                    Some(index) if index.msb() == index.lsb() => {
+                        format!("{} [{}]", var.name(hierarchy), index.lsb())
+                    }
+                    Some(index) => {
+                        format!("{} [{}:{}]", var.name(hierarchy), index.msb(), index.lsb())
+                    }
+                    None => var.name(hierarchy).to_string(),
+                };
+                self.bytes.push(tpe as u8);
+                self.bytes.push(var_direction_to_fst(var.direction()) as u8);
+                write_c_str(&mut self.bytes, &name);
+                let raw_length = if tpe == FstVarType::Port {
+                    3 * length + 2
+                } else {
+                    length
+                };
+                leb128::write::unsigned(&mut self.bytes, raw_length as u64)?;
+                leb128::write::unsigned(&mut self.bytes, alias as u64)?;
+                self.var_count += 1;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_var_attributes(&mut self, var: &Var) -> Result<()> {
+        let hierarchy = self.waveform.hierarchy();
+        // VHDL type information, the same way GHDL encodes it
+        let vhdl_data_type = var_tpe_to_vhdl_data_type(var.var_type());
+        if var.vhdl_type_name(hierarchy).is_some() || vhdl_data_type.is_some() {
+            let type_name = var
+                .vhdl_type_name(hierarchy)
+                .unwrap_or_else(|| default_vhdl_type_name(var.var_type()));
+            let data_type = vhdl_data_type.unwrap_or(FstVhdlDataType::None);
+            let arg = ((FstVhdlVarType::Signal as u64) << FST_SUP_VAR_DATA_TYPE_BITS)
+                | (data_type as u64);
+            self.write_attribute(MISC_SUP_VAR, type_name.as_bytes(), arg)?;
+        }
+        if let Some((name, mapping)) = var.enum_type(hierarchy) {
+            let next_id = self.enum_ids.len() as u64 + 1;
+            let id = *self
+                .enum_ids
+                .entry((name, mapping.clone()))
+                .or_insert(next_id);
+            if id == next_id {
+                // the table is declared before its first use
+                let mut table = format!("{name} {}", mapping.len());
+                for (_, name) in mapping.iter() {
+                    table.push(' ');
+                    table.push_str(name);
+                }
+                for (value, _) in mapping.iter() {
+                    table.push(' ');
+                    table.push_str(value);
+                }
+                self.write_attribute(MISC_ENUM_TABLE, table.as_bytes(), id)?;
+            }
+            self.write_attribute(MISC_ENUM_TABLE, &[], id)?;
+        }
+        Ok(())
+    }
+
+    fn write_source_stem(
+        &mut self,
+        path: &'a str,
+        line: u64,
+        is_instantiation: bool,
+    ) -> Result<()> {
+        let path_id = match self.path_ids.get(path) {
+            Some(id) => *id,
+            None => {
+                let id = self.path_ids.len() as u64 + 1;
+                self.path_ids.insert(path, id);
+                self.write_attribute(MISC_PATH_NAME, path.as_bytes(), id)?;
+                id
+            }
+        };
+        let subtype = if is_instantiation {
+            MISC_SOURCE_INSTANTIATION_STEM
+        } else {
+            MISC_SOURCE_STEM
         };
+        // the path id is encoded as a varint in place of the attribute name
+        let mut path_id_bytes = Vec::with_capacity(4);
+        leb128::write::unsigned(&mut path_id_bytes, path_id)?;
+        self.write_attribute(subtype, &path_id_bytes, line)
+    }
+
+    fn write_attribute(&mut self, subtype: u8, name: &[u8], arg: u64) -> Result<()> {
+        self.bytes.push(HIERARCHY_ATTRIBUTE_BEGIN);
+        self.bytes.push(ATTRIBUTE_MISC);
+        self.bytes.push(subtype);
+        self.bytes.extend_from_slice(name);
+        self.bytes.push(0);
+        leb128::write::unsigned(&mut self.bytes, arg)?;
+        Ok(())
+    }
+}
+
+/// Returns the exclusive end time index of every value change block.
+fn split_into_blocks(
+    time_steps: usize,
+    signals: &[(&Signal, FstSignalKind)],
+    options: WriteOptions,
+) -> Vec<usize> {
+    let mut changes_per_step = vec![0usize; time_steps];
+    for (signal, _) in signals.iter() {
+        for (time_idx, _) in signal.iter_changes() {
+            changes_per_step[time_idx as usize] += 1;
+        }
+    }
+    let mut blocks = Vec::new();
+    let mut block_start = 0;
+    let mut block_changes = 0;
+    for (time_idx, changes) in changes_per_step.into_iter().enumerate() {
+        block_changes += changes;
+        let block_steps = time_idx + 1 - block_start;
+        if block_changes >= options.max_changes_per_block || block_steps >= MAX_TIME_STEPS_PER_BLOCK
+        {
+            blocks.push(time_idx + 1);
+            block_start = time_idx + 1;
+            block_changes = 0;
+        }
+    }
+    if block_start < time_steps {
+        blocks.push(time_steps);
+    }
+    blocks
+}
+
+/// Appends a single value change to the data of a signal and returns the value in the
+/// representation used by the frame.
+fn encode_value_change(
+    data: &mut Vec<u8>,
+    time_delta: usize,
+    value: SignalValue,
+    kind: FstSignalKind,
+) -> Result<Vec<u8>> {
+    let matches_kind = match value {
+        SignalValue::String(_) => kind == FstSignalKind::String,
+        SignalValue::Real(_) => kind == FstSignalKind::Real,
+        SignalValue::Binary(_, bits)
+        | SignalValue::FourValue(_, bits)
+        | SignalValue::NineValue(_, bits) => kind == FstSignalKind::BitVector(bits),
+    };
+    if !matches_kind {
+        return Err(WellenError::UnsupportedFeature(
+            FileFormat::Fst,
+            format!("writing value {value} to a signal of type {kind:?}"),
+        ));
+    }
+    let time_delta = time_delta as u64;
+    let frame_value = match value {
+        SignalValue::String(value) => {
+            leb128::write::unsigned(data, time_delta << 1)?;
+            leb128::write::unsigned(data, value.len() as u64)?;
+            data.extend_from_slice(value.as_bytes());
+            vec![]
+        }
+        SignalValue::Real(value) => {
+            leb128::write::unsigned(data, (time_delta << 1) | 1)?;
+            data.extend_from_slice(&value.to_le_bytes());
+            value.to_le_bytes().to_vec()
+        }
+        other => {
+            let bits = other.to_bit_string().unwrap().into_bytes();
+            let is_binary = bits.iter().all(|b| *b == b'0' || *b == b'1');
+            if bits.len() == 1 {
+                let vli = if is_binary {
+                    (time_delta << 2) | (((bits[0] - b'0') as u64) << 1)
+                } else {
+                    let index = ONE_BIT_NON_BINARY_VALUES
+                        .iter()
+                        .position(|b| *b == bits[0])
+                        .unwrap_or(ONE_BIT_NON_BINARY_VALUES.len() - 1);
+                    (time_delta << 4) | ((index as u64) << 1) | 1
+                };
+                leb128::write::unsigned(data, vli)?;
+            } else if is_binary {
+                leb128::write::unsigned(data, time_delta << 1)?;
+                for chunk in bits.chunks(8) {
+                    let byte = chunk
+                        .iter()
+                        .enumerate()
+                        .fold(0u8, |byte, (ii, b)| byte | ((*b - b'0') << (7 - ii)));
+                    data.push(byte);
+                }
+            } else {
+                leb128::write::unsigned(data, (time_delta << 1) | 1)?;
+                data.extend_from_slice(&bits);
+            }
+            bits
+        }
+    };
+    Ok(frame_value)
+}
+
+/// Encodes a value change block, starting at the section length.
+fn encode_value_change_block(
+    times: &[Time],
+    frame: &[u8],
+    signal_data: &[Vec<u8>],
+) -> Result<Vec<u8>> {
+    let max_handle = signal_data.len() as u64;
+
+    // signal data followed by the chain table which contains the offset of every signal
+    let mut vc_data = vec![PACK_TYPE_LZ4];
+    let mut chain = Vec::new();
+    let mut memory_required = 0u64;
+    let mut prev_offset = 0;
+    let mut zeros = 0u64;
+    for data in signal_data.iter() {
+        if data.is_empty() {
+            zeros += 1;
+            continue;
+        }
+        if zeros > 0 {
+            leb128::write::unsigned(&mut chain, zeros << 1)?;
+            zeros = 0;
+        }
+        let offset = vc_data.len() as u64;
+        leb128::write::unsigned(&mut chain, ((offset - prev_offset) << 1) | 1)?;
+        prev_offset = offset;
+        memory_required += data.len() as u64;
+        let compressed = lz4_flex::compress(data);
+        if compressed.len() < data.len() {
+            leb128::write::unsigned(&mut vc_data, data.len() as u64)?;
+            vc_data.extend_from_slice(&compressed);
+        } else {
+            // a length of zero indicates uncompressed data
+            vc_data.push(0);
+            vc_data.extend_from_slice(data);
+        }
+    }
+    if zeros > 0 {
+        leb128::write::unsigned(&mut chain, zeros << 1)?;
+    }
+
+    // time table deltas, stored uncompressed
+    let mut time_chain = Vec::with_capacity(times.len() * 2);
+    let mut prev_time = 0;
+    for time in times.iter() {
+        leb128::write::unsigned(&mut time_chain, *time - prev_time)?;
+        prev_time = *time;
+    }
+
+    let mut out = vec![0u8; 8]; // section length is filled in at the end
+    out.extend_from_slice(&times[0].to_be_bytes());
+    out.extend_from_slice(&times[times.len() - 1].to_be_bytes());
+    out.extend_from_slice(&memory_required.to_be_bytes());
+    // uncompressed frame
+    leb128::write::unsigned(&mut out, frame.len() as u64)?;
+    leb128::write::unsigned(&mut out, frame.len() as u64)?;
+    leb128::write::unsigned(&mut out, max_handle)?;
+    out.extend_from_slice(frame);
+    leb128::write::unsigned(&mut out, max_handle)?;
+    out.extend_from_slice(&vc_data);
+    out.extend_from_slice(&chain);
+    out.extend_from_slice(&(chain.len() as u64).to_be_bytes());
+    out.extend_from_slice(&time_chain);
+    out.extend_from_slice(&(time_chain.len() as u64).to_be_bytes());
+    out.extend_from_slice(&(time_chain.len() as u64).to_be_bytes());
+    out.extend_from_slice(&(times.len() as u64).to_be_bytes());
+    let section_length = out.len() as u64;
+    out[0..8].copy_from_slice(&section_length.to_be_bytes());
+    Ok(out)
+}
+
+fn write_c_str(out: &mut Vec<u8>, value: &str) {
+    out.extend_from_slice(value.as_bytes());
+    out.push(0);
+}
+
+/// Writes a zero padded string, truncating it if necessary.
+fn write_fixed_len_str(out: &mut impl Write, value: &str, len: usize) -> Result<()> {
+    let mut bytes = value.as_bytes().to_vec();
+    bytes.truncate(len - 1);
+    bytes.resize(len, 0);
+    out.write_all(&bytes)?;
+    Ok(())
+}
+
+fn timescale_to_exponent(timescale: Option<Timescale>) -> Result<i8> {
+    let Some(timescale) = timescale else {
+        return Ok(0);
+    };
+    let unsupported = || {
+        WellenError::UnsupportedFeature(
+            FileFormat::Fst,
+            format!("timescale {} {:?}", timescale.factor, timescale.unit),
+        )
     };
-    reader.read_hierarchy(cb).unwrap();
-    h.finish()
+    let mut exponent = timescale.unit.to_exponent().ok_or_else(unsupported)?;
+    let mut factor = timescale.factor;
+    while factor >= 10 && factor % 10 == 0 {
+        factor /= 10;
+        exponent += 1;
+    }
+    if factor != 1 {
+        return Err(unsupported());
+    }
+    Ok(exponent)
+}
+
+fn scope_tpe_to_fst(tpe: ScopeType) -> FstScopeType {
+    match tpe {
+        ScopeType::Module => FstScopeType::Module,
+        ScopeType::Task => FstScopeType::Task,
+        ScopeType::Function => FstScopeType::Function,
+        ScopeType::Begin => FstScopeType::Begin,
+        ScopeType::Fork => FstScopeType::Fork,
+        ScopeType::Generate => FstScopeType::Generate,
+        ScopeType::Struct => FstScopeType::Struct,
+        ScopeType::Union => FstScopeType::Union,
+        ScopeType::Class => FstScopeType::Class,
+        ScopeType::Interface => FstScopeType::Interface,
+        ScopeType::Package => FstScopeType::Package,
+        ScopeType::Program => FstScopeType::Program,
+        ScopeType::VhdlArchitecture => FstScopeType::VhdlArchitecture,
+        ScopeType::VhdlProcedure => FstScopeType::VhdlProcedure,
+        ScopeType::VhdlFunction => FstScopeType::VhdlFunction,
+        ScopeType::VhdlRecord => FstScopeType::VhdlRecord,
+        ScopeType::VhdlProcess => FstScopeType::VhdlProcess,
+        ScopeType::VhdlBlock => FstScopeType::VhdlBlock,
+        ScopeType::VhdlForGenerate => FstScopeType::VhdlForGenerate,
+        ScopeType::VhdlIfGenerate => FstScopeType::VhdlIfGenerate,
+        ScopeType::VhdlGenerate => FstScopeType::VhdlGenerate,
+        ScopeType::VhdlPackage => FstScopeType::VhdlPackage,
+        // there is no FST equivalent for these GHW scopes
+        ScopeType::GhwGeneric | ScopeType::VhdlArray => FstScopeType::Module,
+    }
+}
+
+fn var_tpe_to_fst(tpe: VarType) -> FstVarType {
+    match tpe {
+        VarType::Event => FstVarType::Event,
+        VarType::Integer => FstVarType::Integer,
+        VarType::Parameter => FstVarType::Parameter,
+        VarType::Real => FstVarType::Real,
+        VarType::Reg => FstVarType::Reg,
+        VarType::Supply0 => FstVarType::Supply0,
+        VarType::Supply1 => FstVarType::Supply1,
+        VarType::Time => FstVarType::Time,
+        VarType::Tri => FstVarType::Tri,
+        VarType::TriAnd => FstVarType::TriAnd,
+        VarType::TriOr => FstVarType::TriOr,
+        VarType::TriReg => FstVarType::TriReg,
+        VarType::Tri0 => FstVarType::Tri0,
+        VarType::Tri1 => FstVarType::Tri1,
+        VarType::WAnd => FstVarType::Wand,
+        VarType::Wire => FstVarType::Wire,
+        VarType::WOr => FstVarType::Wor,
+        VarType::String => FstVarType::GenericString,
+        VarType::Port => FstVarType::Port,
+        VarType::SparseArray => FstVarType::SparseArray,
+        VarType::RealTime => FstVarType::RealTime,
+        VarType::Bit => FstVarType::Bit,
+        VarType::Logic => FstVarType::Logic,
+        VarType::Int => FstVarType::Int,
+        VarType::ShortInt => FstVarType::ShortInt,
+        VarType::LongInt => FstVarType::LongInt,
+        VarType::Byte => FstVarType::Byte,
+        VarType::Enum => FstVarType::Enum,
+        VarType::ShortReal => FstVarType::ShortReal,
+        // VHDL types are additionally annotated with an attribute
+        VarType::Boolean | VarType::BitVector => FstVarType::Bit,
+        VarType::StdLogic
+        | VarType::StdLogicVector
+        | VarType::StdULogic
+        | VarType::StdULogicVector => FstVarType::Logic,
+    }
+}
+
+fn var_direction_to_fst(direction: VarDirection) -> FstVarDirection {
+    match direction {
+        VarDirection::Unknown | VarDirection::Implicit => FstVarDirection::Implicit,
+        VarDirection::Input => FstVarDirection::Input,
+        VarDirection::Output => FstVarDirection::Output,
+        VarDirection::InOut => FstVarDirection::InOut,
+        VarDirection::Buffer => FstVarDirection::Buffer,
+        VarDirection::Linkage => FstVarDirection::Linkage,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_u64(bytes: &[u8], pos: usize) -> u64 {
+        u64::from_be_bytes(bytes[pos..pos + 8].try_into().unwrap())
+    }
+
+    /// Walks over all blocks of a file written by us and checks that their layout matches
+    /// what GTKWave expects.
+    #[test]
+    fn test_write_block_layout() {
+        let vcd = "$timescale 1ns $end\n$scope module top $end\n$var wire 1 ! a $end\n\
+                   $var wire 8 \" b $end\n$var real 64 # r $end\n$upscope $end\n\
+                   $enddefinitions $end\n#0\n0!\nb0 \"\nr0 #\n#10\n1!\nb101 \"\n\
+                   #20\n0!\nr1.5 #\n#30\n1!\nbx \"\n";
+        let mut waves = crate::vcd::read_from_bytes(vcd.as_bytes()).unwrap();
+        let ids: Vec<_> = waves
+            .hierarchy()
+            .iter_vars()
+            .map(|v| v.signal_ref())
+            .collect();
+        waves.load_signals(&ids).unwrap();
+        let options = WriteOptions {
+            max_changes_per_block: 3,
+            ..Default::default()
+        };
+        let mut out = Vec::new();
+        write(&waves, &mut out, options).unwrap();
+
+        // header
+        assert_eq!(out[0], BLOCK_HEADER);
+        assert_eq!(read_u64(&out, 1), HEADER_LENGTH);
+        assert_eq!((read_u64(&out, 9), read_u64(&out, 17)), (0, 30));
+        assert_eq!(out[25..33], DOUBLE_ENDIAN_TEST.to_le_bytes());
+        assert_eq!(read_u64(&out, 41), 1, "scope count");
+        assert_eq!(read_u64(&out, 49), 3, "var count");
+        assert_eq!(read_u64(&out, 57), 3, "max handle");
+        let vc_section_count = read_u64(&out, 65);
+        assert_eq!(out[73] as i8, -9, "timescale");
+        assert_eq!(out[1 + 320], FILE_TYPE_VERILOG);
+        let mut pos = 1 + HEADER_LENGTH as usize;
+
+        // value change blocks cover the time table in order
+        let mut prev_end = None;
+        for _ in 0..vc_section_count {
+            assert_eq!(out[pos], BLOCK_VC_DATA_DYNAMIC_ALIAS);
+            let section_length = read_u64(&out, pos + 1) as usize;
+            let (start, end) = (read_u64(&out, pos + 9), read_u64(&out, pos + 17));
+            assert!(start <= end);
+            assert!(prev_end.is_none_or(|prev| prev < start));
+            prev_end = Some(end);
+            // the last 8 bytes contain the number of time steps
+            let steps = read_u64(&out, pos + 1 + section_length - 8);
+            assert!(steps > 0);
+            pos += 1 + section_length;
+        }
+        assert!(vc_section_count > 1);
+        assert_eq!(prev_end, Some(30));
+
+        // geometry is stored uncompressed
+        assert_eq!(out[pos], BLOCK_GEOMETRY);
+        let section_length = read_u64(&out, pos + 1) as usize;
+        let uncompressed_length = read_u64(&out, pos + 9) as usize;
+        assert_eq!(section_length, uncompressed_length + 3 * 8);
+        assert_eq!(read_u64(&out, pos + 17), 3);
+        assert_eq!(out[pos + 25..pos + 1 + section_length], [1, 8, 0]);
+        pos += 1 + section_length;
+
+        // hierarchy
+        assert_eq!(out[pos], BLOCK_HIERARCHY_LZ4);
+        let section_length = read_u64(&out, pos + 1) as usize;
+        let uncompressed_length = read_u64(&out, pos + 9) as usize;
+        let compressed = &out[pos + 17..pos + 1 + section_length];
+        let hierarchy = lz4_flex::decompress(compressed, uncompressed_length).unwrap();
+        assert_eq!(hierarchy[0], HIERARCHY_SCOPE);
+        assert_eq!(hierarchy[1], FstScopeType::Module as u8);
+        assert_eq!(&hierarchy[2..6], b"top\0");
+        assert_eq!(hierarchy.last(), Some(&HIERARCHY_UP_SCOPE));
+        pos += 1 + section_length;
+        assert_eq!(pos, out.len());
+    }
+
+    #[test]
+    fn test_write_value_of_wrong_kind() {
+        let mut data = Vec::new();
+        let value = SignalValue::Binary(&[0b1], 1);
+        let result = encode_value_change(&mut data, 0, value, FstSignalKind::BitVector(8));
+        assert!(matches!(
+            result,
+            Err(WellenError::UnsupportedFeature(FileFormat::Fst, _))
+        ));
+        let result =
+            encode_value_change(&mut data, 0, SignalValue::Real(1.0), FstSignalKind::String);
+        assert!(result.is_err());
+        assert!(data.is_empty());
+    }
 }
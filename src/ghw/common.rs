@@ -41,11 +41,21 @@ pub(crate) enum GhwParseError {
     ParseInt(#[from] std::num::ParseIntError),
     #[error("[ghw] I/O operation failed")]
     Io(#[from] std::io::Error),
+    #[error("[ghw] loading was cancelled")]
+    Cancelled,
+    #[error("[ghw] unsupported feature: {0}")]
+    UnsupportedFeature(String),
 }
 
 impl From<GhwParseError> for WellenError {
     fn from(value: GhwParseError) -> Self {
-        WellenError::FailedToLoad(FileFormat::Ghw, value.to_string())
+        match value {
+            GhwParseError::Cancelled => WellenError::Cancelled,
+            GhwParseError::UnsupportedFeature(feature) => {
+                WellenError::UnsupportedFeature(FileFormat::Ghw, feature)
+            }
+            other => WellenError::FailedToLoad(FileFormat::Ghw, other.to_string()),
+        }
     }
 }
 
@@ -86,10 +96,8 @@ pub fn read_directory(header: &HeaderData, input: &mut impl BufRead) -> Result<V
 
 #[derive(Debug)]
 pub struct SectionPos {
-    #[allow(dead_code)]
-    id: [u8; 4],
-    #[allow(dead_code)]
-    pos: u32,
+    pub id: [u8; 4],
+    pub pos: u32,
 }
 
 pub(crate) fn check_header_zeros(section: &'static str, header: &[u8]) -> Result<()> {
@@ -240,11 +248,22 @@ pub type GhwDecodeInfo = (GhwSignals, Vec<GhwVecInfo>);
 pub struct GhwSignals {
     /// Type and signal reference info. Indexed by `GhwSignalId`
     signals: Vec<GhwSignalInfo>,
+    /// Marks the signals whose values should be kept, derived from the `LoadOptions`.
+    mask: Option<Vec<bool>>,
 }
 
 impl GhwSignals {
     pub fn new(signals: Vec<GhwSignalInfo>) -> Self {
-        Self { signals }
+        Self {
+            signals,
+            mask: None,
+        }
+    }
+    pub fn set_signal_mask(&mut self, mask: Option<Vec<bool>>) {
+        self.mask = mask;
+    }
+    pub fn signal_mask(&self) -> Option<&[bool]> {
+        self.mask.as_deref()
     }
     pub fn get_info(&self, signal_id: GhwSignalId) -> &GhwSignalInfo {
         &self.signals[signal_id.index()]
@@ -287,8 +306,6 @@ pub struct GhwVecInfo {
     min: GhwSignalId,
     two_state: bool,
     signal_ref: SignalRef,
-    /// pointer to an alias list
-    alias: Option<NonZeroU32>,
 }
 
 impl GhwVecInfo {
@@ -298,7 +315,6 @@ impl GhwVecInfo {
             max,
             two_state,
             signal_ref,
-            alias: None,
         }
     }
     pub fn bits(&self) -> u32 {
@@ -307,22 +323,12 @@ impl GhwVecInfo {
     pub fn is_two_state(&self) -> bool {
         self.two_state
     }
-    pub fn min(&self) -> GhwSignalId {
-        self.min
-    }
     pub fn max(&self) -> GhwSignalId {
         self.max
     }
     pub fn signal_ref(&self) -> SignalRef {
         self.signal_ref
     }
-    pub fn alias(&self) -> Option<NonZeroU32> {
-        self.alias
-    }
-
-    pub fn set_alias(&mut self, alias: NonZeroU32) {
-        self.alias = Some(alias);
-    }
 }
 
 /// Specifies the signal type info that is needed in order to read it.
@@ -429,6 +435,6 @@ mod tests {
         // This is in comparison to ghwdump which stores at least two 8-bit pointers per signal.
         assert_eq!(std::mem::size_of::<GhwSignalInfo>(), 8);
 
-        assert_eq!(std::mem::size_of::<GhwVecInfo>(), 20);
+        assert_eq!(std::mem::size_of::<GhwVecInfo>(), 16);
     }
 }
@@ -9,8 +9,8 @@
 use crate::ghw::common::*;
 use crate::hierarchy::{EnumTypeId, HierarchyBuilder, HierarchyStringId};
 use crate::{
-    FileFormat, Hierarchy, ScopeType, SignalRef, Timescale, TimescaleUnit, VarDirection, VarIndex,
-    VarType,
+    FileFormat, Hierarchy, LoadOptions, ScopeType, SignalRef, Timescale, TimescaleUnit,
+    VarDirection, VarIndex, VarType,
 };
 use num_enum::TryFromPrimitive;
 use std::collections::HashMap;
@@ -113,6 +113,7 @@ pub(crate) fn try_read_directory(
 pub(crate) fn read_hierarchy(
     header: &HeaderData,
     input: &mut impl BufRead,
+    options: &LoadOptions,
 ) -> Result<(GhwDecodeInfo, Hierarchy)> {
     let mut tables = GhwTables::default();
     let mut strings = Vec::new();
@@ -180,7 +181,7 @@ pub(crate) fn read_hierarchy(
                 }
             }
             GHW_HIERARCHY_SECTION => {
-                let dec = read_hierarchy_section(header, &mut tables, input, &mut hb)?;
+                let dec = read_hierarchy_section(header, &mut tables, input, &mut hb, options)?;
                 debug_assert!(
                     decode.is_none(),
                     "unexpected second hierarchy section:\n{:?}\n{:?}",
@@ -200,7 +201,9 @@ pub(crate) fn read_hierarchy(
         }
     }
     let hierarchy = hb.finish();
-    Ok((decode.unwrap(), hierarchy))
+    let (mut signals, vectors) = decode.unwrap();
+    signals.set_signal_mask(options.signal_mask(&hierarchy));
+    Ok(((signals, vectors), hierarchy))
 }
 
 /// adds all enums to the hierarchy and
@@ -361,10 +364,19 @@ fn read_type_section(
             GhwRtik::TypeI32 => VhdlType::I32(name, None),
             GhwRtik::TypeI64 => VhdlType::I64(name, None),
             GhwRtik::TypeF64 => VhdlType::F64(name, None),
+            GhwRtik::TypeP32 | GhwRtik::TypeP64 => {
+                // physical values are recorded as integers in multiples of the primary unit
+                skip_physical_units(header, input)?;
+                if kind == GhwRtik::TypeP32 {
+                    VhdlType::I32(name, None)
+                } else {
+                    VhdlType::I64(name, None)
+                }
+            }
             GhwRtik::SubtypeScalar => {
                 let base = read_type_id(input)?;
                 let range = read_range(input)?;
-                VhdlType::from_subtype_scalar(name, &types, base, range)
+                VhdlType::from_subtype_scalar(name, &types, base, range)?
             }
             GhwRtik::TypeArray => {
                 let element_tpe = read_type_id(input)?;
@@ -374,15 +386,17 @@ fn read_type_section(
                     dims.push(read_type_id(input)?);
                 }
                 debug_assert!(!dims.is_empty());
-                if dims.len() > 1 {
-                    todo!("support multi-dimensional arrays!")
-                }
-                VhdlType::from_array(name, &types, element_tpe, dims[0])
+                VhdlType::from_array(name, &types, element_tpe, &dims)
             }
             GhwRtik::SubtypeArray => {
                 let base = read_type_id(input)?;
-                let range = read_range(input)?;
-                VhdlType::from_subtype_array(name, &types, base, range)
+                // there is one range for every dimension of the base array
+                let num_dims = lookup_concrete_type(&types, base).num_array_dims();
+                let mut ranges = Vec::with_capacity(num_dims);
+                for _ in 0..num_dims {
+                    ranges.push(read_range(input)?);
+                }
+                VhdlType::from_subtype_array(name, &types, base, ranges)?
             }
             GhwRtik::TypeRecord => {
                 let num_fields = leb128::read::unsigned(input)?;
@@ -395,7 +409,11 @@ fn read_type_section(
                 }
                 VhdlType::from_record(name, fields)
             }
-            other => todo!("Support: {other:?}"),
+            other => {
+                return Err(GhwParseError::UnsupportedFeature(format!(
+                    "type kind {other:?}"
+                )))
+            }
         };
         types.push(tpe);
     }
@@ -411,6 +429,18 @@ fn read_type_section(
     }
 }
 
+/// Reads over the unit declarations of a physical type (GHW version 1 and later).
+fn skip_physical_units(header: &HeaderData, input: &mut impl BufRead) -> Result<()> {
+    if header.version > 0 {
+        let num_units = leb128::read::unsigned(input)?;
+        for _ in 0..num_units {
+            let _unit_name = read_string_id(input)?;
+            let _unit_value = leb128::read::signed(input)?;
+        }
+    }
+    Ok(())
+}
+
 /// Our own custom representation of VHDL Types.
 /// During GHW parsing we convert the GHDL types to our own representation.
 #[derive(Debug)]
@@ -435,8 +465,8 @@ enum VhdlType {
     Record(StringId, Vec<(StringId, TypeId)>),
     /// An enum that was not detected to be a 9-value bit. The last entry is a unique ID, starting at 0.
     Enum(StringId, Vec<StringId>, u16),
-    /// Array
-    Array(StringId, TypeId, Option<IntRange>),
+    /// Array with one (possibly unbounded) index range per dimension.
+    Array(StringId, TypeId, Vec<Option<IntRange>>),
 }
 
 /// resolves 1 layer of type aliases
@@ -485,16 +515,23 @@ impl VhdlType {
         }
     }
 
-    fn from_array(name: StringId, types: &[VhdlType], element_tpe: TypeId, index: TypeId) -> Self {
+    fn from_array(
+        name: StringId,
+        types: &[VhdlType],
+        element_tpe: TypeId,
+        dims: &[TypeId],
+    ) -> Self {
         let element_tpe_id = lookup_concrete_type_id(types, element_tpe);
-        let index_type = lookup_concrete_type(types, index);
-        let index_range = index_type.int_range();
+        let index_ranges: Vec<_> = dims
+            .iter()
+            .map(|index| lookup_concrete_type(types, *index).int_range())
+            .collect();
         // this one wont be an alias!
         let concrete_element_type = &types[element_tpe_id.index()];
-        match (concrete_element_type, index_range) {
-            (VhdlType::NineValueBit(_), Some(range)) => VhdlType::NineValueVec(name, range),
-            (VhdlType::Bit(_), Some(range)) => VhdlType::BitVec(name, range),
-            _ => VhdlType::Array(name, element_tpe_id, index_range),
+        match (concrete_element_type, index_ranges.as_slice()) {
+            (VhdlType::NineValueBit(_), [Some(range)]) => VhdlType::NineValueVec(name, *range),
+            (VhdlType::Bit(_), [Some(range)]) => VhdlType::BitVec(name, *range),
+            _ => VhdlType::Array(name, element_tpe_id, index_ranges),
         }
     }
 
@@ -502,61 +539,87 @@ impl VhdlType {
         VhdlType::Record(name, fields)
     }
 
-    fn from_subtype_array(name: StringId, types: &[VhdlType], base: TypeId, range: Range) -> Self {
+    fn from_subtype_array(
+        name: StringId,
+        types: &[VhdlType],
+        base: TypeId,
+        ranges: Vec<Range>,
+    ) -> Result<Self> {
         let base_tpe = lookup_concrete_type(types, base);
-        match (base_tpe, range) {
-            (VhdlType::Array(base_name, element_tpe, maybe_base_range), Range::Int(int_range)) => {
-                if let Some(base_range) = maybe_base_range {
-                    debug_assert!(
-                        int_range.is_subset_of(&base_range),
-                        "{int_range:?} {base_range:?}"
-                    );
-                }
-                VhdlType::Array(
+        match (base_tpe, ranges.as_slice()) {
+            (VhdlType::Array(base_name, element_tpe, base_ranges), _) => {
+                debug_assert_eq!(base_ranges.len(), ranges.len());
+                let int_ranges = ranges
+                    .iter()
+                    .zip(base_ranges.iter())
+                    .map(|(range, maybe_base_range)| match range {
+                        Range::Int(int_range) => {
+                            if let Some(base_range) = maybe_base_range {
+                                debug_assert!(
+                                    int_range.is_subset_of(base_range),
+                                    "{int_range:?} {base_range:?}"
+                                );
+                            }
+                            Ok(Some(*int_range))
+                        }
+                        // enum indices are recorded as positions and thus turn into an IntRange
+                        other => Err(GhwParseError::UnsupportedFeature(format!(
+                            "array index range {other:?}"
+                        ))),
+                    })
+                    .collect::<Result<_>>()?;
+                Ok(VhdlType::Array(
                     pick_best_name(name, *base_name),
                     *element_tpe,
-                    Some(int_range),
-                )
+                    int_ranges,
+                ))
             }
-            (VhdlType::NineValueVec(base_name, base_range), Range::Int(int_range)) => {
+            (VhdlType::NineValueVec(base_name, base_range), [Range::Int(int_range)]) => {
                 debug_assert!(
-                    int_range.is_subset_of(&base_range),
+                    int_range.is_subset_of(base_range),
                     "{int_range:?} {base_range:?}"
                 );
-                VhdlType::NineValueVec(pick_best_name(name, *base_name), int_range)
+                Ok(VhdlType::NineValueVec(
+                    pick_best_name(name, *base_name),
+                    *int_range,
+                ))
             }
-            (VhdlType::BitVec(base_name, base_range), Range::Int(int_range)) => {
+            (VhdlType::BitVec(base_name, base_range), [Range::Int(int_range)]) => {
                 debug_assert!(
-                    int_range.is_subset_of(&base_range),
+                    int_range.is_subset_of(base_range),
                     "{int_range:?} {base_range:?}"
                 );
-                VhdlType::BitVec(pick_best_name(name, *base_name), int_range)
+                Ok(VhdlType::BitVec(
+                    pick_best_name(name, *base_name),
+                    *int_range,
+                ))
             }
-            other => todo!("Currently unsupported combination: {other:?}"),
+            (other, ranges) => Err(GhwParseError::UnsupportedFeature(format!(
+                "array subtype of {other:?} with ranges {ranges:?}"
+            ))),
         }
     }
 
-    fn from_subtype_scalar(name: StringId, types: &[VhdlType], base: TypeId, range: Range) -> Self {
-        let base_tpe = lookup_concrete_type(types, base);
+    fn from_subtype_scalar(
+        name: StringId,
+        types: &[VhdlType],
+        base: TypeId,
+        range: Range,
+    ) -> Result<Self> {
+        let base_id = lookup_concrete_type_id(types, base);
+        let base_tpe = &types[base_id.index()];
         match (base_tpe, range) {
-            (VhdlType::Enum(_, lits, _), Range::Int(int_range)) => {
-                let range = int_range.range();
-                debug_assert!(range.start >= 0 && range.start <= lits.len() as i64);
-                debug_assert!(range.end >= 0 && range.end <= lits.len() as i64);
-                // check to see if this is just an alias or if we need to create a new enum
-                if range.start == 0 && range.end == lits.len() as i64 {
-                    VhdlType::TypeAlias(name, base)
-                } else {
-                    todo!("actual sub enum!")
-                }
-            }
-            (VhdlType::NineValueBit(_), Range::Int(int_range)) => {
-                let range = int_range.range();
-                if range.start == 0 && range.end == 9 {
-                    VhdlType::TypeAlias(name, base)
-                } else {
-                    todo!("actual sub enum!")
-                }
+            // Values of an enum subtype are recorded as positions in the base enum. Thus we
+            // can use the base enum (and its literal table) to decode them.
+            (
+                VhdlType::Enum(_, _, _) | VhdlType::NineValueBit(_) | VhdlType::Bit(_),
+                Range::Int(int_range),
+            ) => {
+                debug_assert!(
+                    int_range.is_subset_of(&base_tpe.int_range().unwrap()),
+                    "{int_range:?} {base_tpe:?}"
+                );
+                Ok(VhdlType::TypeAlias(name, base_id))
             }
             (VhdlType::I32(_, maybe_base_range), Range::Int(int_range)) => {
                 let base_range = IntRange::from_i32_option(*maybe_base_range);
@@ -564,7 +627,16 @@ impl VhdlType {
                     int_range.is_subset_of(&base_range),
                     "{int_range:?} {base_range:?}"
                 );
-                VhdlType::I32(name, Some(int_range))
+                Ok(VhdlType::I32(name, Some(int_range)))
+            }
+            (VhdlType::I64(_, maybe_base_range), Range::Int(int_range)) => {
+                if let Some(base_range) = maybe_base_range {
+                    debug_assert!(
+                        int_range.is_subset_of(base_range),
+                        "{int_range:?} {base_range:?}"
+                    );
+                }
+                Ok(VhdlType::I64(name, Some(int_range)))
             }
             (VhdlType::F64(_, maybe_base_range), Range::Float(float_range)) => {
                 let base_range = FloatRange::from_f64_option(*maybe_base_range);
@@ -572,9 +644,11 @@ impl VhdlType {
                     float_range.is_subset_of(&base_range),
                     "{float_range:?} {base_range:?}"
                 );
-                VhdlType::F64(name, Some(float_range))
+                Ok(VhdlType::F64(name, Some(float_range)))
             }
-            other => todo!("Currently unsupported combination: {other:?}"),
+            (other, range) => Err(GhwParseError::UnsupportedFeature(format!(
+                "scalar subtype of {other:?} with range {range:?}"
+            ))),
         }
     }
 
@@ -597,12 +671,21 @@ impl VhdlType {
     fn int_range(&self) -> Option<IntRange> {
         match self {
             VhdlType::NineValueBit(_) => Some(IntRange(RangeDir::To, 0, 8)),
+            VhdlType::Bit(_) => Some(IntRange(RangeDir::To, 0, 1)),
             VhdlType::I32(_, range) => *range,
             VhdlType::I64(_, range) => *range,
-            VhdlType::Enum(_, lits, _) => Some(IntRange(RangeDir::To, 0, lits.len() as i64)),
+            VhdlType::Enum(_, lits, _) => Some(IntRange(RangeDir::To, 0, lits.len() as i64 - 1)),
             _ => None,
         }
     }
+
+    /// Number of index ranges that a subtype of this type needs to specify.
+    fn num_array_dims(&self) -> usize {
+        match self {
+            VhdlType::Array(_, _, ranges) => ranges.len(),
+            _ => 1,
+        }
+    }
 }
 
 /// Returns Some(VhdlType::NineValueBit(..)) if the enum corresponds to a 9-value bit type.
@@ -706,6 +789,7 @@ fn read_hierarchy_section(
     tables: &mut GhwTables,
     input: &mut impl BufRead,
     h: &mut HierarchyBuilder,
+    options: &LoadOptions,
 ) -> Result<GhwDecodeInfo> {
     let mut hdr = [0u8; 16];
     input.read_exact(&mut hdr)?;
@@ -742,7 +826,7 @@ fn read_hierarchy_section(
             | GhwHierarchyKind::Instance
             | GhwHierarchyKind::Generic
             | GhwHierarchyKind::Package => {
-                read_hierarchy_scope(tables, input, kind, h)?;
+                read_hierarchy_scope(tables, input, kind, h, options)?;
             }
             GhwHierarchyKind::Signal
             | GhwHierarchyKind::PortIn
@@ -772,10 +856,7 @@ fn read_hierarchy_section(
     }
 
     // println!("Wellen Signal Refs: {}", signal_info.signal_ref_count);
-    let (decode_info, aliases) = signal_info.into_decode_info();
-    for alias in aliases.into_iter() {
-        h.add_slice(alias.signal_ref, alias.msb, alias.lsb, alias.sliced_signal);
-    }
+    let decode_info = signal_info.finish(h);
     // println!("GHW Signals: {}", decode_info.0.signal_len());
     // println!("Vectors: {}", decode_info.1.len());
 
@@ -808,12 +889,11 @@ fn dummy_read_signal_value(
                 dummy_read_signal_value(tables, f_tpe, input)?;
             }
         }
-        VhdlType::Array(_, el_tpe_id, maybe_range) => {
+        VhdlType::Array(_, el_tpe_id, ranges) => {
             let (element_tpe, _) = tables.get_type_and_name(*el_tpe_id);
-            if let Some(range) = maybe_range {
-                for _ in range.range() {
-                    dummy_read_signal_value(tables, element_tpe, input)?;
-                }
+            let num_elements: Option<i64> = ranges.iter().map(|r| r.map(|r| r.len())).product();
+            for _ in 0..num_elements.unwrap_or(0) {
+                dummy_read_signal_value(tables, element_tpe, input)?;
             }
         }
         VhdlType::TypeAlias(_, _) => unreachable!("type should have been resolved"),
@@ -826,8 +906,11 @@ fn read_hierarchy_scope(
     input: &mut impl BufRead,
     kind: GhwHierarchyKind,
     h: &mut HierarchyBuilder,
+    options: &LoadOptions,
 ) -> Result<()> {
     let name = read_string_id(input)?;
+    let flatten =
+        options.remove_scopes_with_empty_name && h.get_str(tables.get_str(name)).is_empty();
 
     if kind == GhwHierarchyKind::GenerateFor {
         let iter_tpe_id = read_type_id(input)?;
@@ -843,7 +926,7 @@ fn read_hierarchy_scope(
         convert_scope_type(kind),
         None, // no source info in GHW
         None, // no source info in GHW
-        false,
+        flatten,
     );
 
     Ok(())
@@ -912,18 +995,16 @@ fn get_index_string(
 struct GhwSignalTracker {
     signals: Vec<Option<GhwSignalInfo>>,
     signal_ref_count: usize,
-    vectors: Vec<GhwVecInfo>,
-    aliases: Vec<AliasInfo>,
+    /// Bits and bit-vectors identified by the (inclusive) range of GHW signal ids that they cover.
+    /// Ranges may overlap, e.g., because of aliases or port maps that connect slices of a
+    /// larger vector. Thus, we only decide how to store them once the complete hierarchy is known.
+    bit_vecs: HashMap<(usize, usize), BitVecInfo>,
 }
 
-/// Used to define aliased signals in the `wellen` wavemem.
 #[derive(Debug, Clone, Copy)]
-struct AliasInfo {
-    msb: u32,
-    lsb: u32,
+struct BitVecInfo {
     signal_ref: SignalRef,
-    sliced_signal: SignalRef,
-    next: Option<NonZeroU32>,
+    is_binary: bool,
 }
 
 impl GhwSignalTracker {
@@ -932,15 +1013,92 @@ impl GhwSignalTracker {
         Self {
             signals,
             signal_ref_count: 0,
-            vectors: vec![],
-            aliases: vec![],
+            bit_vecs: HashMap::new(),
         }
     }
 
-    fn into_decode_info(self) -> (GhwDecodeInfo, Vec<AliasInfo>) {
+    /// Decides how bits and bit-vectors are stored. Overlapping ranges of GHW signals are
+    /// merged into a single vector and every signal that does not cover the complete vector
+    /// becomes a slice of it. If no declared signal covers the complete vector, it is added
+    /// to the hierarchy as a hidden signal.
+    fn finish(mut self, h: &mut HierarchyBuilder) -> GhwDecodeInfo {
+        let mut ranges: Vec<_> = std::mem::take(&mut self.bit_vecs).into_iter().collect();
+        // for ranges with the same start, the longest one comes first
+        ranges.sort_unstable_by_key(|((min, max), _)| (*min, std::cmp::Reverse(*max)));
+
+        let mut vectors = Vec::new();
+        let mut group_start = 0;
+        while group_start < ranges.len() {
+            let ((min, mut max), _) = ranges[group_start];
+            let mut group_end = group_start + 1;
+            while group_end < ranges.len() && ranges[group_end].0 .0 <= max {
+                max = max.max(ranges[group_end].0 .1);
+                group_end += 1;
+            }
+            self.add_vec(h, &mut vectors, min, max, &ranges[group_start..group_end]);
+            group_start = group_end;
+        }
+
         let mut signals: Vec<_> = self.signals.into_iter().flatten().collect();
         signals.shrink_to_fit();
-        ((GhwSignals::new(signals), self.vectors), self.aliases)
+        (GhwSignals::new(signals), vectors)
+    }
+
+    /// Stores the GHW signals `min..=max` which are covered by the (overlapping) `ranges`.
+    fn add_vec(
+        &mut self,
+        h: &mut HierarchyBuilder,
+        vectors: &mut Vec<GhwVecInfo>,
+        min: usize,
+        max: usize,
+        ranges: &[((usize, usize), BitVecInfo)],
+    ) {
+        let is_binary = ranges.iter().all(|(_, info)| info.is_binary);
+        if min == max {
+            // single bit with no aliased vector behaves essentially like a scalar
+            debug_assert_eq!(ranges.len(), 1);
+            let tpe = if is_binary {
+                SignalType::TwoState
+            } else {
+                SignalType::NineState
+            };
+            self.signals[min] = Some(GhwSignalInfo::new(tpe, ranges[0].1.signal_ref, None));
+            return;
+        }
+
+        let signal_ref = match ranges[0] {
+            ((_, first_max), info) if first_max == max => info.signal_ref,
+            _ => {
+                let signal_ref = self.new_signal_ref();
+                let tpe = crate::hierarchy::SignalType::from_uint((max - min + 1) as u32, None);
+                h.add_hidden_signal(signal_ref, tpe);
+                signal_ref
+            }
+        };
+
+        let vec_id = vectors.len();
+        let (min_id, max_id) = (
+            GhwSignalId::new(min as u32 + 1),
+            GhwSignalId::new(max as u32 + 1),
+        );
+        vectors.push(GhwVecInfo::new(min_id, max_id, is_binary, signal_ref));
+        let tpe = if is_binary {
+            SignalType::TwoStateVec
+        } else {
+            SignalType::NineStateVec
+        };
+        for ii in min..=max {
+            debug_assert!(self.signals[ii].is_none(), "{ii} is not a bit");
+            self.signals[ii] = Some(GhwSignalInfo::new(tpe, signal_ref, Some(vec_id)));
+        }
+
+        // the GHW signal with the lowest id is the most significant bit
+        for ((slice_min, slice_max), info) in ranges.iter() {
+            if info.signal_ref != signal_ref {
+                let (msb, lsb) = ((max - slice_min) as u32, (max - slice_max) as u32);
+                h.add_slice(info.signal_ref, msb, lsb, signal_ref);
+            }
+        }
     }
 
     fn max_signal_id(&self) -> usize {
@@ -967,135 +1125,27 @@ impl GhwSignalTracker {
         id
     }
 
-    fn find_or_add_alias(&mut self, vec_id: GhwVecId, msb: u32, lsb: u32) -> SignalRef {
-        // first we try to see if we can find an existing alias
-        let sliced_signal = self.vectors[vec_id.index()].signal_ref();
-        if let Some(mut alias_id) = self.vectors[vec_id.index()].alias() {
-            loop {
-                let alias = self.aliases[alias_id.get() as usize - 1].clone();
-                if alias.msb == msb && alias.lsb == lsb {
-                    return alias.signal_ref;
-                }
-                match alias.next {
-                    Some(next) => alias_id = next,
-                    None => {
-                        // create new entry
-                        let signal_ref = self.new_signal_ref();
-                        let new_alias_id = self.aliases.len() + 1;
-                        self.aliases.push(AliasInfo {
-                            msb,
-                            lsb,
-                            signal_ref,
-                            sliced_signal,
-                            next: None,
-                        });
-                        self.aliases[alias_id.get() as usize - 1].next =
-                            Some(NonZeroU32::new(new_alias_id as u32).unwrap());
-                        return signal_ref;
-                    }
-                }
-            }
-        } else {
-            // create entry
-            let signal_ref = self.new_signal_ref();
-            let alias_id = self.aliases.len() + 1;
-            self.aliases.push(AliasInfo {
-                msb,
-                lsb,
-                signal_ref,
-                sliced_signal,
-                next: None,
-            });
-            self.vectors[vec_id.index()].set_alias(NonZeroU32::new(alias_id as u32).unwrap());
-            signal_ref
-        }
-    }
-
     fn register_bit_vec(
         &mut self,
         min_id: GhwSignalId,
         max_id: GhwSignalId,
         is_binary: bool,
     ) -> SignalRef {
-        let (min, max) = (min_id.index(), max_id.index());
-        debug_assert!(max >= min);
-
-        // check to see if there is already a vector that this signal aliases with
-        let maybe_vector_id = self.find_vec(min, max);
-        if let Some(vector_id) = maybe_vector_id {
-            let vector = &self.vectors[vector_id.index()];
-            let (prev_min, prev_max) = (vector.min().index(), vector.max().index());
-            if max == prev_max && min == prev_min {
-                // perfect alias -> just return the signal ref
-                self.signals[min].unwrap().signal_ref()
-            } else if prev_min <= min && prev_max >= max {
-                // this means that a larger vector already exists and our signal is a slice of this vector
-                let (msb, lsb) = (max - prev_min, min - prev_min);
-                self.find_or_add_alias(vector_id, msb as u32, lsb as u32)
-            } else if prev_min >= min && prev_max <= max {
-                todo!("super signal alias!")
-            } else {
-                todo!("overlapped aliased vectors")
-            }
+        let key = (min_id.index(), max_id.index());
+        debug_assert!(key.1 >= key.0);
+        if let Some(info) = self.bit_vecs.get(&key) {
+            // perfect alias -> just return the signal ref
+            debug_assert_eq!(info.is_binary, is_binary);
+            info.signal_ref
         } else {
-            // no existing vector to alias with
-            if min == max {
-                // single bit with no aliased vector behaves essentially like a scalar
-                if is_binary {
-                    self.register_scalar(min_id, SignalType::TwoState)
-                } else {
-                    self.register_scalar(min_id, SignalType::NineState)
-                }
-            } else {
-                // for a vector we want to make sure that there are no bits that alias this vector
-                for ii in min..=max {
-                    // this situation is very unlikely since assigning bit ids first would make it harder for GHDL
-                    // to assign contiguous vec ids
-                    assert!(
-                        self.signals[ii].is_none(),
-                        "TODO: deal with bit that aliases with vector"
-                    );
-                }
-
-                // create a new vec entry
-                let vec_id = self.vectors.len();
-                let id = self.new_signal_ref();
-                self.vectors
-                    .push(GhwVecInfo::new(min_id, max_id, is_binary, id));
-                let tpe = if is_binary {
-                    SignalType::TwoStateVec
-                } else {
-                    SignalType::NineStateVec
-                };
-
-                // update all bits
-                for ii in min..=max {
-                    self.signals[ii] = Some(GhwSignalInfo::new(tpe, id, Some(vec_id)));
-                }
-                id
-            }
-        }
-    }
-
-    /// Searches for vector info in the specified range.
-    #[inline]
-    fn find_vec(&self, min: usize, max: usize) -> Option<GhwVecId> {
-        let mut res = None;
-        for ii in min..=max {
-            if let Some(info) = &self.signals[ii] {
-                let vec_id = info.vec_id();
-                debug_assert!(
-                    res.is_none() || vec_id.is_none() || res == vec_id,
-                    "Signal spans several sub-vectors"
-                );
-                res = vec_id;
-                // early returns in release mode
-                if !cfg!(debug_assertions) && res.is_some() {
-                    return res;
-                }
-            }
+            let signal_ref = self.new_signal_ref();
+            let info = BitVecInfo {
+                signal_ref,
+                is_binary,
+            };
+            self.bit_vecs.insert(key, info);
+            signal_ref
         }
-        res
     }
 }
 
@@ -1167,6 +1217,20 @@ fn add_var(
                 Some(tpe_name),
             );
         }
+        VhdlType::I64(_, _) => {
+            let index = read_signal_id(input, signals.max_signal_id())?;
+            let signal_ref = signals.register_scalar(index, SignalType::Leb128Signed);
+            h.add_var(
+                name,
+                VarType::Integer,
+                dir,
+                64,
+                None,
+                signal_ref,
+                None,
+                Some(tpe_name),
+            );
+        }
         VhdlType::F64(_, maybe_range) => {
             // TODO: we could use the range to deduce indices and tighter widths
             let _range = FloatRange::from_f64_option(*maybe_range);
@@ -1186,50 +1250,10 @@ fn add_var(
             );
         }
         VhdlType::NineValueVec(_, range) | VhdlType::BitVec(_, range) => {
-            let num_bits = range.len().abs() as u32;
-            if num_bits == 0 {
-                // TODO: how should we correctly deal with an empty vector?
-                return Ok(());
-            }
-
-            let mut signal_ids = Vec::with_capacity(num_bits as usize);
-            for _ in 0..num_bits {
-                signal_ids.push(read_signal_id(input, signals.max_signal_id())?);
-            }
-
-            // check assumption that all IDs are continuous
-            for (prev, cur) in signal_ids
-                .iter()
-                .take(signal_ids.len() - 1)
-                .zip(signal_ids.iter().skip(1))
-            {
-                debug_assert_eq!(
-                    prev.index() + 1,
-                    cur.index(),
-                    "We expected signal ids increasing by exactly 1, not {prev:?} -> {cur:?}"
-                );
-            }
-
             let is_binary = matches!(vhdl_tpe, VhdlType::BitVec(_, _));
-            let min = signal_ids.first().unwrap().clone();
-            let max = signal_ids.last().unwrap().clone();
-            let signal_ref = signals.register_bit_vec(min, max, is_binary);
-            let var_type = match h.get_str(tpe_name).to_ascii_lowercase().as_str() {
-                "std_ulogic_vector" => VarType::StdULogicVector,
-                "std_logic_vector" => VarType::StdLogicVector,
-                "bit_vector" => VarType::BitVector,
-                _ => VarType::Wire,
-            };
-            h.add_var(
-                name,
-                var_type,
-                dir,
-                num_bits,
-                Some(range.as_var_index()),
-                signal_ref,
-                None,
-                Some(tpe_name),
-            );
+            if let Some(signal_ref) = read_bit_vec_signal(input, signals, range, is_binary)? {
+                add_bit_vec_var(h, name, tpe_name, dir, range, signal_ref);
+            }
         }
         VhdlType::Record(_, fields) => {
             h.add_scope(name, None, ScopeType::VhdlRecord, None, None, false);
@@ -1248,29 +1272,156 @@ fn add_var(
             h.pop_scope();
         }
         // we treat arrays like records
-        VhdlType::Array(_, element_tpe, maybe_range) => {
-            let range = IntRange::from_i32_option(*maybe_range);
-            h.add_scope(name, None, ScopeType::VhdlArray, None, None, false);
-            for element_id in range.range() {
-                let name = get_index_string(index_string_cache, h, element_id);
-                add_var(
-                    tables,
-                    input,
-                    kind,
-                    signals,
-                    index_string_cache,
-                    h,
-                    name,
-                    *element_tpe,
-                )?;
+        VhdlType::Array(_, element_tpe, maybe_ranges) => {
+            let ranges: Vec<_> = maybe_ranges
+                .iter()
+                .map(|r| IntRange::from_i32_option(*r))
+                .collect();
+            let array = ArrayVar {
+                name,
+                tpe_name,
+                element_tpe: *element_tpe,
+                ranges: &ranges,
+            };
+            add_array(tables, input, kind, signals, index_string_cache, h, array)?;
+        }
+        other => {
+            return Err(GhwParseError::UnsupportedFeature(format!(
+                "signal of type {other:?}"
+            )))
+        }
+    }
+    Ok(())
+}
+
+/// An array signal (or a slice of a multi-dimensional array signal) that is being declared.
+struct ArrayVar<'a> {
+    name: HierarchyStringId,
+    tpe_name: HierarchyStringId,
+    element_tpe: TypeId,
+    /// index ranges of the remaining dimensions, outermost first
+    ranges: &'a [IntRange],
+}
+
+/// Multi-dimensional arrays are represented as nested `VhdlArray` scopes, one level per dimension.
+/// GHDL lays out the elements in row-major order, i.e., the last dimension changes fastest.
+/// If the elements are bits, the last dimension is turned into a bit-vector.
+fn add_array(
+    tables: &GhwTables,
+    input: &mut impl BufRead,
+    kind: GhwHierarchyKind,
+    signals: &mut GhwSignalTracker,
+    index_string_cache: &mut IndexCache,
+    h: &mut HierarchyBuilder,
+    array: ArrayVar,
+) -> Result<()> {
+    let (range, inner_ranges) = array
+        .ranges
+        .split_first()
+        .expect("arrays have at least one dimension");
+    if inner_ranges.is_empty() {
+        let (element_tpe, _) = tables.get_type_and_name(array.element_tpe);
+        if let VhdlType::NineValueBit(_) | VhdlType::Bit(_) = element_tpe {
+            let is_binary = matches!(element_tpe, VhdlType::Bit(_));
+            if let Some(signal_ref) = read_bit_vec_signal(input, signals, range, is_binary)? {
+                let dir = convert_kind_to_dir(kind);
+                add_bit_vec_var(h, array.name, array.tpe_name, dir, range, signal_ref);
             }
-            h.pop_scope();
+            return Ok(());
         }
-        other => todo!("deal with {other:?}"),
     }
+
+    h.add_scope(array.name, None, ScopeType::VhdlArray, None, None, false);
+    for element_id in range.range() {
+        let name = get_index_string(index_string_cache, h, element_id);
+        if inner_ranges.is_empty() {
+            add_var(
+                tables,
+                input,
+                kind,
+                signals,
+                index_string_cache,
+                h,
+                name,
+                array.element_tpe,
+            )?;
+        } else {
+            let inner = ArrayVar {
+                name,
+                ranges: inner_ranges,
+                ..array
+            };
+            add_array(tables, input, kind, signals, index_string_cache, h, inner)?;
+        }
+    }
+    h.pop_scope();
     Ok(())
 }
 
+/// Reads the GHW signal ids of all bits and registers them as a single vector.
+/// Returns `None` for empty vectors. Bits need to be stored in consecutive GHW signals.
+fn read_bit_vec_signal(
+    input: &mut impl BufRead,
+    signals: &mut GhwSignalTracker,
+    range: &IntRange,
+    is_binary: bool,
+) -> Result<Option<SignalRef>> {
+    let num_bits = range.len().unsigned_abs() as u32;
+    if num_bits == 0 {
+        // TODO: how should we correctly deal with an empty vector?
+        return Ok(None);
+    }
+
+    let mut signal_ids = Vec::with_capacity(num_bits as usize);
+    for _ in 0..num_bits {
+        signal_ids.push(read_signal_id(input, signals.max_signal_id())?);
+    }
+
+    // bits are decoded as offsets from the first signal id, thus all ids need to be continuous
+    let is_continuous = signal_ids
+        .windows(2)
+        .all(|w| w[0].index() + 1 == w[1].index());
+    if !is_continuous {
+        return Err(GhwParseError::UnsupportedFeature(format!(
+            "bit vector with non-continuous signal ids {:?}",
+            signal_ids
+                .iter()
+                .map(|id| id.index() + 1)
+                .collect::<Vec<_>>()
+        )));
+    }
+
+    let min = *signal_ids.first().unwrap();
+    let max = *signal_ids.last().unwrap();
+    Ok(Some(signals.register_bit_vec(min, max, is_binary)))
+}
+
+fn add_bit_vec_var(
+    h: &mut HierarchyBuilder,
+    name: HierarchyStringId,
+    tpe_name: HierarchyStringId,
+    dir: VarDirection,
+    range: &IntRange,
+    signal_ref: SignalRef,
+) {
+    let var_type = match h.get_str(tpe_name).to_ascii_lowercase().as_str() {
+        "std_ulogic_vector" => VarType::StdULogicVector,
+        "std_logic_vector" => VarType::StdLogicVector,
+        "bit_vector" => VarType::BitVector,
+        _ => VarType::Wire,
+    };
+    h.add_var(
+        name,
+        var_type,
+        dir,
+        range.len().unsigned_abs() as u32,
+        Some(range.as_var_index()),
+        signal_ref,
+        None,
+        Some(tpe_name),
+    );
+}
+
 fn read_signal_id(input: &mut impl BufRead, max_signal_id: usize) -> Result<GhwSignalId> {
     let index = leb128::read::unsigned(input)? as usize;
     if index > max_signal_id {
@@ -1386,3 +1537,419 @@ struct GhwUnit {
     name: StringId,
     value: i64,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STRINGS: &[&str] = &[
+        "std_ulogic",
+        "'U'",
+        "'X'",
+        "'0'",
+        "'1'",
+        "'Z'",
+        "'W'",
+        "'L'",
+        "'H'",
+        "'-'",
+        "integer",
+        "top",
+        "mem_t",
+        "mem",
+        "bool_mat_t",
+        "bool_mat",
+        "boolean",
+        "false",
+        "true",
+        "state_t",
+        "idle",
+        "run",
+        "done",
+        "state_sub",
+        "state",
+        "x01",
+        "x",
+        "small_t",
+        "n",
+        "time",
+        "fs",
+        "ps",
+        "delay_t",
+        "t",
+        "real",
+        "volt_t",
+        "v",
+        "idx_t",
+        "slv_t",
+        "a",
+        "b",
+        "c",
+        "d",
+        "e",
+        "f",
+    ];
+
+    const E8: u8 = GhwRtik::TypeE8 as u8;
+    const I32: u8 = GhwRtik::TypeI32 as u8;
+    const SIGNAL: u8 = GhwHierarchyKind::Signal as u8;
+
+    fn str_id(name: &str) -> u8 {
+        STRINGS.iter().position(|s| *s == name).unwrap() as u8 + 1
+    }
+
+    fn section(out: &mut Vec<u8>, mark: &[u8; 4], counts: &[u32]) {
+        out.extend_from_slice(mark);
+        out.extend_from_slice(&[0, 0, 0, 0]);
+        for count in counts {
+            out.extend_from_slice(&count.to_le_bytes());
+        }
+    }
+
+    /// Generates the header of a GHW file with all signals declared in a single `top` instance.
+    fn ghw_header(num_types: u32, types: &[u8], num_vars: u32, vars: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        // note: the string count does not include the last string
+        section(&mut out, GHW_STRING_SECTION, &[STRINGS.len() as u32 - 1, 0]);
+        for s in STRINGS {
+            out.extend_from_slice(s.as_bytes());
+            out.push(0); // no shared prefix with the next string
+        }
+
+        section(&mut out, GHW_TYPE_SECTION, &[num_types]);
+        // 1: std_ulogic is always available
+        out.extend_from_slice(&[E8, str_id("std_ulogic"), 9]);
+        out.extend(str_id("'U'")..=str_id("'-'"));
+        out.extend_from_slice(types);
+        out.push(0);
+
+        // we use a generous upper bound for the number of signals
+        section(&mut out, GHW_HIERARCHY_SECTION, &[1, num_vars, 64]);
+        out.extend_from_slice(&[GhwHierarchyKind::Instance as u8, str_id("top")]);
+        out.extend_from_slice(vars);
+        out.push(GhwHierarchyKind::EndOfScope as u8);
+        out.push(GhwHierarchyKind::End as u8);
+
+        out.extend_from_slice(GHW_END_OF_HEADER_SECTION);
+        out
+    }
+
+    fn try_read_test_hierarchy(input: &[u8]) -> Result<(GhwDecodeInfo, Hierarchy)> {
+        let header = HeaderData {
+            version: 1,
+            big_endian: false,
+            word_len: 8,
+            word_offset: 1,
+        };
+        read_hierarchy(&header, &mut &input[..], &LoadOptions::default())
+    }
+
+    fn read_test_hierarchy(input: &[u8]) -> (GhwDecodeInfo, Hierarchy) {
+        try_read_test_hierarchy(input).unwrap()
+    }
+
+    #[test]
+    fn test_unsupported_subtypes() {
+        // 2: record a is b: std_ulogic; end record
+        let record = [GhwRtik::TypeRecord as u8, str_id("a"), 1, str_id("b"), 1];
+        // 3: integer
+        let integer = [I32, str_id("integer")];
+        // a scalar subtype of a record and an array subtype of a scalar
+        let subtypes = [
+            [GhwRtik::SubtypeScalar as u8, str_id("c"), 2, I32, 0, 3],
+            [GhwRtik::SubtypeArray as u8, str_id("c"), 3, I32, 0, 3],
+        ];
+        for subtype in subtypes {
+            let types = [record.as_slice(), &integer, &subtype].concat();
+            let input = ghw_header(4, &types, 0, &[]);
+            let err = try_read_test_hierarchy(&input).err().unwrap();
+            assert!(
+                matches!(err, GhwParseError::UnsupportedFeature(_)),
+                "{err:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_non_continuous_bit_vector() {
+        let mut types = Vec::new();
+        // 2: integer
+        types.extend_from_slice(&[I32, str_id("integer")]);
+        // 3: type slv_t is array (integer range <>) of std_ulogic
+        types.extend_from_slice(&[GhwRtik::TypeArray as u8, str_id("slv_t"), 1, 1, 2]);
+        // 4: slv_t(1 downto 0)
+        types.extend_from_slice(&[GhwRtik::SubtypeArray as u8, 0, 3, I32 | 0x80, 1, 0]);
+        // `b` aliases the bits of `a` in a different order
+        let mut vars = vec![SIGNAL, str_id("a"), 4, 1, 2];
+        vars.extend_from_slice(&[SIGNAL, str_id("b"), 4, 2, 1]);
+        let input = ghw_header(4, &types, 2, &vars);
+        let err = try_read_test_hierarchy(&input).err().unwrap();
+        assert!(
+            matches!(err, GhwParseError::UnsupportedFeature(_)),
+            "{err:?}"
+        );
+    }
+
+    #[test]
+    fn test_unsupported_type_kind() {
+        // 2: a record subtype, which we do not support
+        let types = [GhwRtik::SubtypeRecord as u8, str_id("a")];
+        let input = ghw_header(2, &types, 0, &[]);
+        let err = try_read_test_hierarchy(&input).err().unwrap();
+        assert!(
+            matches!(err, GhwParseError::UnsupportedFeature(_)),
+            "{err:?}"
+        );
+    }
+
+    #[test]
+    fn test_multi_dimensional_arrays() {
+        let mut types = Vec::new();
+        // 2: integer
+        types.extend_from_slice(&[I32, str_id("integer")]);
+        // 3: boolean
+        types.extend_from_slice(&[E8, str_id("boolean"), 2, str_id("false"), str_id("true")]);
+        // 4: mem_t is array (integer range <>, integer range <>) of std_ulogic
+        types.extend_from_slice(&[GhwRtik::TypeArray as u8, str_id("mem_t"), 1, 2, 2, 2]);
+        // 5: mem_t(0 to 3, 7 downto 0)
+        types.extend_from_slice(&[GhwRtik::SubtypeArray as u8, 0, 4]);
+        types.extend_from_slice(&[I32, 0, 3, I32 | 0x80, 7, 0]);
+        // 6: bool_mat_t is array (integer range <>, integer range <>) of boolean
+        types.extend_from_slice(&[GhwRtik::TypeArray as u8, str_id("bool_mat_t"), 3, 2, 2, 2]);
+        // 7: bool_mat_t(0 to 1, 0 to 2)
+        types.extend_from_slice(&[GhwRtik::SubtypeArray as u8, 0, 6]);
+        types.extend_from_slice(&[I32, 0, 1, I32, 0, 2]);
+
+        let mut vars = vec![SIGNAL, str_id("mem"), 5];
+        vars.extend(1..=32u8);
+        vars.extend_from_slice(&[SIGNAL, str_id("bool_mat"), 7]);
+        vars.extend(33..=38u8);
+
+        let input = ghw_header(7, &types, 2, &vars);
+        let ((_, vectors), h) = read_test_hierarchy(&input);
+
+        let vars: Vec<_> = h.iter_vars().collect();
+        let names: Vec<_> = vars.iter().map(|v| v.full_name(&h)).collect();
+        assert_eq!(
+            names,
+            [
+                "top.mem.[0]",
+                "top.mem.[1]",
+                "top.mem.[2]",
+                "top.mem.[3]",
+                "top.bool_mat.[0].[0]",
+                "top.bool_mat.[0].[1]",
+                "top.bool_mat.[0].[2]",
+                "top.bool_mat.[1].[0]",
+                "top.bool_mat.[1].[1]",
+                "top.bool_mat.[1].[2]",
+            ]
+        );
+
+        // the last dimension of a std_ulogic array turns into a bit-vector
+        for var in &vars[..4] {
+            assert_eq!(var.length(), Some(8));
+            let index = var.index().unwrap();
+            assert_eq!((index.msb(), index.lsb()), (7, 0));
+            assert_eq!(var.vhdl_type_name(&h), Some("mem_t"));
+        }
+        // rows are stored one after the other
+        let rows: Vec<_> = vectors
+            .iter()
+            .map(|v| (v.max().index(), v.bits()))
+            .collect();
+        assert_eq!(rows, [(7, 8), (15, 8), (23, 8), (31, 8)]);
+
+        for var in &vars[4..] {
+            assert_eq!(var.var_type(), VarType::Enum);
+            assert_eq!(var.vhdl_type_name(&h), Some("boolean"));
+        }
+        let signals: std::collections::HashSet<_> = vars.iter().map(|v| v.signal_ref()).collect();
+        assert_eq!(signals.len(), vars.len());
+    }
+
+    #[test]
+    fn test_scalar_subtypes() {
+        let mut types = Vec::new();
+        // 2: type state_t is (idle, run, done)
+        types.extend_from_slice(&[E8, str_id("state_t"), 3]);
+        types.extend_from_slice(&[str_id("idle"), str_id("run"), str_id("done")]);
+        // 3: subtype state_sub is state_t range idle to run
+        types.extend_from_slice(&[GhwRtik::SubtypeScalar as u8, str_id("state_sub"), 2]);
+        types.extend_from_slice(&[E8, 0, 1]);
+        // 4: subtype x01 is std_ulogic range 'X' to '1'
+        types.extend_from_slice(&[GhwRtik::SubtypeScalar as u8, str_id("x01"), 1]);
+        types.extend_from_slice(&[E8, 1, 3]);
+        // 5: integer
+        types.extend_from_slice(&[I32, str_id("integer")]);
+        // 6: subtype small_t is integer range 15 downto 0
+        types.extend_from_slice(&[GhwRtik::SubtypeScalar as u8, str_id("small_t"), 5]);
+        types.extend_from_slice(&[I32 | 0x80, 15, 0]);
+        // 7: type time is range ... units fs; ps = 1000 fs; end units
+        types.extend_from_slice(&[GhwRtik::TypeP64 as u8, str_id("time"), 2]);
+        types.extend_from_slice(&[str_id("fs"), 1, str_id("ps"), 0xe8, 0x07]);
+        // 8: subtype delay_t is time range 0 fs to 50 fs
+        types.extend_from_slice(&[GhwRtik::SubtypeScalar as u8, str_id("delay_t"), 7]);
+        types.extend_from_slice(&[GhwRtik::TypeP64 as u8, 0, 50]);
+        // 9: real
+        types.extend_from_slice(&[GhwRtik::TypeF64 as u8, str_id("real")]);
+        // 10: subtype volt_t is real range 0.0 to 5.0
+        types.extend_from_slice(&[GhwRtik::SubtypeScalar as u8, str_id("volt_t"), 9]);
+        types.push(GhwRtik::TypeF64 as u8);
+        types.extend_from_slice(&0.0f64.to_le_bytes());
+        types.extend_from_slice(&5.0f64.to_le_bytes());
+
+        let mut vars = Vec::new();
+        for (ii, (name, tpe)) in [("state", 3), ("x", 4), ("n", 6), ("t", 8), ("v", 10)]
+            .into_iter()
+            .enumerate()
+        {
+            vars.extend_from_slice(&[SIGNAL, str_id(name), tpe, ii as u8 + 1]);
+        }
+
+        let input = ghw_header(10, &types, 5, &vars);
+        let (_, h) = read_test_hierarchy(&input);
+        let vars: Vec<_> = h.iter_vars().collect();
+        let summary: Vec<_> = vars
+            .iter()
+            .map(|v| {
+                (
+                    v.name(&h),
+                    v.var_type(),
+                    v.length(),
+                    v.vhdl_type_name(&h).unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("state", VarType::Enum, Some(2), "state_sub"),
+                ("x", VarType::Wire, Some(1), "x01"),
+                ("n", VarType::Integer, Some(32), "small_t"),
+                ("t", VarType::Integer, Some(64), "delay_t"),
+                ("v", VarType::Real, None, "volt_t"),
+            ]
+        );
+
+        // values of an enum subtype are positions in the base enum
+        let (enum_name, literals) = vars[0].enum_type(&h).unwrap();
+        assert_eq!(enum_name, "state_t");
+        assert_eq!(literals, [("00", "idle"), ("01", "run"), ("10", "done")]);
+    }
+
+    #[test]
+    fn test_enum_indexed_arrays() {
+        let mut types = Vec::new();
+        // 2: type state_t is (idle, run, done)
+        types.extend_from_slice(&[E8, str_id("state_t"), 3]);
+        types.extend_from_slice(&[str_id("idle"), str_id("run"), str_id("done")]);
+        // 3: type slv_t is array (state_t range <>) of std_ulogic
+        types.extend_from_slice(&[GhwRtik::TypeArray as u8, str_id("slv_t"), 1, 1, 2]);
+        // 4: slv_t(idle to run)
+        types.extend_from_slice(&[GhwRtik::SubtypeArray as u8, 0, 3, E8, 0, 1]);
+        // 5: boolean
+        types.extend_from_slice(&[E8, str_id("boolean"), 2, str_id("false"), str_id("true")]);
+        // 6: type mem_t is array (state_t) of boolean
+        types.extend_from_slice(&[GhwRtik::TypeArray as u8, str_id("mem_t"), 5, 1, 2]);
+        // 7: mem_t(done downto run)
+        types.extend_from_slice(&[GhwRtik::SubtypeArray as u8, 0, 6, E8 | 0x80, 2, 1]);
+
+        let mut vars = vec![SIGNAL, str_id("a"), 4, 1, 2];
+        vars.extend_from_slice(&[SIGNAL, str_id("b"), 7, 3, 4]);
+        let input = ghw_header(7, &types, 2, &vars);
+        let (_, h) = read_test_hierarchy(&input);
+        let vars: Vec<_> = h
+            .iter_vars()
+            .map(|v| (v.full_name(&h), v.length()))
+            .collect();
+        assert_eq!(
+            vars,
+            [
+                ("top.a".to_string(), Some(2)),
+                ("top.b.[1]".to_string(), Some(1)),
+                ("top.b.[2]".to_string(), Some(1)),
+            ]
+        );
+    }
+
+    /// Declares vectors that share GHW signals: `b` is a slice of `a`, `a` is a slice of `c`,
+    /// `c` and `d` overlap and `e` is a single bit of `a`.
+    #[test]
+    fn test_overlapping_aliases() {
+        let mut types = Vec::new();
+        // 2: integer
+        types.extend_from_slice(&[I32, str_id("integer")]);
+        // 3: subtype idx_t is integer range 0 to 31
+        types.extend_from_slice(&[GhwRtik::SubtypeScalar as u8, str_id("idx_t"), 2, I32, 0, 31]);
+        // 4: type slv_t is array (idx_t range <>) of std_ulogic
+        types.extend_from_slice(&[GhwRtik::TypeArray as u8, str_id("slv_t"), 1, 1, 3]);
+        // 5 - 7: slv_t(7 downto 0), slv_t(3 downto 0) and slv_t(11 downto 0)
+        for msb in [7, 3, 11] {
+            types.extend_from_slice(&[GhwRtik::SubtypeArray as u8, 0, 4, I32 | 0x80, msb, 0]);
+        }
+
+        let mut vars = Vec::new();
+        for (name, tpe, ids) in [
+            ("a", 5, 1..=8u8),
+            ("b", 6, 3..=6),
+            ("c", 7, 1..=12),
+            ("d", 5, 9..=16),
+            ("e", 1, 2..=2),
+            ("f", 1, 17..=17),
+        ] {
+            vars.extend_from_slice(&[SIGNAL, str_id(name), tpe]);
+            vars.extend(ids);
+        }
+
+        let mut input = GHW_HEADER_START.to_vec();
+        input.extend_from_slice(&[16, 0, 1, 1, 4, 0, 0]);
+        input.extend(ghw_header(7, &types, 6, &vars));
+        // initial values: '1' for odd and '0' for even signal ids
+        input.extend_from_slice(GHW_SNAPSHOT_SECTION);
+        input.extend_from_slice(&[0, 0, 0, 0]);
+        input.extend_from_slice(&0i64.to_le_bytes());
+        input.extend((1..=17).map(|id| if id % 2 == 1 { 3u8 } else { 2 }));
+        input.extend_from_slice(GHW_END_SNAPSHOT_SECTION);
+        // at 10fs, signal 2 changes to '1' and signal 9 changes to 'Z'
+        input.extend_from_slice(GHW_CYCLE_SECTION);
+        input.extend_from_slice(&10i64.to_le_bytes());
+        input.extend_from_slice(&[2, 3, 7, 4, 0, 0x7f]);
+        input.extend_from_slice(GHW_END_CYCLE_SECTION);
+        input.extend_from_slice(GHW_TAILER_SECTION);
+
+        let mut waves = crate::ghw::read_from_bytes(input).unwrap();
+        let h = waves.hierarchy();
+        let vars: Vec<_> = h
+            .iter_vars()
+            .map(|v| (v.name(h).to_string(), v.signal_ref()))
+            .collect();
+        let ids: Vec<_> = vars.iter().map(|(_, id)| *id).collect();
+        waves.load_signals(&ids).unwrap();
+
+        let values: Vec<_> = vars
+            .iter()
+            .map(|(name, id)| {
+                let signal = waves.get_signal(*id).unwrap();
+                let at = |idx| {
+                    let offset = signal.get_offset(idx).unwrap();
+                    signal
+                        .get_value_at(&offset, offset.elements - 1)
+                        .to_string()
+                };
+                (name.as_str(), at(0), at(1))
+            })
+            .collect();
+        assert_eq!(
+            values,
+            [
+                ("a", "10101010".into(), "11101010".into()),
+                ("b", "1010".into(), "1010".into()),
+                ("c", "101010101010".into(), "11101010z010".into()),
+                ("d", "10101010".into(), "z0101010".into()),
+                ("e", "0".into(), "1".into()),
+                ("f", "1".into(), "1".into()),
+            ]
+        );
+    }
+}
@@ -6,38 +6,456 @@ mod common;
 mod hierarchy;
 mod signals;
 
-use crate::{Waveform, WellenError};
-use std::io::{BufRead, Seek, SeekFrom};
+use crate::ghw::common::{GhwDecodeInfo, HeaderData};
+use crate::ghw::common::{
+    GHW_BZIP2_HEADER, GHW_CYCLE_SECTION, GHW_DIRECTORY_SECTION, GHW_GZIP_HEADER,
+    GHW_HIERARCHY_SECTION, GHW_SNAPSHOT_SECTION, GHW_STRING_SECTION, GHW_TYPE_SECTION,
+    GHW_WK_TYPE_SECTION,
+};
+use crate::loader::{BodyLoader, BodyLoaderKind, SharedBytes};
+use crate::progress::{Progress, ProgressReader};
+use crate::{Hierarchy, LoadOptions, LoadPhase, Time, Waveform, WellenError};
+use std::io::{BufRead, Read, Seek, SeekFrom};
+use std::ops::Range;
 
 /// Checks header to see if we are dealing with a GHW file.
 pub(crate) fn is_ghw(input: &mut (impl BufRead + Seek)) -> bool {
-    let is_ghw = hierarchy::read_ghw_header(input).is_ok();
+    let is_ghw = match detect_compression(input) {
+        Ok(Some(compression)) => {
+            hierarchy::read_ghw_header(&mut Decompressor::new(&mut *input, compression)).is_ok()
+        }
+        Ok(None) => hierarchy::read_ghw_header(input).is_ok(),
+        Err(_) => false,
+    };
     // try to reset input
     let _ = input.seek(SeekFrom::Start(0));
     is_ghw
 }
 
 pub fn read(filename: &str) -> Result<Waveform, WellenError> {
-    let f = std::fs::File::open(filename)?;
-    let mut input = std::io::BufReader::new(f);
-    read_internal(&mut input)
+    read_with_options(filename, LoadOptions::default())
 }
 
 pub fn read_from_bytes(bytes: Vec<u8>) -> Result<Waveform, WellenError> {
+    read_from_bytes_with_options(bytes, LoadOptions::default())
+}
+
+pub fn read_with_options(filename: &str, options: LoadOptions) -> Result<Waveform, WellenError> {
+    // load file into memory (lazily)
+    let input_file = std::fs::File::open(filename)?;
+    let mmap = unsafe { memmap2::Mmap::map(&input_file)? };
+    read_from_slice(&mmap[..], &options, None)
+}
+
+pub fn read_from_bytes_with_options(
+    bytes: Vec<u8>,
+    options: LoadOptions,
+) -> Result<Waveform, WellenError> {
+    read_from_slice(&bytes, &options, None)
+}
+
+/// Loads only the signal values that are needed to display the time interval `range`.
+/// Since the GHW directory only records where the signal values start, all values before
+/// the start of the range still need to be scanned linearly. They are skipped without being
+/// decoded, which is cheaper than a full load, but the cost still grows with `range.start`.
+/// The part of the file that comes after the end of the range is never read.
+/// The time table starts with the last time step before `range.start` which holds the
+/// values of all signals at that point.
+pub fn read_time_range(
+    filename: &str,
+    options: LoadOptions,
+    range: Range<Time>,
+) -> Result<Waveform, WellenError> {
+    let input_file = std::fs::File::open(filename)?;
+    let mmap = unsafe { memmap2::Mmap::map(&input_file)? };
+    read_from_slice(&mmap[..], &options, Some(range))
+}
+
+/// Uncompressed inputs that are completely in memory can be decoded using multiple threads
+/// or restricted to a time range.
+fn read_from_slice(
+    bytes: &[u8],
+    options: &LoadOptions,
+    range: Option<Range<Time>>,
+) -> Result<Waveform, WellenError> {
+    options
+        .progress()
+        .map_result(read_from_slice_internal(bytes, options, range))
+}
+
+fn read_from_slice_internal(
+    bytes: &[u8],
+    options: &LoadOptions,
+    range: Option<Range<Time>>,
+) -> Result<Waveform, WellenError> {
+    let progress = options.progress();
     let mut input = std::io::Cursor::new(bytes);
-    read_internal(&mut input)
+    if let Some(compression) = detect_compression(&mut input)? {
+        return match range {
+            None => read_internal(&mut input, options),
+            Some(range) => {
+                // random access requires the uncompressed file
+                progress.start_phase(LoadPhase::Header, bytes.len() as u64)?;
+                let input = ProgressReader::new(input, options.progress.clone());
+                let mut uncompressed = Vec::new();
+                Decompressor::new(input, compression).read_to_end(&mut uncompressed)?;
+                read_from_slice_internal(&uncompressed, options, Some(range))
+            }
+        };
+    }
+
+    let (header, decode_info, hierarchy, body) = read_hierarchy_from_slice(bytes, options)?;
+    let wave_mem = read_signals_from_slice(
+        &header,
+        decode_info,
+        &hierarchy,
+        &bytes[body],
+        options,
+        range,
+    )?;
+    Ok(Waveform::new(hierarchy, wave_mem))
+}
+
+/// Parses the hierarchy of an uncompressed GHW file and returns the position of the signal values.
+fn read_hierarchy_from_slice(
+    bytes: &[u8],
+    options: &LoadOptions,
+) -> Result<(HeaderData, GhwDecodeInfo, Hierarchy, Range<usize>), WellenError> {
+    options
+        .progress()
+        .start_phase(LoadPhase::Header, bytes.len() as u64)?;
+    let mut input = ProgressReader::new(std::io::Cursor::new(bytes), options.progress.clone());
+    let header = hierarchy::read_ghw_header(&mut input)?;
+    let index = SectionIndex::read(&header, &mut input)?;
+    index.seek_to_hierarchy(&mut input)?;
+    let (decode_info, hierarchy) = hierarchy::read_hierarchy(&header, &mut input, options)?;
+    index.seek_to_body(&mut input)?;
+    let body_start = input.stream_position()? as usize;
+    let body_end = index
+        .body_end
+        .map(|pos| pos as usize)
+        .filter(|pos| *pos >= body_start && *pos <= bytes.len())
+        .unwrap_or(bytes.len());
+    Ok((header, decode_info, hierarchy, body_start..body_end))
+}
+
+fn read_signals_from_slice(
+    header: &HeaderData,
+    decode_info: GhwDecodeInfo,
+    hierarchy: &Hierarchy,
+    body: &[u8],
+    options: &LoadOptions,
+    range: Option<Range<Time>>,
+) -> Result<Box<crate::wavemem::Reader>, WellenError> {
+    options
+        .progress()
+        .start_phase(LoadPhase::Body, body.len() as u64)?;
+    let progress = options.progress.clone();
+    let wave_mem = match range {
+        Some(range) => {
+            signals::read_signals_in_range(header, decode_info, hierarchy, body, range, progress)?
+        }
+        None => {
+            let num_chunks = if options.multi_thread {
+                rayon::current_num_threads()
+            } else {
+                1
+            };
+            signals::read_signals_from_bytes(
+                header,
+                decode_info,
+                hierarchy,
+                body,
+                num_chunks,
+                progress,
+            )?
+        }
+    };
+    Ok(wave_mem)
+}
+
+/// Only parses the hierarchy. The signal values can be parsed later, e.g., on a different thread.
+pub fn read_header(
+    filename: &str,
+    options: LoadOptions,
+) -> Result<(Hierarchy, BodyLoader), WellenError> {
+    let bytes = SharedBytes::map_file(filename)?;
+    let progress = options.progress();
+    let (hierarchy, body) = progress.map_result(read_header_internal(bytes, options.clone()))?;
+    Ok((hierarchy, BodyLoader::new(BodyLoaderKind::Ghw(body))))
+}
+
+fn read_header_internal(
+    bytes: SharedBytes,
+    options: LoadOptions,
+) -> Result<(Hierarchy, GhwBody), WellenError> {
+    let mut input = std::io::Cursor::new(bytes.clone());
+    let (header, decode_info, hierarchy, input) = match detect_compression(&mut input)? {
+        Some(compression) => {
+            let len = bytes.as_ref().len() as u64;
+            options.progress().start_phase(LoadPhase::Header, len)?;
+            let input = ProgressReader::new(input, options.progress.clone());
+            let mut input = Decompressor::new(input, compression);
+            let header = hierarchy::read_ghw_header(&mut input)?;
+            let (decode_info, hierarchy) =
+                hierarchy::read_hierarchy(&header, &mut input, &options)?;
+            let input = GhwBodyInput::Compressed(Box::new(input));
+            (header, decode_info, hierarchy, input)
+        }
+        None => {
+            let (header, decode_info, hierarchy, body) =
+                read_hierarchy_from_slice(bytes.as_ref(), &options)?;
+            (
+                header,
+                decode_info,
+                hierarchy,
+                GhwBodyInput::Bytes(bytes, body),
+            )
+        }
+    };
+    let body = GhwBody {
+        header,
+        decode_info,
+        input,
+        options,
+    };
+    Ok((hierarchy, body))
+}
+
+/// The signal values of a GHW file whose hierarchy was already read.
+pub(crate) struct GhwBody {
+    header: HeaderData,
+    decode_info: GhwDecodeInfo,
+    input: GhwBodyInput,
+    options: LoadOptions,
+}
+
+enum GhwBodyInput {
+    Bytes(SharedBytes, Range<usize>),
+    /// decompressor that is positioned right after the hierarchy
+    Compressed(Box<Decompressor<ProgressReader<std::io::Cursor<SharedBytes>>>>),
+}
+
+impl GhwBody {
+    pub(crate) fn load(
+        self,
+        hierarchy: &Hierarchy,
+    ) -> Result<Box<dyn crate::signals::SignalSource + Send + Sync>, WellenError> {
+        let progress = self.options.progress.clone();
+        Progress::new(progress.as_deref()).map_result(self.load_internal(hierarchy))
+    }
+
+    fn load_internal(
+        self,
+        hierarchy: &Hierarchy,
+    ) -> Result<Box<dyn crate::signals::SignalSource + Send + Sync>, WellenError> {
+        let Self {
+            header,
+            decode_info,
+            input,
+            options,
+        } = self;
+        let wave_mem = match input {
+            GhwBodyInput::Bytes(bytes, body) => read_signals_from_slice(
+                &header,
+                decode_info,
+                hierarchy,
+                &bytes.as_ref()[body],
+                &options,
+                None,
+            )?,
+            GhwBodyInput::Compressed(mut input) => {
+                // progress is measured in compressed bytes
+                let compressed = input.get_ref().get_ref();
+                let remaining = compressed.get_ref().as_ref().len() as u64 - compressed.position();
+                options.progress().start_phase(LoadPhase::Body, remaining)?;
+                signals::read_signals(&header, decode_info, hierarchy, &mut input)?
+            }
+        };
+        Ok(wave_mem)
+    }
 }
 
-fn read_internal(input: &mut (impl BufRead + Seek)) -> std::result::Result<Waveform, WellenError> {
-    let header = hierarchy::read_ghw_header(input)?;
-    let header_len = input.stream_position()?;
+pub(crate) fn read_from_reader(
+    mut input: impl BufRead + Seek,
+    options: LoadOptions,
+) -> Result<Waveform, WellenError> {
+    options
+        .progress()
+        .map_result(read_internal(&mut input, &options))
+}
+
+/// GHDL can optionally compress the whole GHW file.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Compression {
+    Gzip,
+    Bzip2,
+}
+
+/// Looks at the magic bytes without consuming any input.
+fn detect_compression(input: &mut impl BufRead) -> std::io::Result<Option<Compression>> {
+    let start = input.fill_buf()?;
+    if start.starts_with(GHW_GZIP_HEADER) {
+        Ok(Some(Compression::Gzip))
+    } else if start.starts_with(GHW_BZIP2_HEADER) {
+        Ok(Some(Compression::Bzip2))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Decompresses the input on the fly.
+enum Decompressor<R: BufRead> {
+    Gzip(std::io::BufReader<flate2::bufread::MultiGzDecoder<R>>),
+    Bzip2(std::io::BufReader<bzip2::bufread::MultiBzDecoder<R>>),
+}
 
-    // currently we do read the directory, however we are not using it yet
-    let _sections = hierarchy::try_read_directory(&header, input)?;
-    input.seek(SeekFrom::Start(header_len))?;
-    // TODO: use actual section positions
+impl<R: BufRead> Decompressor<R> {
+    fn new(input: R, compression: Compression) -> Self {
+        match compression {
+            Compression::Gzip => Self::Gzip(std::io::BufReader::new(
+                flate2::bufread::MultiGzDecoder::new(input),
+            )),
+            Compression::Bzip2 => Self::Bzip2(std::io::BufReader::new(
+                bzip2::bufread::MultiBzDecoder::new(input),
+            )),
+        }
+    }
+
+    /// Returns the compressed input.
+    fn get_ref(&self) -> &R {
+        match self {
+            Self::Gzip(inner) => inner.get_ref().get_ref(),
+            Self::Bzip2(inner) => inner.get_ref().get_ref(),
+        }
+    }
+}
+
+impl<R: BufRead> Read for Decompressor<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            Self::Gzip(inner) => inner.read(buf),
+            Self::Bzip2(inner) => inner.read(buf),
+        }
+    }
+}
+
+impl<R: BufRead> BufRead for Decompressor<R> {
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        match self {
+            Self::Gzip(inner) => inner.fill_buf(),
+            Self::Bzip2(inner) => inner.fill_buf(),
+        }
+    }
+
+    fn consume(&mut self, amt: usize) {
+        match self {
+            Self::Gzip(inner) => inner.consume(amt),
+            Self::Bzip2(inner) => inner.consume(amt),
+        }
+    }
+}
+
+fn read_internal(
+    input: &mut (impl BufRead + Seek),
+    options: &LoadOptions,
+) -> std::result::Result<Waveform, WellenError> {
+    let progress = options.progress();
+    let start = input.stream_position()?;
+    let len = input.seek(SeekFrom::End(0))? - start;
+    input.seek(SeekFrom::Start(start))?;
+
+    if let Some(compression) = detect_compression(input)? {
+        // Compressed files are decoded on the fly, without using the directory.
+        // Progress is measured in compressed bytes for the file as a whole.
+        progress.start_phase(LoadPhase::Body, len)?;
+        let input = ProgressReader::new(input, options.progress.clone());
+        let mut input = Decompressor::new(input, compression);
+        let header = hierarchy::read_ghw_header(&mut input)?;
+        return read_hierarchy_and_signals(&header, &mut input, options);
+    }
+
+    progress.start_phase(LoadPhase::Header, len)?;
+    let mut header_input = ProgressReader::new(&mut *input, options.progress.clone());
+    let header = hierarchy::read_ghw_header(&mut header_input)?;
+    let index = SectionIndex::read(&header, &mut header_input)?;
+    index.seek_to_hierarchy(&mut header_input)?;
+    let (decode_info, hierarchy) = hierarchy::read_hierarchy(&header, &mut header_input, options)?;
+    index.seek_to_body(&mut header_input)?;
+    drop(header_input);
+
+    let body_start = input.stream_position()?;
+    progress.start_phase(LoadPhase::Body, (start + len).saturating_sub(body_start))?;
+    let mut input = ProgressReader::new(input, options.progress.clone());
+    let wave_mem = signals::read_signals(&header, decode_info, &hierarchy, &mut input)?;
+    Ok(Waveform::new(hierarchy, wave_mem))
+}
+
+/// Section positions as recorded in the directory at the end of the GHW file.
+/// GHDL only lists the first snapshot and cycle sections, thus we can use the
+/// directory to locate the start, but not any later part of the signal values.
+#[derive(Debug, Default)]
+struct SectionIndex {
+    hierarchy_start: Option<u64>,
+    body_start: Option<u64>,
+    /// the signal values end where the directory starts
+    body_end: Option<u64>,
+}
+
+impl SectionIndex {
+    /// Reads the directory, if there is one. Input will be positioned right after the header.
+    fn read(
+        header: &common::HeaderData,
+        input: &mut (impl BufRead + Seek),
+    ) -> common::Result<Self> {
+        let header_len = input.stream_position()?;
+        let sections = hierarchy::try_read_directory(header, input)?.unwrap_or_default();
+        input.seek(SeekFrom::Start(header_len))?;
+
+        let first_of = |ids: &[&[u8; 4]]| {
+            sections
+                .iter()
+                .filter(|s| ids.contains(&&s.id))
+                .map(|s| s.pos as u64)
+                .min()
+        };
+        Ok(Self {
+            hierarchy_start: first_of(&[
+                GHW_STRING_SECTION,
+                GHW_TYPE_SECTION,
+                GHW_WK_TYPE_SECTION,
+                GHW_HIERARCHY_SECTION,
+            ]),
+            body_start: first_of(&[GHW_SNAPSHOT_SECTION, GHW_CYCLE_SECTION]),
+            body_end: first_of(&[GHW_DIRECTORY_SECTION]),
+        })
+    }
+
+    fn seek_to_hierarchy(&self, input: &mut impl Seek) -> std::io::Result<()> {
+        if let Some(pos) = self.hierarchy_start {
+            input.seek(SeekFrom::Start(pos))?;
+        }
+        Ok(())
+    }
+
+    /// Skips over anything between the end of the hierarchy and the first signal values.
+    fn seek_to_body(&self, input: &mut impl Seek) -> std::io::Result<()> {
+        if let Some(pos) = self.body_start {
+            // the directory could be corrupted, we never want to go back into the hierarchy
+            if pos >= input.stream_position()? {
+                input.seek(SeekFrom::Start(pos))?;
+            }
+        }
+        Ok(())
+    }
+}
 
-    let (decode_info, hierarchy) = hierarchy::read_hierarchy(&header, input)?;
-    let wave_mem = signals::read_signals(&header, decode_info, &hierarchy, input)?;
+fn read_hierarchy_and_signals(
+    header: &common::HeaderData,
+    input: &mut impl BufRead,
+    options: &LoadOptions,
+) -> std::result::Result<Waveform, WellenError> {
+    let (decode_info, hierarchy) = hierarchy::read_hierarchy(header, input, options)?;
+    let wave_mem = signals::read_signals(header, decode_info, &hierarchy, input)?;
     Ok(Waveform::new(hierarchy, wave_mem))
 }
@@ -3,9 +3,12 @@
 // author: Kevin Laeufer <laeufer@berkeley.edu>
 
 use crate::ghw::common::*;
+use crate::progress::{Progress, ProgressReader};
 use crate::wavemem::{Encoder, States};
-use crate::{Hierarchy, SignalRef};
-use std::io::BufRead;
+use crate::{Hierarchy, LoadPhase, LoadProgress, SignalRef};
+use rayon::prelude::*;
+use std::io::{BufRead, Read};
+use std::sync::Arc;
 
 /// Reads the GHW signal values. `input` should be advanced until right after the end of hierarchy
 pub(crate) fn read_signals(
@@ -15,21 +18,84 @@ pub(crate) fn read_signals(
     input: &mut impl BufRead,
 ) -> Result<Box<crate::wavemem::Reader>> {
     let (info, vectors) = decode_info;
-    // TODO: multi-threading
-    let mut encoder = Encoder::new(hierarchy);
+    let mut encoder = Encoder::with_signal_mask(hierarchy, info.signal_mask());
     let mut vecs = VecBuffer::from_vec_info(vectors);
+    read_sections(header, &info, &mut vecs, &mut encoder, input)?;
+    Ok(Box::new(encoder.finish()))
+}
+
+/// Reads the GHW signal values from a `body` that starts right after the end of the hierarchy.
+/// The body is split into up to `num_chunks` parts which are decoded in parallel.
+pub(crate) fn read_signals_from_bytes(
+    header: &HeaderData,
+    decode_info: GhwDecodeInfo,
+    hierarchy: &Hierarchy,
+    body: &[u8],
+    num_chunks: usize,
+    progress: Option<Arc<LoadProgress>>,
+) -> Result<Box<crate::wavemem::Reader>> {
+    if num_chunks <= 1 {
+        let mut input = ProgressReader::new(body, progress);
+        return read_signals(header, decode_info, hierarchy, &mut input);
+    }
+    let (info, vectors) = decode_info;
+    let chunks = find_chunks(header, &info, body, num_chunks)?;
+
+    // decode chunks in parallel
+    let encoders: Vec<Encoder> = chunks
+        .par_iter()
+        .map(|chunk| {
+            let mut encoder = Encoder::with_signal_mask(hierarchy, info.signal_mask());
+            let mut vecs = VecBuffer::from_vec_info(vectors.clone());
+            vecs.restore_bits(&info, &chunk.bits);
+            let mut input = ProgressReader::new(&body[chunk.start..chunk.end], progress.clone());
+            read_chunk(
+                header,
+                &info,
+                &mut vecs,
+                &mut encoder,
+                &mut input,
+                chunk.cycle_time,
+            )?;
+            Ok(encoder)
+        })
+        .collect::<Result<_>>()?;
+
+    // combine encoders
+    let progress = Progress::new(progress.as_deref());
+    progress
+        .start_phase(LoadPhase::Merge, encoders.len() as u64)
+        .map_err(|_| GhwParseError::Cancelled)?;
+    let mut encoder_iter = encoders.into_iter();
+    let mut encoder = encoder_iter.next().unwrap();
+    progress.add_done(1);
+    for other in encoder_iter {
+        encoder.append(other);
+        progress.add_done(1);
+    }
+    Ok(Box::new(encoder.finish()))
+}
 
+fn read_sections(
+    header: &HeaderData,
+    info: &GhwSignals,
+    vecs: &mut VecBuffer,
+    encoder: &mut Encoder,
+    input: &mut impl BufRead,
+) -> Result<()> {
     // loop over signal sections
     loop {
+        // the body might end without a tailer, e.g., when GHDL was still running
+        if input.fill_buf()?.is_empty() {
+            break;
+        }
         let mut mark = [0u8; 4];
         input.read_exact(&mut mark)?;
 
         // read_sm_hdr
         match &mark {
-            GHW_SNAPSHOT_SECTION => {
-                read_snapshot_section(header, &info, &mut vecs, &mut encoder, input)?
-            }
-            GHW_CYCLE_SECTION => read_cycle_section(header, &info, &mut vecs, &mut encoder, input)?,
+            GHW_SNAPSHOT_SECTION => read_snapshot_section(header, info, vecs, encoder, input)?,
+            GHW_CYCLE_SECTION => read_cycle_section(header, info, vecs, encoder, input)?,
             GHW_DIRECTORY_SECTION => {
                 // skip the directory by reading it
                 let _ = read_directory(header, input)?;
@@ -45,9 +111,264 @@ pub(crate) fn read_signals(
             }
         }
     }
+    Ok(())
+}
+
+/// A part of the GHW body that can be decoded independently of all other parts.
+#[derive(Debug)]
+struct BodyChunk {
+    start: usize,
+    end: usize,
+    /// Time of the first time step, if the chunk starts in the middle of a cycle section.
+    cycle_time: Option<u64>,
+    /// Values of all bits that belong to a vector at the start of the chunk.
+    bits: Vec<u8>,
+}
+
+/// Splits the body into chunks of roughly the same size. We need to skip over all values
+/// in order to find time steps to split at and to track the state of all vectors.
+fn find_chunks(
+    header: &HeaderData,
+    info: &GhwSignals,
+    body: &[u8],
+    num_chunks: usize,
+) -> Result<Vec<BodyChunk>> {
+    let min_chunk_len = body.len() / num_chunks;
+    let mut chunks = Vec::with_capacity(num_chunks);
+    let mut skimmer = BodySkimmer::new(info, body);
+    let mut chunk = BodyChunk {
+        start: 0,
+        end: body.len(),
+        cycle_time: None,
+        bits: vec![0u8; info.signal_len()],
+    };
+    while chunks.len() + 1 < num_chunks
+        && skimmer.skim_until(header, info, |time, prev_time, pos| {
+            let is_new_time = prev_time.is_some_and(|prev| time > prev);
+            is_new_time && pos - chunk.start >= min_chunk_len
+        })?
+    {
+        let next = BodyChunk {
+            start: skimmer.pos,
+            end: body.len(),
+            cycle_time: skimmer.cycle_time,
+            bits: skimmer.vec_bits(info),
+        };
+        chunk.end = skimmer.pos;
+        chunks.push(std::mem::replace(&mut chunk, next));
+    }
+    // the last chunk contains the remaining body
+    chunks.push(chunk);
+    Ok(chunks)
+}
+
+/// Decodes a single chunk. `vecs` needs to reflect the state of all vectors at the chunk start.
+fn read_chunk(
+    header: &HeaderData,
+    info: &GhwSignals,
+    vecs: &mut VecBuffer,
+    encoder: &mut Encoder,
+    input: &mut impl BufRead,
+    cycle_time: Option<u64>,
+) -> Result<()> {
+    if let Some(time) = cycle_time {
+        if read_time_steps(info, vecs, encoder, time, input)? {
+            check_magic_end(input, "cycle", GHW_END_CYCLE_SECTION)?;
+        }
+    }
+    read_sections(header, info, vecs, encoder, input)
+}
+
+/// Reads only the signal values that are needed to display the time interval `range`.
+/// All values before the start of the range are scanned linearly, but skipped without being
+/// decoded. We stop reading as soon as we reach the end of the range.
+/// The state of all signals before the range is reported at the time step that
+/// precedes the range start.
+pub(crate) fn read_signals_in_range(
+    header: &HeaderData,
+    decode_info: GhwDecodeInfo,
+    hierarchy: &Hierarchy,
+    body: &[u8],
+    range: std::ops::Range<u64>,
+    progress: Option<Arc<LoadProgress>>,
+) -> Result<Box<crate::wavemem::Reader>> {
+    let (info, vectors) = decode_info;
+    let mut skimmer = BodySkimmer::new(&info, body);
+    skimmer.skim_until(header, &info, |time, _, _| time >= range.start)?;
+    let (start, cycle_time, prev_time) = (skimmer.pos, skimmer.cycle_time, skimmer.prev_time);
+    let value_pos = skimmer.value_pos.clone();
+    skimmer.skim_until(header, &info, |time, _, _| time >= range.end)?;
+    let end = skimmer.pos;
+
+    let mut encoder = Encoder::with_signal_mask(hierarchy, info.signal_mask());
+    let mut vecs = VecBuffer::from_vec_info(vectors);
+    if let Some(time) = prev_time {
+        encoder.time_change(time);
+        for (index, pos) in value_pos.iter().enumerate() {
+            if let Some(pos) = pos {
+                let signal_id = GhwSignalId::new(index as u32 + 1);
+                let mut input = &body[*pos..];
+                read_signal_value(&info, signal_id, &mut vecs, &mut encoder, &mut input)?;
+            }
+        }
+        finish_time_step(&mut vecs, &mut encoder);
+    }
+    let mut input = ProgressReader::new(&body[start..end], progress);
+    read_chunk(
+        header,
+        &info,
+        &mut vecs,
+        &mut encoder,
+        &mut input,
+        cycle_time,
+    )?;
     Ok(Box::new(encoder.finish()))
 }
 
+/// Walks over the signal values without decoding them in order to find time steps at which
+/// decoding can be started. Remembers where the latest value of every signal is stored.
+struct BodySkimmer<'a> {
+    body: &'a [u8],
+    /// current position in the body
+    pos: usize,
+    /// Time of the next time step, if we stopped in the middle of a cycle section.
+    cycle_time: Option<u64>,
+    /// Time of the last time step we skimmed over.
+    prev_time: Option<u64>,
+    /// Position of the latest value of each signal.
+    value_pos: Vec<Option<usize>>,
+}
+
+impl<'a> BodySkimmer<'a> {
+    fn new(info: &GhwSignals, body: &'a [u8]) -> Self {
+        Self {
+            body,
+            pos: 0,
+            cycle_time: None,
+            prev_time: None,
+            value_pos: vec![None; info.signal_len()],
+        }
+    }
+
+    /// Skims over time steps until `stop(time, prev_time, pos)` returns true for the
+    /// start of a time step inside a cycle section. Returns false if we reached the end of
+    /// the body instead. A subsequent call continues with the time step we stopped at.
+    fn skim_until(
+        &mut self,
+        header: &HeaderData,
+        info: &GhwSignals,
+        mut stop: impl FnMut(u64, Option<u64>, usize) -> bool,
+    ) -> Result<bool> {
+        let mut input = &self.body[self.pos..];
+        let mut resumed = self.cycle_time.is_some();
+        loop {
+            let pos = self.body.len() - input.len();
+            if let Some(time) = self.cycle_time {
+                if !resumed && stop(time, self.prev_time, pos) {
+                    self.pos = pos;
+                    return Ok(true);
+                }
+                resumed = false;
+                let mut pos_signal_index = 0;
+                loop {
+                    let delta = leb128::read::unsigned(&mut input)? as usize;
+                    if delta == 0 {
+                        break;
+                    }
+                    pos_signal_index += delta;
+                    self.skip_signal_value(info, pos_signal_index, &mut input)?;
+                }
+                self.prev_time = Some(time);
+                let time_delta = leb128::read::signed(&mut input)?;
+                if time_delta < 0 {
+                    self.cycle_time = None;
+                    check_magic_end(&mut input, "cycle", GHW_END_CYCLE_SECTION)?;
+                } else {
+                    self.cycle_time = Some(time + time_delta as u64);
+                }
+                continue;
+            }
+
+            if input.is_empty() {
+                break;
+            }
+            let mut mark = [0u8; 4];
+            input.read_exact(&mut mark)?;
+            match &mark {
+                GHW_SNAPSHOT_SECTION => {
+                    let mut h = [0u8; 12];
+                    input.read_exact(&mut h)?;
+                    check_header_zeros("snapshot", &h)?;
+                    for sig_index in 0..info.signal_len() {
+                        self.skip_signal_value(info, sig_index + 1, &mut input)?;
+                    }
+                    check_magic_end(&mut input, "snapshot", GHW_END_SNAPSHOT_SECTION)?;
+                    self.prev_time = Some(header.read_i64(&mut &h[4..12])? as u64);
+                }
+                GHW_CYCLE_SECTION => {
+                    let mut h = [0u8; 8];
+                    input.read_exact(&mut h)?;
+                    self.cycle_time = Some(header.read_i64(&mut &h[..])? as u64);
+                }
+                GHW_DIRECTORY_SECTION => {
+                    let _ = read_directory(header, &mut input)?;
+                }
+                GHW_TAILER_SECTION => break,
+                other => {
+                    return Err(GhwParseError::UnexpectedSection(
+                        String::from_utf8_lossy(other).to_string(),
+                    ))
+                }
+            }
+        }
+        self.pos = self.body.len();
+        Ok(false)
+    }
+
+    /// Reads over a signal value and remembers where it is stored.
+    fn skip_signal_value(
+        &mut self,
+        info: &GhwSignals,
+        signal_index: usize,
+        input: &mut &[u8],
+    ) -> Result<()> {
+        let signal_id = GhwSignalId::new(signal_index as u32);
+        self.value_pos[signal_id.index()] = Some(self.body.len() - input.len());
+        match info.get_info(signal_id).tpe() {
+            SignalType::NineState
+            | SignalType::TwoState
+            | SignalType::U8
+            | SignalType::NineStateVec
+            | SignalType::TwoStateVec => {
+                let _ = read_u8(input)?;
+            }
+            SignalType::Leb128Signed => {
+                let _ = leb128::read::signed(input)?;
+            }
+            SignalType::F64 => {
+                let _ = read_f64_le(input)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the current value of all bits that are part of a vector.
+    fn vec_bits(&self, info: &GhwSignals) -> Vec<u8> {
+        let mut bits = vec![0u8; info.signal_len()];
+        for (index, pos) in self.value_pos.iter().enumerate() {
+            if let Some(pos) = pos {
+                let value = self.body[*pos];
+                let signal_id = GhwSignalId::new(index as u32 + 1);
+                bits[index] = match info.get_info(signal_id).tpe() {
+                    SignalType::NineStateVec => STD_LOGIC_LUT[value as usize],
+                    _ => value,
+                };
+            }
+        }
+        bits
+    }
+}
+
 fn read_snapshot_section(
     header: &HeaderData,
     info: &GhwSignals,
@@ -85,25 +406,40 @@ fn read_cycle_section(
     // note: cycle sections do not have the four zero bytes!
 
     // time in femto seconds
-    let mut start_time = header.read_i64(&mut &h[..])? as u64;
+    let start_time = header.read_i64(&mut &h[..])? as u64;
 
+    if read_time_steps(info, vecs, enc, start_time, input)? {
+        // check cycle end
+        check_magic_end(input, "cycle", GHW_END_CYCLE_SECTION)?;
+    }
+
+    Ok(())
+}
+
+/// Reads time steps until the end of the cycle section. Returns false if the input ends
+/// before the cycle section does, which happens at the end of a chunk.
+fn read_time_steps(
+    info: &GhwSignals,
+    vecs: &mut VecBuffer,
+    enc: &mut Encoder,
+    mut time: u64,
+    input: &mut impl BufRead,
+) -> Result<bool> {
     loop {
-        enc.time_change(start_time);
+        enc.time_change(time);
         read_cycle_signals(info, vecs, enc, input)?;
         finish_time_step(vecs, enc);
 
         let time_delta = leb128::read::signed(input)?;
         if time_delta < 0 {
-            break; // end of cycle
+            return Ok(true); // end of cycle
         } else {
-            start_time += time_delta as u64;
+            time += time_delta as u64;
+        }
+        if input.fill_buf()?.is_empty() {
+            return Ok(false);
         }
     }
-
-    // check cycle end
-    check_magic_end(input, "cycle", GHW_END_CYCLE_SECTION)?;
-
-    Ok(())
 }
 
 fn read_cycle_signals(
@@ -284,6 +620,18 @@ impl VecBuffer {
         }
     }
 
+    /// Sets all vector bits to the values in `bits` without recording any changes.
+    fn restore_bits(&mut self, signals: &GhwSignals, bits: &[u8]) {
+        for (index, value) in bits.iter().enumerate() {
+            let signal_id = GhwSignalId::new(index as u32 + 1);
+            if let Some(vec_id) = signals.get_info(signal_id).vec_id() {
+                let info = &self.info[vec_id.index()];
+                let bit = info.max_index - signal_id.index() as u32;
+                Self::set_value(&mut self.data, info, bit, *value);
+            }
+        }
+    }
+
     fn process_changed_signals(&mut self, mut callback: impl FnMut(SignalRef, &[u8], States)) {
         let change_list = std::mem::take(&mut self.change_list);
         for vec_id in change_list.into_iter() {
@@ -599,6 +599,8 @@ pub struct Hierarchy {
     signal_idx_to_var: Vec<Option<VarRef>>,
     meta: HierarchyMetaData,
     slices: HashMap<SignalRef, SignalSlice>,
+    /// Signals that no variable refers to. They are only stored in order to be sliced.
+    hidden_signals: HashMap<SignalRef, SignalType>,
 }
 
 struct HierarchyMetaData {
@@ -733,8 +735,10 @@ impl Hierarchy {
     /// Retrieves the length of a signal identified by its id by looking up a
     /// variable that refers to the signal.
     pub(crate) fn get_signal_tpe(&self, signal_idx: SignalRef) -> Option<SignalType> {
-        let var_id = (*self.signal_idx_to_var.get(signal_idx.index())?)?;
-        Some(self.get(var_id).signal_tpe)
+        match self.signal_idx_to_var.get(signal_idx.index()) {
+            Some(Some(var_id)) => Some(self.get(*var_id).signal_tpe()),
+            _ => self.hidden_signals.get(&signal_idx).copied(),
+        }
     }
 
     pub(crate) fn get_slice_info(&self, signal_idx: SignalRef) -> Option<SignalSlice> {
@@ -806,6 +810,7 @@ pub struct HierarchyBuilder {
     handle_to_node: Vec<Option<VarRef>>,
     meta: HierarchyMetaData,
     slices: HashMap<SignalRef, SignalSlice>,
+    hidden_signals: HashMap<SignalRef, SignalType>,
 }
 
 const EMPTY_STRING: HierarchyStringId = HierarchyStringId(unsafe { NonZeroU32::new_unchecked(1) });
@@ -829,6 +834,7 @@ impl HierarchyBuilder {
             handle_to_node: Vec::default(),
             meta: HierarchyMetaData::new(file_type),
             slices: HashMap::default(),
+            hidden_signals: HashMap::default(),
         }
     }
 }
@@ -842,6 +848,7 @@ impl HierarchyBuilder {
         self.enums.shrink_to_fit();
         self.handle_to_node.shrink_to_fit();
         self.slices.shrink_to_fit();
+        self.hidden_signals.shrink_to_fit();
         Hierarchy {
             vars: self.vars,
             scopes: self.scopes,
@@ -852,6 +859,7 @@ impl HierarchyBuilder {
             signal_idx_to_var: self.handle_to_node,
             meta: self.meta,
             slices: self.slices,
+            hidden_signals: self.hidden_signals,
         }
     }
 
@@ -1070,7 +1078,7 @@ impl HierarchyBuilder {
         // for strings, the length is always flexible
         let signal_tpe = match tpe {
             VarType::String => SignalType::String,
-            VarType::Real => SignalType::Real,
+            VarType::Real | VarType::ShortReal => SignalType::Real,
             _ => SignalType::from_uint(raw_length, index),
         };
 
@@ -1145,6 +1153,20 @@ impl HierarchyBuilder {
             },
         );
     }
+
+    /// Declares a signal that is not referred to by any variable, but that other signals
+    /// can be a slice of.
+    pub(crate) fn add_hidden_signal(&mut self, signal_ref: SignalRef, tpe: SignalType) {
+        let handle_idx = signal_ref.index();
+        debug_assert!(
+            !matches!(self.handle_to_node.get(handle_idx), Some(Some(_))),
+            "{signal_ref:?} is already used by a variable"
+        );
+        if self.handle_to_node.len() <= handle_idx {
+            self.handle_to_node.resize(handle_idx + 1, None);
+        }
+        self.hidden_signals.insert(signal_ref, tpe);
+    }
 }
 
 /// finds the first not flattened parent scope
@@ -2,14 +2,130 @@
 // released under BSD 3-Clause License
 // author: Kevin Laeufer <laeufer@berkeley.edu>
 
+mod cache;
 mod detect;
+mod expr;
 pub mod fst;
 pub mod ghw;
 mod hierarchy;
+mod loader;
+mod progress;
+mod shared;
 mod signals;
+mod summary;
 pub mod vcd;
 mod wavemem;
 
+#[derive(Debug, Clone)]
+pub struct LoadOptions {
+    /// Indicates that the file should be parsed using multiple threads.
+    pub multi_thread: bool,
+    /// Indicates that scopes with empty names should not be part of the hierarchy.
+    pub remove_scopes_with_empty_name: bool,
+    /// Allows another thread to observe the progress of opening the file and to cancel it.
+    pub progress: Option<std::sync::Arc<LoadProgress>>,
+    /// Only parses the values of some signals.
+    pub signal_filter: Option<SignalFilter>,
+}
+
+impl Default for LoadOptions {
+    fn default() -> Self {
+        Self {
+            multi_thread: true,
+            remove_scopes_with_empty_name: false,
+            progress: None,
+            signal_filter: None,
+        }
+    }
+}
+
+impl LoadOptions {
+    /// Allows another thread to observe the progress of opening the file and to cancel it.
+    pub fn with_progress(mut self, progress: std::sync::Arc<LoadProgress>) -> Self {
+        self.progress = Some(progress);
+        self
+    }
+
+    /// Only parses the values of the signals selected by the `filter`.
+    pub fn with_signal_filter(mut self, filter: SignalFilter) -> Self {
+        self.signal_filter = Some(filter);
+        self
+    }
+
+    pub(crate) fn progress(&self) -> progress::Progress<'_> {
+        progress::Progress::new(self.progress.as_deref())
+    }
+
+    /// Returns for every unique signal whether its values should be parsed,
+    /// or `None` if all signals are needed.
+    pub(crate) fn signal_mask(&self, hierarchy: &Hierarchy) -> Option<Vec<bool>> {
+        self.signal_filter
+            .as_ref()
+            .map(|filter| filter.signal_mask(hierarchy))
+    }
+}
+
+/// Selects the signals whose value changes are parsed. Value changes of all other signals
+/// are skipped, which saves time and memory when only a few signals of a large dump are needed.
+/// Loading a signal that was filtered out results in a [`WellenError::SignalFiltered`] error.
+#[derive(Clone)]
+pub enum SignalFilter {
+    Signals(Vec<SignalRef>),
+    /// Keeps the signals of all variables in these scopes, including all nested scopes.
+    Scopes(Vec<ScopeRef>),
+    /// Keeps the signals of all variables for which the predicate returns true.
+    Predicate(SignalPredicate),
+}
+
+pub type SignalPredicate = std::sync::Arc<dyn Fn(&Hierarchy, &Var) -> bool + Send + Sync>;
+
+impl std::fmt::Debug for SignalFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SignalFilter::Signals(signals) => f.debug_tuple("Signals").field(signals).finish(),
+            SignalFilter::Scopes(scopes) => f.debug_tuple("Scopes").field(scopes).finish(),
+            SignalFilter::Predicate(_) => write!(f, "Predicate(...)"),
+        }
+    }
+}
+
+impl SignalFilter {
+    /// Returns for every unique signal whether its values should be parsed.
+    pub(crate) fn signal_mask(&self, hierarchy: &Hierarchy) -> Vec<bool> {
+        let mut mask = vec![false; hierarchy.num_unique_signals()];
+        let mut keep = |signal: SignalRef| {
+            // a slice is computed from the signal that it refers to
+            let sliced = hierarchy.get_slice_info(signal).map(|s| s.sliced_signal);
+            for signal in std::iter::once(signal).chain(sliced) {
+                if let Some(m) = mask.get_mut(signal.index()) {
+                    *m = true;
+                }
+            }
+        };
+        match self {
+            SignalFilter::Signals(signals) => signals.iter().for_each(|s| keep(*s)),
+            SignalFilter::Scopes(scopes) => {
+                let mut todo = scopes.clone();
+                while let Some(scope) = todo.pop() {
+                    let scope: &Scope = hierarchy.get(scope);
+                    for var in scope.vars(hierarchy) {
+                        keep(hierarchy.get(var).signal_ref());
+                    }
+                    todo.extend(scope.scopes(hierarchy));
+                }
+            }
+            SignalFilter::Predicate(predicate) => {
+                for var in hierarchy.iter_vars() {
+                    if predicate(hierarchy, var) {
+                        keep(var.signal_ref());
+                    }
+                }
+            }
+        }
+        mask
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum WellenError {
     #[error("[vcd] failed to parse length: `{0}` for variable `{1}`")]
@@ -32,6 +148,20 @@ pub enum WellenError {
     VcdUnknownVarType(String),
     #[error("[vcd] unknown scope type: {0}")]
     VcdUnknownScopeType(String),
+    #[error("[vcd] unexpected command {0}. Supported commands are: {1}")]
+    VcdUnknownCommand(String, String),
+    #[error("[vcd] unexpected tokens: `{0}` and `{1}` ({2} lines after header)")]
+    VcdUnexpectedBodyTokens(String, String, usize),
+    #[error("[vcd] unknown signal id: {0}")]
+    VcdUnknownId(String),
+    #[error("[vcd] failed to parse value `{0}`: {1}")]
+    VcdInvalidValue(String, String),
+    #[error("[fst] failed to parse: {0}")]
+    FstParse(String),
+    #[error("unexpected end of {0:?} file, it might be truncated")]
+    TruncatedFile(FileFormat),
+    #[error("[{0:?}] unsupported feature: {1}")]
+    UnsupportedFeature(FileFormat, String),
     #[error("failed to decode string")]
     Utf8(#[from] std::str::Utf8Error),
     #[error("failed to parse an integer")]
@@ -40,14 +170,36 @@ pub enum WellenError {
     Io(#[from] std::io::Error),
     #[error("failed to load {0:?}:\n{1}")]
     FailedToLoad(FileFormat, String),
+    #[error("unknown file format, only GHW, FST and VCD are supported")]
+    UnknownFileFormat,
+    #[error("signal {0} needs to be loaded before it can be written")]
+    SignalNotLoaded(String),
+    #[error("signal {0} was excluded by the signal filter")]
+    SignalFiltered(String),
+    #[error("failed to parse expression `{0}`: {1}")]
+    ExprParse(String, String),
+    #[error("invalid expression: {0}")]
+    InvalidExpr(String),
+    #[error("loading was cancelled")]
+    Cancelled,
+    #[error("value changes starting at {1} cannot be appended after {0}")]
+    NotChronological(u64, u64),
 }
 
+pub use cache::CacheStatistics;
 pub use detect::{detect_file_format, open_and_detect_file_format, FileFormat};
+pub use expr::{BinaryOp, Expr, UnaryOp};
 pub use hierarchy::{
     GetItem, Hierarchy, HierarchyItem, Scope, ScopeRef, ScopeType, SignalRef, Timescale,
     TimescaleUnit, Var, VarDirection, VarIndex, VarRef, VarType,
 };
-pub use signals::{Real, Signal, SignalValue, Time, TimeTableIdx, Waveform};
+pub use loader::{open, open_from_reader, open_header, Body, BodyLoader};
+pub use progress::{LoadPhase, LoadProgress};
+pub use shared::SharedWaveform;
+pub use signals::{
+    BitPattern, Edge, Radix, Real, Signal, SignalValue, Time, TimeTableIdx, Waveform,
+};
+pub use summary::SignalSummary;
 use thiserror::Error;
 
 #[cfg(feature = "benchmark")]
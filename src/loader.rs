@@ -0,0 +1,133 @@
+// Copyright 2024 The Regents of the University of California
+// released under BSD 3-Clause License
+// author: Kevin Laeufer <laeufer@berkeley.edu>
+//
+// File format independent entry points.
+
+use crate::signals::SignalSource;
+use crate::{detect_file_format, FileFormat, Hierarchy, LoadOptions, Time, Waveform, WellenError};
+use std::io::{BufRead, Seek};
+use std::sync::Arc;
+
+pub type Result<T> = std::result::Result<T, WellenError>;
+
+/// Detects the file format and loads the waveform using the matching backend.
+pub fn open(filename: &str, options: LoadOptions) -> Result<Waveform> {
+    match detect_file_format_of(filename)? {
+        FileFormat::Vcd => crate::vcd::read_with_options(filename, options),
+        FileFormat::Fst => crate::fst::read_with_options(filename, options),
+        FileFormat::Ghw => crate::ghw::read_with_options(filename, options),
+        FileFormat::Unknown => Err(WellenError::UnknownFileFormat),
+    }
+}
+
+/// Detects the file format and loads the waveform from an arbitrary input.
+pub fn open_from_reader<R: BufRead + Seek + Send + Sync + 'static>(
+    mut input: R,
+    options: LoadOptions,
+) -> Result<Waveform> {
+    match detect_file_format(&mut input) {
+        FileFormat::Vcd => crate::vcd::read_from_stream(input, options),
+        FileFormat::Fst => crate::fst::read_from_reader(input, options),
+        FileFormat::Ghw => crate::ghw::read_from_reader(input, options),
+        FileFormat::Unknown => Err(WellenError::UnknownFileFormat),
+    }
+}
+
+/// Detects the file format and only parses the header and the hierarchy.
+/// The signal values can be loaded later, possibly on a different thread,
+/// with the returned [`BodyLoader`].
+pub fn open_header(filename: &str, options: LoadOptions) -> Result<(Hierarchy, BodyLoader)> {
+    match detect_file_format_of(filename)? {
+        FileFormat::Vcd => crate::vcd::read_header(filename, options),
+        FileFormat::Fst => crate::fst::read_header(filename, options),
+        FileFormat::Ghw => crate::ghw::read_header(filename, options),
+        FileFormat::Unknown => Err(WellenError::UnknownFileFormat),
+    }
+}
+
+fn detect_file_format_of(filename: &str) -> Result<FileFormat> {
+    let f = std::fs::File::open(filename)?;
+    let mut reader = std::io::BufReader::new(f);
+    Ok(detect_file_format(&mut reader))
+}
+
+/// Parses the signal values of a waveform whose hierarchy was already loaded.
+pub struct BodyLoader(BodyLoaderKind);
+
+pub(crate) enum BodyLoaderKind {
+    Vcd(crate::vcd::VcdBody),
+    Fst(Box<dyn SignalSource + Send + Sync>),
+    Ghw(crate::ghw::GhwBody),
+}
+
+impl BodyLoader {
+    pub(crate) fn new(kind: BodyLoaderKind) -> Self {
+        Self(kind)
+    }
+
+    pub fn file_format(&self) -> FileFormat {
+        match &self.0 {
+            BodyLoaderKind::Vcd(_) => FileFormat::Vcd,
+            BodyLoaderKind::Fst(_) => FileFormat::Fst,
+            BodyLoaderKind::Ghw(_) => FileFormat::Ghw,
+        }
+    }
+
+    /// Parses the signal values. `hierarchy` needs to be the hierarchy that was returned
+    /// together with this loader.
+    pub fn load(self, hierarchy: &Hierarchy) -> Result<Body> {
+        let source = match self.0 {
+            BodyLoaderKind::Vcd(body) => body.load(hierarchy)?,
+            // the FST reader only loads signals on demand
+            BodyLoaderKind::Fst(source) => source,
+            BodyLoaderKind::Ghw(body) => body.load(hierarchy)?,
+        };
+        Ok(Body::new(source))
+    }
+}
+
+/// The signal values and time table of a waveform. Combine with the hierarchy
+/// through [`Waveform::from_parts`].
+pub struct Body {
+    source: Box<dyn SignalSource + Send + Sync>,
+    time_table: Arc<[Time]>,
+}
+
+impl Body {
+    fn new(source: Box<dyn SignalSource + Send + Sync>) -> Self {
+        let time_table = source.get_time_table();
+        Self { source, time_table }
+    }
+
+    pub fn time_table(&self) -> &[Time] {
+        &self.time_table
+    }
+
+    pub(crate) fn into_source(self) -> Box<dyn SignalSource + Send + Sync> {
+        self.source
+    }
+}
+
+/// A memory mapped or in-memory file that can be shared between threads.
+#[derive(Clone)]
+pub(crate) struct SharedBytes(Arc<dyn AsRef<[u8]> + Send + Sync>);
+
+impl SharedBytes {
+    pub(crate) fn new(bytes: impl AsRef<[u8]> + Send + Sync + 'static) -> Self {
+        Self(Arc::new(bytes))
+    }
+
+    /// Maps the file into memory (lazily).
+    pub(crate) fn map_file(filename: &str) -> Result<Self> {
+        let input_file = std::fs::File::open(filename)?;
+        let mmap = unsafe { memmap2::Mmap::map(&input_file)? };
+        Ok(Self::new(mmap))
+    }
+}
+
+impl AsRef<[u8]> for SharedBytes {
+    fn as_ref(&self) -> &[u8] {
+        (*self.0).as_ref()
+    }
+}
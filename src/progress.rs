@@ -0,0 +1,253 @@
+// Copyright 2024 The Regents of the University of California
+// released under BSD 3-Clause License
+// author: Kevin Laeufer <laeufer@berkeley.edu>
+//
+// Progress reporting and cancellation of long running load operations.
+
+use crate::WellenError;
+use std::io::{BufRead, Read, Seek, SeekFrom};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicU8, Ordering};
+use std::sync::Arc;
+
+/// Shared between a loader and e.g. a GUI thread in order to observe the progress of
+/// loading a waveform and to cancel it.
+#[derive(Debug, Default)]
+pub struct LoadProgress {
+    phase: AtomicU8,
+    done: AtomicU64,
+    total: AtomicU64,
+    cancelled: AtomicBool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoadPhase {
+    /// Parsing the header and the hierarchy. Progress is measured in bytes.
+    Header,
+    /// Parsing signal values. Progress is measured in bytes.
+    Body,
+    /// Combining the results of parsing the body with multiple threads.
+    /// Progress is measured in the number of parts that were combined.
+    Merge,
+}
+
+impl LoadProgress {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn phase(&self) -> LoadPhase {
+        match self.phase.load(Ordering::Relaxed) {
+            0 => LoadPhase::Header,
+            1 => LoadPhase::Body,
+            _ => LoadPhase::Merge,
+        }
+    }
+
+    /// Returns the amount of work done and the total amount of work in the current phase.
+    /// The total is zero if it is unknown, e.g., because the input is a stream.
+    pub fn progress(&self) -> (u64, u64) {
+        (
+            self.done.load(Ordering::Relaxed),
+            self.total.load(Ordering::Relaxed),
+        )
+    }
+
+    /// Requests the loader to stop. The loader will return [`WellenError::Cancelled`].
+    /// Only affects opening the file. Signals that are loaded on demand later, e.g., from
+    /// a FST file, are neither tracked nor cancelled.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    fn start_phase(&self, phase: LoadPhase, total: u64) {
+        self.phase.store(phase as u8, Ordering::Relaxed);
+        self.done.store(0, Ordering::Relaxed);
+        self.total.store(total, Ordering::Relaxed);
+    }
+
+    fn add_done(&self, amount: u64) {
+        self.done.fetch_add(amount, Ordering::Relaxed);
+    }
+}
+
+/// Cheap to copy handle that turns all operations into no-ops when progress is not tracked.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct Progress<'a>(Option<&'a LoadProgress>);
+
+impl<'a> Progress<'a> {
+    pub(crate) fn new(progress: Option<&'a LoadProgress>) -> Self {
+        Self(progress)
+    }
+
+    /// The start of every phase is also a point at which we check for cancellation.
+    pub(crate) fn start_phase(self, phase: LoadPhase, total: u64) -> Result<(), WellenError> {
+        if let Some(p) = self.0 {
+            p.start_phase(phase, total);
+        }
+        self.check_cancelled()
+    }
+
+    pub(crate) fn add_done(self, amount: u64) {
+        if let Some(p) = self.0 {
+            p.add_done(amount);
+        }
+    }
+
+    pub(crate) fn check_cancelled(self) -> Result<(), WellenError> {
+        match self.0 {
+            Some(p) if p.is_cancelled() => Err(WellenError::Cancelled),
+            _ => Ok(()),
+        }
+    }
+
+    /// Any error that occurs after the user cancelled loading is most likely caused by
+    /// the cancellation, e.g., because a [`ProgressReader`] stopped returning data.
+    pub(crate) fn map_result<T>(self, result: Result<T, WellenError>) -> Result<T, WellenError> {
+        match result {
+            Err(_) if self.check_cancelled().is_err() => Err(WellenError::Cancelled),
+            other => other,
+        }
+    }
+}
+
+/// Bytes are reported in batches in order to avoid contention when multiple threads are used.
+pub(crate) const PROGRESS_INTERVAL: u64 = 64 * 1024;
+
+/// Counts the number of bytes read and returns an I/O error once loading was cancelled.
+pub(crate) struct ProgressReader<R> {
+    inner: R,
+    progress: Option<Arc<LoadProgress>>,
+    pending: u64,
+    cancelled: bool,
+    detached: Arc<AtomicBool>,
+}
+
+impl<R> ProgressReader<R> {
+    pub(crate) fn new(inner: R, progress: Option<Arc<LoadProgress>>) -> Self {
+        Self {
+            inner,
+            progress,
+            pending: 0,
+            cancelled: false,
+            detached: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    pub(crate) fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Returns a flag which makes the reader stop reporting progress and checking for
+    /// cancellation once it is set. Used for readers that outlive the load operation.
+    pub(crate) fn detach_flag(&self) -> Arc<AtomicBool> {
+        self.detached.clone()
+    }
+
+    #[inline]
+    fn advance(&mut self, amount: usize) {
+        if self.progress.is_some() {
+            self.pending += amount as u64;
+            if self.pending >= PROGRESS_INTERVAL {
+                self.flush();
+            }
+        }
+    }
+
+    fn flush(&mut self) {
+        if self.detached.load(Ordering::Relaxed) {
+            self.progress = None;
+        }
+        if let Some(progress) = &self.progress {
+            progress.add_done(std::mem::take(&mut self.pending));
+            self.cancelled = progress.is_cancelled();
+        }
+    }
+
+    #[inline]
+    fn check_cancelled(&self) -> std::io::Result<()> {
+        if self.cancelled {
+            Err(std::io::Error::other(WellenError::Cancelled))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl<R> Drop for ProgressReader<R> {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+impl<R: Read> Read for ProgressReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.check_cancelled()?;
+        let len = self.inner.read(buf)?;
+        self.advance(len);
+        Ok(len)
+    }
+}
+
+impl<R: BufRead> BufRead for ProgressReader<R> {
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        self.check_cancelled()?;
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.inner.consume(amt);
+        self.advance(amt);
+    }
+}
+
+/// Readers that seek, like the FST reader, may be kept open for a long time. Thus we report
+/// progress whenever they jump to a new section instead of only once they are dropped.
+impl<R: Seek> Seek for ProgressReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.flush();
+        self.check_cancelled()?;
+        self.inner.seek(pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_progress_reader() {
+        let data = vec![0u8; 3 * PROGRESS_INTERVAL as usize];
+        let progress = Arc::new(LoadProgress::new());
+        progress.start_phase(LoadPhase::Body, data.len() as u64);
+        let mut reader = ProgressReader::new(&data[..], Some(progress.clone()));
+        let mut buf = vec![0u8; PROGRESS_INTERVAL as usize];
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(progress.progress(), (PROGRESS_INTERVAL, data.len() as u64));
+
+        // cancellation is noticed the next time we report progress
+        progress.cancel();
+        reader.read_exact(&mut buf).unwrap();
+        assert!(reader.read_exact(&mut buf).is_err());
+        drop(reader);
+        assert_eq!(progress.progress().0, 2 * PROGRESS_INTERVAL);
+    }
+
+    #[test]
+    fn test_detached_progress_reader() {
+        let data = vec![0u8; 3 * PROGRESS_INTERVAL as usize];
+        let progress = Arc::new(LoadProgress::new());
+        let mut reader = ProgressReader::new(&data[..], Some(progress.clone()));
+        let mut buf = vec![0u8; PROGRESS_INTERVAL as usize];
+        reader.read_exact(&mut buf).unwrap();
+        reader.detach_flag().store(true, Ordering::Relaxed);
+        progress.cancel();
+        reader.read_exact(&mut buf).unwrap();
+        reader.read_exact(&mut buf).unwrap();
+        drop(reader);
+        assert_eq!(progress.progress().0, PROGRESS_INTERVAL);
+    }
+}
@@ -0,0 +1,120 @@
+// Copyright 2024 The Regents of the University of California
+// released under BSD 3-Clause License
+// author: Kevin Laeufer <laeufer@berkeley.edu>
+//
+// A waveform that can be used by many threads at the same time.
+
+use crate::hierarchy::{Hierarchy, SignalRef};
+use crate::signals::{load_signals_from_source, Signal, SignalSource, Time};
+use crate::WellenError;
+use std::collections::HashMap;
+use std::fmt::{Debug, Formatter};
+use std::sync::{Arc, RwLock};
+
+pub type Result<T> = std::result::Result<T, WellenError>;
+
+/// Thread-safe version of [`crate::Waveform`], e.g., for a server that answers requests
+/// from many users. Hierarchy and time table are immutable and shared through an [`Arc`].
+/// Loaded signals are returned as [`Arc<Signal>`] and thus remain valid after they are
+/// unloaded. Threads that request different signals load them concurrently.
+/// Create one through [`crate::Waveform::into_shared`].
+pub struct SharedWaveform {
+    hierarchy: Arc<Hierarchy>,
+    time_table: Arc<[Time]>,
+    source: Box<dyn SignalSource + Send + Sync>,
+    signals: RwLock<HashMap<SignalRef, Arc<Signal>>>,
+}
+
+impl Debug for SharedWaveform {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SharedWaveform(...)")
+    }
+}
+
+impl SharedWaveform {
+    pub(crate) fn new(
+        hierarchy: Hierarchy,
+        source: Box<dyn SignalSource + Send + Sync>,
+        time_table: Arc<[Time]>,
+        signals: HashMap<SignalRef, Signal>,
+    ) -> Self {
+        let signals = signals
+            .into_iter()
+            .map(|(id, signal)| (id, Arc::new(signal)))
+            .collect();
+        Self {
+            hierarchy: Arc::new(hierarchy),
+            time_table,
+            source,
+            signals: RwLock::new(signals),
+        }
+    }
+
+    pub fn hierarchy(&self) -> &Arc<Hierarchy> {
+        &self.hierarchy
+    }
+
+    pub fn time_table(&self) -> &Arc<[Time]> {
+        &self.time_table
+    }
+
+    /// Returns the signals in the order in which they were requested and loads all signals
+    /// that are not in memory yet. No lock is held while loading, thus other threads can
+    /// access loaded signals or load other signals in the meantime.
+    pub fn load_signals(&self, ids: &[SignalRef]) -> Result<Vec<Arc<Signal>>> {
+        self.load_signals_internal(ids, false)
+    }
+
+    pub fn load_signals_multi_threaded(&self, ids: &[SignalRef]) -> Result<Vec<Arc<Signal>>> {
+        self.load_signals_internal(ids, true)
+    }
+
+    fn load_signals_internal(
+        &self,
+        ids: &[SignalRef],
+        multi_threaded: bool,
+    ) -> Result<Vec<Arc<Signal>>> {
+        let mut out: Vec<_> = {
+            let signals = self.signals.read().unwrap();
+            ids.iter().map(|id| signals.get(id).cloned()).collect()
+        };
+        let missing: Vec<_> = ids
+            .iter()
+            .zip(out.iter())
+            .filter(|(_, signal)| signal.is_none())
+            .map(|(id, _)| *id)
+            .collect();
+        if !missing.is_empty() {
+            let loaded =
+                load_signals_from_source(&self.hierarchy, &*self.source, &missing, multi_threaded)?;
+            let mut signals = self.signals.write().unwrap();
+            let loaded: HashMap<_, _> = loaded
+                .into_iter()
+                .map(|(id, signal)| {
+                    // another thread might have loaded the same signal in the meantime
+                    let signal = signals.entry(id).or_insert_with(|| Arc::new(signal));
+                    (id, signal.clone())
+                })
+                .collect();
+            drop(signals);
+            for (id, signal) in ids.iter().zip(out.iter_mut()) {
+                if signal.is_none() {
+                    *signal = Some(loaded[id].clone());
+                }
+            }
+        }
+        Ok(out.into_iter().map(|s| s.unwrap()).collect())
+    }
+
+    /// Removes signals from memory. Threads that still hold a reference can continue to use them.
+    pub fn unload_signals(&self, ids: &[SignalRef]) {
+        let mut signals = self.signals.write().unwrap();
+        for id in ids.iter() {
+            signals.remove(id);
+        }
+    }
+
+    pub fn get_signal(&self, id: SignalRef) -> Option<Arc<Signal>> {
+        self.signals.read().unwrap().get(&id).cloned()
+    }
+}
@@ -2,14 +2,19 @@
 // released under BSD 3-Clause License
 // author: Kevin Laeufer <laeufer@berkeley.edu>
 
-use crate::fst::{get_bytes_per_entry, get_len_and_meta, push_zeros};
-use crate::hierarchy::{Hierarchy, SignalRef, SignalType};
+use crate::cache::{CacheStatistics, SignalCache};
+use crate::fst::{expand_entries, get_bytes_per_entry, get_len_and_meta, push_zeros};
+use crate::hierarchy::{GetItem, Hierarchy, SignalRef, SignalType, VarRef};
+use crate::shared::SharedWaveform;
+use crate::summary::{SignalSummary, SummaryPyramid};
 use crate::vcd::usize_div_ceil;
 use crate::wavemem::{check_if_changed_and_truncate, States};
+use crate::WellenError;
 use num_enum::TryFromPrimitive;
 use std::collections::HashMap;
 use std::fmt::{Debug, Display, Formatter};
 use std::num::NonZeroU32;
+use std::sync::{Arc, RwLock};
 
 pub type Real = f64;
 pub type Time = u64;
@@ -44,12 +49,13 @@ impl<'a> Display for SignalValue<'a> {
 }
 
 impl<'a> SignalValue<'a> {
+    /// Returns the value as a string of bits. Returns None if the value is a real or string.
     pub fn to_bit_string(&self) -> Option<String> {
         match &self {
             SignalValue::Binary(data, bits) => Some(two_state_to_bit_string(data, *bits)),
             SignalValue::FourValue(data, bits) => Some(four_state_to_bit_string(data, *bits)),
             SignalValue::NineValue(data, bits) => Some(nine_state_to_bit_string(data, *bits)),
-            other => panic!("Cannot convert {other:?} to bit string"),
+            _ => None,
         }
     }
 
@@ -63,6 +69,129 @@ impl<'a> SignalValue<'a> {
         }
     }
 
+    /// Returns the value as little endian bytes. Returns None if the value is a real or string
+    /// or if any bit is not `0` or `1`. The weak states `l` and `h` count as `0` and `1`.
+    pub fn to_le_bytes(&self) -> Option<Vec<u8>> {
+        let bits = self.bits()?;
+        let mut out = vec![0u8; (bits as usize).div_ceil(8)];
+        for bit in 0..bits {
+            if bit_value(self.bit_char(bit)?)? {
+                out[(bit / 8) as usize] |= 1 << (bit % 8);
+            }
+        }
+        Some(out)
+    }
+
+    /// Returns None if the value does not fit into 64 bits or contains unknown bits.
+    pub fn to_u64(&self) -> Option<u64> {
+        let bytes = self.to_le_bytes()?;
+        if bytes.iter().skip(8).any(|b| *b != 0) {
+            return None;
+        }
+        Some(
+            bytes
+                .iter()
+                .take(8)
+                .rev()
+                .fold(0u64, |value, b| (value << 8) | *b as u64),
+        )
+    }
+
+    /// Interprets the value as a two's complement number of its width.
+    /// Returns None if the value does not fit into 64 bits or contains unknown bits.
+    pub fn to_i64(&self) -> Option<i64> {
+        let bits = self.bits()?;
+        let bytes = self.to_le_bytes()?;
+        let bit = |ii: u32| (bytes[(ii / 8) as usize] >> (ii % 8)) & 1 == 1;
+        let lower = (0..bits.min(64))
+            .rev()
+            .fold(0u64, |value, ii| (value << 1) | bit(ii) as u64);
+        if bits <= 64 {
+            // sign extend, a zero width value is always zero
+            let shift = 64 - bits;
+            let shifted = lower.checked_shl(shift).unwrap_or(0) as i64;
+            return Some(shifted.checked_shr(shift).unwrap_or(0));
+        }
+        // all bits from 63 upwards need to be the same as the sign
+        let sign = bit(bits - 1);
+        (63..bits).all(|ii| bit(ii) == sign).then_some(lower as i64)
+    }
+
+    /// Formats bit-vectors in the requested radix. Strings and reals are always formatted as is.
+    ///
+    /// Octal and hex digits that contain unknown bits are shown as the state of these bits, if
+    /// all bits in the digit have the same state, and as `x` otherwise.
+    /// Decimal numbers follow the same rule for the value as a whole.
+    /// ASCII characters with unknown bits are shown as `?`, non-printable characters as `.`,
+    /// while leading zero bytes are skipped.
+    pub fn to_string_radix(&self, radix: Radix) -> String {
+        let bits = match self.bits() {
+            Some(bits) => bits,
+            None => return self.to_string(),
+        };
+        let chars: Vec<char> = (0..bits).map(|bit| self.bit_char(bit).unwrap()).collect();
+        match radix {
+            Radix::Binary => chars.iter().rev().collect(),
+            Radix::Octal => format_digits(&chars, 3),
+            Radix::Hex => format_digits(&chars, 4),
+            Radix::Unsigned | Radix::Signed => match self.to_le_bytes() {
+                Some(mut bytes) => {
+                    let negative = radix == Radix::Signed
+                        && bits > 0
+                        && bit_value(chars[bits as usize - 1]) == Some(true);
+                    if negative {
+                        twos_complement(&mut bytes, bits);
+                        format!("-{}", le_bytes_to_decimal(bytes))
+                    } else {
+                        le_bytes_to_decimal(bytes)
+                    }
+                }
+                None => unknown_digit(&chars).to_string(),
+            },
+            Radix::Ascii => format_ascii(&chars),
+        }
+    }
+
+    /// Translates the value into the name of an enum literal, using a mapping returned by
+    /// [`crate::Var::enum_type`]. GHW uses binary keys of the enum width, while FST keys may
+    /// be binary or `0x` prefixed hex strings, with or without leading zeros.
+    pub fn to_enum_literal<'m>(&self, mapping: &[(&'m str, &'m str)]) -> Option<&'m str> {
+        let bits = self.to_bit_string()?;
+        if let Some((_, name)) = mapping.iter().find(|(key, _)| *key == bits) {
+            return Some(name);
+        }
+        let bits = bits.trim_start_matches('0');
+        mapping
+            .iter()
+            .find(|(key, _)| {
+                enum_key_to_bits(key).is_some_and(|k| k.trim_start_matches('0') == bits)
+            })
+            .map(|(_, name)| *name)
+    }
+
+    /// Returns the character (`0`, `1`, `x`, `z`, ...) of a single bit, starting with the lsb.
+    /// Returns None if the value is a real or string or if the bit does not exist.
+    pub(crate) fn bit_char(&self, bit: u32) -> Option<char> {
+        let (states, data, bits) = match *self {
+            SignalValue::Binary(data, bits) => (States::Two, data, bits),
+            SignalValue::FourValue(data, bits) => (States::Four, data, bits),
+            SignalValue::NineValue(data, bits) => (States::Nine, data, bits),
+            _ => return None,
+        };
+        if bit >= bits {
+            return None;
+        }
+        let bits_per_byte = states.bits_in_a_byte() as u32;
+        let byte = data[data.len() - 1 - (bit / bits_per_byte) as usize];
+        let value = (byte >> ((bit % bits_per_byte) * states.bits() as u32)) & states.mask();
+        let lookup = match states {
+            States::Two => TWO_STATE_LOOKUP.as_slice(),
+            States::Four => FOUR_STATE_LOOKUP.as_slice(),
+            States::Nine => NINE_STATE_LOOKUP.as_slice(),
+        };
+        Some(lookup[value as usize])
+    }
+
     /// Returns the states per bit. Returns None if the value is a real or string.
     pub(crate) fn states(&self) -> Option<States> {
         match self {
@@ -84,6 +213,134 @@ impl<'a> SignalValue<'a> {
     }
 }
 
+/// Number formats supported by [`SignalValue::to_string_radix`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Radix {
+    Binary,
+    Octal,
+    Hex,
+    Unsigned,
+    /// two's complement
+    Signed,
+    Ascii,
+}
+
+/// Converts binary or hex enum keys into a string of bits.
+fn enum_key_to_bits(key: &str) -> Option<String> {
+    let hex = match key.strip_prefix("0x").or_else(|| key.strip_prefix("0X")) {
+        Some(hex) => hex,
+        None if key.chars().all(|c| NINE_STATE_LOOKUP.contains(&c)) => {
+            return Some(key.to_string())
+        }
+        None => key,
+    };
+    let mut out = String::with_capacity(hex.len() * 4);
+    for c in hex.chars() {
+        match c.to_digit(16) {
+            Some(value) => out.push_str(&format!("{value:04b}")),
+            None if NINE_STATE_LOOKUP.contains(&c.to_ascii_lowercase()) => {
+                (0..4).for_each(|_| out.push(c.to_ascii_lowercase()))
+            }
+            None => return None,
+        }
+    }
+    Some(out)
+}
+
+/// Returns the logic value of a bit, weak states count as strong ones.
+#[inline]
+pub(crate) fn bit_value(state: char) -> Option<bool> {
+    match state {
+        '0' | 'l' => Some(false),
+        '1' | 'h' => Some(true),
+        _ => None,
+    }
+}
+
+/// A number with unknown bits is shown as the state of all bits, if they are all the same.
+fn unknown_digit(bits: &[char]) -> char {
+    match bits.first() {
+        Some(first) if bits.iter().all(|b| b == first) => *first,
+        _ => 'x',
+    }
+}
+
+/// Turns the bits (lsb first) into octal or hex digits (msb first).
+fn format_digits(bits: &[char], bits_per_digit: usize) -> String {
+    let mut out: Vec<char> = bits
+        .chunks(bits_per_digit)
+        .map(|digit| {
+            let values: Option<Vec<bool>> = digit.iter().map(|c| bit_value(*c)).collect();
+            match values {
+                Some(values) => {
+                    let value = values.iter().rev().fold(0u32, |d, v| (d << 1) | *v as u32);
+                    char::from_digit(value, 16).unwrap()
+                }
+                None => unknown_digit(digit),
+            }
+        })
+        .collect();
+    out.reverse();
+    out.into_iter().collect()
+}
+
+/// Turns the bits (lsb first) into characters (msb first).
+fn format_ascii(bits: &[char]) -> String {
+    let bytes: Vec<Option<u8>> = bits
+        .chunks(8)
+        .rev()
+        .map(|byte| {
+            byte.iter()
+                .rev()
+                .try_fold(0u8, |b, c| Some((b << 1) | bit_value(*c)? as u8))
+        })
+        .collect();
+    bytes
+        .into_iter()
+        .skip_while(|b| *b == Some(0))
+        .map(|b| match b {
+            None => '?',
+            Some(b) if b.is_ascii_graphic() || b == b' ' => b as char,
+            Some(_) => '.',
+        })
+        .collect()
+}
+
+/// Negates a little endian number of the given width.
+fn twos_complement(bytes: &mut [u8], bits: u32) {
+    let mut carry = true;
+    for b in bytes.iter_mut() {
+        let (value, overflow) = (!*b).overflowing_add(carry as u8);
+        *b = value;
+        carry = overflow;
+    }
+    // clear all bits above the width
+    if !bits.is_multiple_of(8) {
+        if let Some(last) = bytes.last_mut() {
+            *last &= (1u8 << (bits % 8)) - 1;
+        }
+    }
+}
+
+/// Arbitrary width unsigned integer to decimal conversion.
+fn le_bytes_to_decimal(mut bytes: Vec<u8>) -> String {
+    let mut digits = Vec::new();
+    while bytes.iter().any(|b| *b != 0) {
+        // divide by 10, starting with the most significant byte
+        let mut remainder = 0u32;
+        for b in bytes.iter_mut().rev() {
+            let value = (remainder << 8) | *b as u32;
+            *b = (value / 10) as u8;
+            remainder = value % 10;
+        }
+        digits.push(char::from_digit(remainder, 10).unwrap());
+    }
+    if digits.is_empty() {
+        digits.push('0');
+    }
+    digits.iter().rev().collect()
+}
+
 const TWO_STATE_LOOKUP: [char; 2] = ['0', '1'];
 const FOUR_STATE_LOOKUP: [char; 4] = ['0', '1', 'x', 'z'];
 const NINE_STATE_LOOKUP: [char; 9] = ['0', '1', 'x', 'z', 'h', 'u', 'w', 'l', '-'];
@@ -138,6 +395,17 @@ fn n_state_to_bit_string(states: States, data: &[u8], bits: u32) -> String {
     out
 }
 
+/// Appends fixed size entries and returns true if the first one was skipped because it
+/// is the same as the last entry in `bytes`.
+fn append_entries(bytes: &mut Vec<u8>, other: &[u8], bytes_per_entry: usize) -> bool {
+    let is_same = bytes.len() >= bytes_per_entry
+        && other.len() >= bytes_per_entry
+        && bytes[bytes.len() - bytes_per_entry..] == other[..bytes_per_entry];
+    let start = if is_same { bytes_per_entry } else { 0 };
+    bytes.extend_from_slice(&other[start..]);
+    is_same
+}
+
 /// Specifies the encoding of a signal.
 #[derive(Debug, Clone, Copy)]
 pub(crate) enum SignalEncoding {
@@ -154,7 +422,6 @@ pub(crate) enum SignalEncoding {
 }
 
 pub struct Signal {
-    #[allow(unused)]
     idx: SignalRef,
     time_indices: Vec<TimeTableIdx>,
     data: SignalChangeData,
@@ -207,6 +474,76 @@ impl Signal {
         }
     }
 
+    /// Appends the changes of `other` which all come after our changes.
+    /// `time_offset` is added to the time table indices of `other`.
+    /// The first change of `other` is skipped if it just repeats our last value.
+    pub(crate) fn append(&mut self, other: Signal, time_offset: TimeTableIdx) {
+        debug_assert_eq!(self.idx, other.idx);
+        let entries = self.time_indices.len();
+        let other_entries = other.time_indices.len();
+        let skip_first = match (&mut self.data, other.data) {
+            (
+                SignalChangeData::FixedLength {
+                    encoding:
+                        SignalEncoding::BitVector {
+                            max_states,
+                            bits,
+                            meta_byte,
+                        },
+                    width,
+                    bytes,
+                },
+                SignalChangeData::FixedLength {
+                    encoding:
+                        SignalEncoding::BitVector {
+                            max_states: other_states,
+                            ..
+                        },
+                    bytes: other_bytes,
+                    ..
+                },
+            ) => {
+                // both signals need to use the same number of states
+                let states = States::join(*max_states, other_states);
+                if states != *max_states {
+                    *bytes = expand_entries(*max_states, states, bytes, entries, *bits);
+                }
+                let other_bytes = if states != other_states {
+                    expand_entries(other_states, states, &other_bytes, other_entries, *bits)
+                } else {
+                    other_bytes
+                };
+                let (len, has_meta) = get_len_and_meta(states, *bits);
+                *max_states = states;
+                *meta_byte = has_meta;
+                *width = get_bytes_per_entry(len, has_meta) as u32;
+                append_entries(bytes, &other_bytes, *width as usize)
+            }
+            (
+                SignalChangeData::FixedLength { bytes, width, .. },
+                SignalChangeData::FixedLength {
+                    bytes: other_bytes, ..
+                },
+            ) => append_entries(bytes, &other_bytes, *width as usize),
+            (
+                SignalChangeData::VariableLength(strings),
+                SignalChangeData::VariableLength(other),
+            ) => {
+                let is_same = strings.last().is_some() && strings.last() == other.first();
+                strings.extend(other.into_iter().skip(is_same as usize));
+                is_same
+            }
+            _ => unreachable!("cannot append signals of different types"),
+        };
+        self.time_indices.extend(
+            other
+                .time_indices
+                .iter()
+                .skip(skip_first as usize)
+                .map(|ii| ii + time_offset),
+        );
+    }
+
     pub fn size_in_memory(&self) -> usize {
         let base = std::mem::size_of::<Self>();
         let time = self.time_indices.len() * std::mem::size_of::<TimeTableIdx>();
@@ -220,6 +557,17 @@ impl Signal {
         base + time + data
     }
 
+    /// Returns the number of bits of a bit vector signal. Returns None for reals and strings.
+    pub(crate) fn bits(&self) -> Option<u32> {
+        match self.data {
+            SignalChangeData::FixedLength {
+                encoding: SignalEncoding::BitVector { bits, .. },
+                ..
+            } => Some(bits),
+            _ => None,
+        }
+    }
+
     /// Returns the data offset for the nearest change at or before the provided idx.
     /// Returns `None` of not data is available for this signal at or before the idx.
     pub fn get_offset(&self, time_table_idx: TimeTableIdx) -> Option<DataOffset> {
@@ -242,6 +590,115 @@ impl Signal {
         self.data.get_value_at(offset.start + element as usize)
     }
 
+    /// Returns the value of the `idx`th change.
+    pub(crate) fn change_value(&self, idx: usize) -> SignalValue<'_> {
+        self.data.get_value_at(idx)
+    }
+
+    /// Returns the value of the signal at the time table index. If the value changes several
+    /// times in the same time step (delta cycles), the last value is returned. All values of a
+    /// time step are available through [`Signal::get_offset`].
+    /// Returns `None` if the signal does not have a value yet.
+    pub fn value_at_idx(&self, time_table_idx: TimeTableIdx) -> Option<SignalValue<'_>> {
+        let offset = self.get_offset(time_table_idx)?;
+        Some(self.get_value_at(&offset, offset.elements - 1))
+    }
+
+    /// Returns the value of the signal at `time`, i.e., the value of the last change at or before
+    /// `time`. The signal needs to be part of the `waveform`.
+    pub fn value_at_time(&self, waveform: &Waveform, time: Time) -> Option<SignalValue<'_>> {
+        self.value_at_idx(waveform.time_table_idx_at(time)?)
+    }
+
+    /// Splits the time range `start..end` into `buckets` equally sized parts and summarizes the
+    /// changes in each of them, e.g., to draw one pixel column per bucket. The summaries are
+    /// computed from a cached pyramid and thus do not require iterating over all changes.
+    /// The signal needs to be part of the `waveform`.
+    pub fn summarize(
+        &self,
+        waveform: &Waveform,
+        start: Time,
+        end: Time,
+        buckets: usize,
+    ) -> Vec<SignalSummary<'_>> {
+        let pyramid = waveform.summary_pyramid(self);
+        crate::summary::summarize(self, &pyramid, waveform.time_table(), start, end, buckets)
+    }
+
+    /// Returns the time table index of the first change after `time_table_idx`.
+    pub fn next_change(&self, time_table_idx: TimeTableIdx) -> Option<TimeTableIdx> {
+        let start = self.time_indices.partition_point(|i| *i <= time_table_idx);
+        self.time_indices.get(start).cloned()
+    }
+
+    /// Returns the time table index of the last change before `time_table_idx`.
+    pub fn prev_change(&self, time_table_idx: TimeTableIdx) -> Option<TimeTableIdx> {
+        let end = self.time_indices.partition_point(|i| *i < time_table_idx);
+        end.checked_sub(1).map(|ii| self.time_indices[ii])
+    }
+
+    /// Returns the time table index of the first edge after `time_table_idx`.
+    /// Always returns `None` for signals that are not 1-bit wide.
+    pub fn next_edge(&self, time_table_idx: TimeTableIdx, edge: Edge) -> Option<TimeTableIdx> {
+        let start = self.time_indices.partition_point(|i| *i <= time_table_idx);
+        (start.max(1)..self.time_indices.len())
+            .find(|ii| self.is_edge(*ii, edge))
+            .map(|ii| self.time_indices[ii])
+    }
+
+    /// Returns the time table index of the last edge before `time_table_idx`.
+    /// Always returns `None` for signals that are not 1-bit wide.
+    pub fn prev_edge(&self, time_table_idx: TimeTableIdx, edge: Edge) -> Option<TimeTableIdx> {
+        let end = self.time_indices.partition_point(|i| *i < time_table_idx);
+        (1..end)
+            .rev()
+            .find(|ii| self.is_edge(*ii, edge))
+            .map(|ii| self.time_indices[ii])
+    }
+
+    /// Returns the time table index of the first change after `time_table_idx` to a value
+    /// that matches the `pattern`.
+    pub fn next_match(
+        &self,
+        time_table_idx: TimeTableIdx,
+        pattern: &BitPattern,
+    ) -> Option<TimeTableIdx> {
+        let start = self.time_indices.partition_point(|i| *i <= time_table_idx);
+        (start..self.time_indices.len())
+            .find(|ii| pattern.matches(&self.data.get_value_at(*ii)))
+            .map(|ii| self.time_indices[ii])
+    }
+
+    /// Returns the time table index of the last change before `time_table_idx` to a value
+    /// that matches the `pattern`.
+    pub fn prev_match(
+        &self,
+        time_table_idx: TimeTableIdx,
+        pattern: &BitPattern,
+    ) -> Option<TimeTableIdx> {
+        let end = self.time_indices.partition_point(|i| *i < time_table_idx);
+        (0..end)
+            .rev()
+            .find(|ii| pattern.matches(&self.data.get_value_at(*ii)))
+            .map(|ii| self.time_indices[ii])
+    }
+
+    /// Checks whether the change at `offset` is an edge, delta cycles are treated like any
+    /// other change.
+    fn is_edge(&self, offset: usize, edge: Edge) -> bool {
+        let bit = |ii: usize| {
+            let value = self.data.get_value_at(ii);
+            match value.bits() {
+                Some(1) => value.bit_char(0),
+                _ => None,
+            }
+        };
+        match (bit(offset - 1), bit(offset)) {
+            (Some(from), Some(to)) => edge.matches(from, to),
+            _ => false,
+        }
+    }
+
     pub fn get_first_time_idx(&self) -> Option<TimeTableIdx> {
         self.time_indices.first().cloned()
     }
@@ -255,6 +712,82 @@ impl Signal {
     }
 }
 
+/// Transition of a 1-bit signal. Follows the Verilog definition, i.e., a change from `0` to `x`
+/// is a rising edge and a change from `x` to `0` is a falling edge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Edge {
+    Rising,
+    Falling,
+}
+
+impl Edge {
+    fn matches(self, from: char, to: char) -> bool {
+        let level = |c: char| match c {
+            '0' | 'l' => Some(false),
+            '1' | 'h' => Some(true),
+            _ => None,
+        };
+        let (from, to) = (level(from), level(to));
+        match self {
+            Edge::Rising => from != Some(true) && to != Some(false) && from != to,
+            Edge::Falling => from != Some(false) && to != Some(true) && from != to,
+        }
+    }
+}
+
+/// Matches bit-vector values bit by bit. Every bit either needs to be exactly the same
+/// state (`0`, `1`, `x`, `z`, `h`, `u`, `w`, `l` or `-`) or is a don't care (`?`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BitPattern {
+    /// one character per bit, starting with the lsb
+    bits: Vec<char>,
+}
+
+impl BitPattern {
+    /// Parses a pattern that starts with the msb, like `10x?_0011`. Underscores are ignored.
+    pub fn from_bit_string(pattern: &str) -> Self {
+        let bits = pattern
+            .chars()
+            .rev()
+            .filter(|c| *c != '_')
+            .map(|c| c.to_ascii_lowercase())
+            .collect();
+        Self { bits }
+    }
+
+    pub fn from_value(value: u64, bits: u32) -> Self {
+        Self::from_value_and_mask(value, u64::MAX, bits)
+    }
+
+    /// Only bits that are set in the `mask` are compared. Bits above 64 need to be zero.
+    pub fn from_value_and_mask(value: u64, mask: u64, bits: u32) -> Self {
+        let bits = (0..bits)
+            .map(|ii| {
+                if ii >= u64::BITS {
+                    '0'
+                } else if (mask >> ii) & 1 == 0 {
+                    '?'
+                } else if (value >> ii) & 1 == 1 {
+                    '1'
+                } else {
+                    '0'
+                }
+            })
+            .collect();
+        Self { bits }
+    }
+
+    /// Values of a different width never match.
+    pub fn matches(&self, value: &SignalValue) -> bool {
+        value.bits() == Some(self.bits.len() as u32)
+            && self
+                .bits
+                .iter()
+                .enumerate()
+                .all(|(ii, c)| *c == '?' || value.bit_char(ii as u32) == Some(*c))
+    }
+}
+
 pub struct SignalChangeIterator<'a> {
     signal: &'a Signal,
     offset: usize,
@@ -291,7 +824,7 @@ pub(crate) struct BitVectorBuilder {
 }
 
 impl BitVectorBuilder {
-    fn new(max_states: States, bits: u32) -> Self {
+    pub(crate) fn new(max_states: States, bits: u32) -> Self {
         assert!(bits > 0);
         let (len, has_meta) = get_len_and_meta(max_states, bits);
         let bytes_per_entry = get_bytes_per_entry(len, has_meta);
@@ -308,10 +841,10 @@ impl BitVectorBuilder {
         }
     }
 
-    fn add_change(&mut self, time_idx: TimeTableIdx, value: SignalValue) {
+    pub(crate) fn add_change(&mut self, time_idx: TimeTableIdx, value: SignalValue) {
         debug_assert_eq!(value.bits().unwrap(), self.bits);
         let local_encoding = value.states().unwrap();
-        debug_assert!(local_encoding.bits() >= self.max_states.bits());
+        debug_assert!(local_encoding.bits() <= self.max_states.bits());
         if self.bits == 1 {
             let value = value.data().unwrap()[0] & 0xf;
             let meta_data = (local_encoding as u8) << 6;
@@ -350,7 +883,7 @@ impl BitVectorBuilder {
         }
     }
 
-    fn finish(self, id: SignalRef) -> Signal {
+    pub(crate) fn finish(self, id: SignalRef) -> Signal {
         debug_assert_eq!(
             self.data.len(),
             self.time_indices.len() * self.bytes_per_entry
@@ -449,9 +982,10 @@ fn slice_n_states(
     let out_bits = msb - lsb + 1;
     debug_assert!(in_bits > out_bits);
     let mut working_byte = 0u8;
+    debug_assert_eq!(data.len(), in_bits.div_ceil(states.bits_in_a_byte()));
     for (out_bit, in_bit) in (lsb..(msb + 1)).enumerate().rev() {
-        let rev_in_bit = in_bits - in_bit - 1;
-        let in_byte = data[rev_in_bit / states.bits_in_a_byte()];
+        // data is big endian and right aligned
+        let in_byte = data[data.len() - 1 - in_bit / states.bits_in_a_byte()];
         let in_value =
             (in_byte >> ((in_bit % states.bits_in_a_byte()) * states.bits())) & states.mask();
 
@@ -467,10 +1001,13 @@ fn slice_n_states(
 pub struct Waveform {
     hierarchy: Hierarchy,
     source: Box<dyn SignalSource + Send + Sync>,
-    time_table: Vec<Time>,
+    time_table: Arc<[Time]>,
     /// Signals are stored in a HashMap since we expect only a small subset of signals to be
     /// loaded at a time.
     signals: HashMap<SignalRef, Signal>,
+    cache: SignalCache,
+    /// Built on demand by [`Signal::summarize`] and dropped when the signal is unloaded.
+    summaries: RwLock<HashMap<SignalRef, Arc<SummaryPyramid>>>,
 }
 
 impl Debug for Waveform {
@@ -487,9 +1024,17 @@ impl Waveform {
             source,
             time_table,
             signals: HashMap::new(),
+            cache: SignalCache::default(),
+            summaries: RwLock::default(),
         }
     }
 
+    /// Combines a hierarchy and the signal values that were loaded separately,
+    /// e.g., through [`crate::open_header`].
+    pub fn from_parts(hierarchy: Hierarchy, body: crate::Body) -> Self {
+        Self::new(hierarchy, body.into_source())
+    }
+
     pub fn hierarchy(&self) -> &Hierarchy {
         &self.hierarchy
     }
@@ -498,66 +1043,304 @@ impl Waveform {
         &self.time_table
     }
 
-    fn load_signals_internal(&mut self, ids: &[SignalRef], multi_threaded: bool) {
-        // sort and dedup ids
-        let mut ids = Vec::from_iter(ids.iter().cloned());
-        ids.sort();
-        ids.dedup();
+    /// Returns the index of the last time step at or before `time`.
+    /// Returns `None` if `time` is before the first time step.
+    pub fn time_table_idx_at(&self, time: Time) -> Option<TimeTableIdx> {
+        let steps = self.time_table.partition_point(|t| *t <= time);
+        steps.checked_sub(1).map(|idx| idx as TimeTableIdx)
+    }
 
-        // replace any aliases by their source signal
-        let orig_ids = ids.clone();
-        let mut is_alias = vec![false; ids.len()];
-        for (ii, id) in ids.iter_mut().enumerate() {
-            if let Some(slice) = self.hierarchy.get_slice_info(*id) {
-                *id = slice.sliced_signal;
-                is_alias[ii] = true;
-            }
+    /// Returns the values of the signals at `time` and loads all signals that are not in
+    /// memory yet. The result is `None` for signals that do not have a value yet.
+    pub fn sample(
+        &mut self,
+        ids: &[SignalRef],
+        time: Time,
+    ) -> Result<Vec<Option<SignalValue<'_>>>, WellenError> {
+        self.load_signals(ids)?;
+        let idx = self.time_table_idx_at(time);
+        Ok(ids
+            .iter()
+            .map(|id| idx.and_then(|i| self.signals[id].value_at_idx(i)))
+            .collect())
+    }
+
+    /// Returns the value of `var` at `time_table_idx` as the name of an enum literal and loads
+    /// the signal if it is not in memory yet. Values without a matching literal and variables
+    /// without an enum type are formatted as bits. Returns `None` if the signal does not have
+    /// a value yet.
+    pub fn enum_value_at(
+        &mut self,
+        var: VarRef,
+        time_table_idx: TimeTableIdx,
+    ) -> Result<Option<String>, WellenError> {
+        let signal_ref = self.hierarchy.get(var).signal_ref();
+        self.load_signals(&[signal_ref])?;
+        let Some(value) = self.signals[&signal_ref].value_at_idx(time_table_idx) else {
+            return Ok(None);
+        };
+        let literal = self
+            .hierarchy
+            .get(var)
+            .enum_type(&self.hierarchy)
+            .and_then(|(_, mapping)| value.to_enum_literal(&mapping));
+        Ok(Some(match literal {
+            Some(name) => name.to_string(),
+            None => value.to_string(),
+        }))
+    }
+
+    /// Computes a new signal from other signals, e.g., `a & ~b` or `count == 3`, and loads
+    /// all inputs that are not in memory yet.
+    /// The result changes whenever one of its inputs changes and is not part of the hierarchy.
+    pub fn derive_signal(&mut self, expr: &crate::Expr) -> Result<Signal, WellenError> {
+        self.load_signals(&expr.signals())?;
+        crate::expr::derive_signal(self, expr)
+    }
+
+    fn load_signals_internal(
+        &mut self,
+        ids: &[SignalRef],
+        multi_threaded: bool,
+    ) -> Result<(), WellenError> {
+        let signals =
+            load_signals_from_source(&self.hierarchy, &*self.source, ids, multi_threaded)?;
+        for (id, signal) in signals {
+            // a reloaded signal might contain new changes
+            self.remove_summary(id);
+            self.signals.insert(id, signal);
         }
+        Ok(())
+    }
 
-        // collect meta data
-        let types: Vec<_> = ids
-            .iter()
-            .map(|i| self.hierarchy.get_signal_tpe(*i).unwrap())
-            .collect();
-        let signals = self.source.load_signals(&ids, &types, multi_threaded);
-        // the signal source must always return the correct number of signals!
-        assert_eq!(signals.len(), ids.len());
-        for ((id, is_alias), signal) in orig_ids
-            .iter()
-            .zip(is_alias.iter())
-            .zip(signals.into_iter())
-        {
-            if *is_alias {
-                let slice = self.hierarchy.get_slice_info(*id).unwrap();
-                let sliced = slice_signal(*id, &signal, slice.msb, slice.lsb);
-                self.signals.insert(*id, sliced);
+    /// Loads all signals that are not in memory yet.
+    fn load_missing_signals(
+        &mut self,
+        ids: &[SignalRef],
+        multi_threaded: bool,
+    ) -> Result<(), WellenError> {
+        let mut missing = vec![];
+        for id in ids.iter() {
+            if self.signals.contains_key(id) {
+                self.cache.hit(*id);
             } else {
-                self.signals.insert(*id, signal);
+                missing.push(*id);
+            }
+        }
+        missing.sort();
+        missing.dedup();
+        if missing.is_empty() {
+            return Ok(());
+        }
+        self.load_signals_internal(&missing, multi_threaded)?;
+        missing.iter().for_each(|id| self.cache.miss(*id));
+        let mut pinned = ids.to_vec();
+        pinned.sort();
+        self.enforce_memory_budget(&pinned);
+        Ok(())
+    }
+
+    pub fn load_signals(&mut self, ids: &[SignalRef]) -> Result<(), WellenError> {
+        self.load_missing_signals(ids, false)
+    }
+
+    pub fn load_signals_multi_threaded(&mut self, ids: &[SignalRef]) -> Result<(), WellenError> {
+        self.load_missing_signals(ids, true)
+    }
+
+    /// Returns a signal and loads it first if it is not in memory,
+    /// e.g., because it was unloaded in order to stay within the memory budget.
+    pub fn get_or_load_signal(&mut self, id: SignalRef) -> Result<&Signal, WellenError> {
+        self.load_signals(&[id])?;
+        Ok(&self.signals[&id])
+    }
+
+    /// Limits the memory used by loaded signals to roughly `bytes`. Whenever loading signals
+    /// exceeds the budget, the least recently used signals are unloaded. Signals requested by
+    /// the same call to [`Waveform::load_signals`] are never unloaded, even if they exceed
+    /// the budget. Unloaded signals are transparently reloaded by all methods that take
+    /// `&mut self`, e.g., [`Waveform::get_or_load_signal`] and [`Waveform::sample`], while
+    /// [`Waveform::get_signal`] returns `None` for them.
+    /// `None` removes the limit.
+    pub fn set_memory_budget(&mut self, bytes: Option<usize>) {
+        self.cache.set_budget(bytes);
+        self.enforce_memory_budget(&[]);
+    }
+
+    pub fn memory_budget(&self) -> Option<usize> {
+        self.cache.budget()
+    }
+
+    /// `pinned` needs to be sorted.
+    fn enforce_memory_budget(&mut self, pinned: &[SignalRef]) {
+        let Some(budget) = self.cache.budget() else {
+            return;
+        };
+        let mut used = self.signals_size_in_memory();
+        for id in self.cache.lru_order() {
+            if used <= budget {
+                break;
+            }
+            if pinned.binary_search(&id).is_err() {
+                if let Some(signal) = self.signals.remove(&id) {
+                    used -= signal.size_in_memory() + self.remove_summary(id);
+                    self.cache.evict(id);
+                }
             }
         }
     }
 
-    pub fn load_signals(&mut self, ids: &[SignalRef]) {
-        self.load_signals_internal(ids, false)
+    fn signals_size_in_memory(&self) -> usize {
+        let summaries: usize = self
+            .summaries
+            .read()
+            .unwrap()
+            .values()
+            .map(|s| s.size_in_memory())
+            .sum();
+        let signals: usize = self.signals.values().map(|s| s.size_in_memory()).sum();
+        signals + summaries
     }
 
-    pub fn load_signals_multi_threaded(&mut self, ids: &[SignalRef]) {
-        self.load_signals_internal(ids, true)
+    /// Returns the cached summary of a loaded signal. Signals that are not part of this
+    /// waveform, e.g., derived signals, are summarized without caching the result.
+    fn summary_pyramid(&self, signal: &Signal) -> Arc<SummaryPyramid> {
+        let is_loaded = self
+            .signals
+            .get(&signal.idx)
+            .is_some_and(|s| std::ptr::eq(s, signal));
+        if !is_loaded {
+            return Arc::new(SummaryPyramid::new(signal));
+        }
+        if let Some(pyramid) = self.summaries.read().unwrap().get(&signal.idx) {
+            return pyramid.clone();
+        }
+        let pyramid = Arc::new(SummaryPyramid::new(signal));
+        let mut summaries = self.summaries.write().unwrap();
+        summaries.entry(signal.idx).or_insert(pyramid).clone()
+    }
+
+    /// Returns the number of bytes that were freed.
+    fn remove_summary(&mut self, id: SignalRef) -> usize {
+        let summaries = self.summaries.get_mut().unwrap();
+        summaries.remove(&id).map_or(0, |s| s.size_in_memory())
+    }
+
+    /// Reports how often signals were served from memory or had to be loaded.
+    pub fn cache_statistics(&self) -> CacheStatistics {
+        self.cache
+            .statistics(self.signals.len(), self.signals_size_in_memory())
+    }
+
+    /// Appends value changes that come after the end of the current time table.
+    /// Only the new changes are decoded and appended to the signals that are currently loaded.
+    pub(crate) fn append_values(
+        &mut self,
+        values: crate::wavemem::Reader,
+    ) -> Result<(), WellenError> {
+        let loaded: Vec<_> = self.signals.keys().cloned().collect();
+        let new_changes = load_signals_from_source(&self.hierarchy, &values, &loaded, false)?;
+        let time_offset = self.time_table.len() as TimeTableIdx;
+        self.source.append(values)?;
+        self.time_table = self.source.get_time_table();
+        for (id, changes) in new_changes {
+            self.remove_summary(id);
+            self.signals
+                .get_mut(&id)
+                .unwrap()
+                .append(changes, time_offset);
+        }
+        // new changes make signals larger
+        self.enforce_memory_budget(&[]);
+        Ok(())
     }
 
     pub fn unload_signals(&mut self, ids: &[SignalRef]) {
         for id in ids.iter() {
             self.signals.remove(id);
+            self.remove_summary(*id);
+            self.cache.remove(*id);
         }
     }
 
+    /// Returns a loaded signal. Use [`Waveform::get_or_load_signal`] if a memory budget is
+    /// set, since signals may have been unloaded in order to stay within the budget.
     pub fn get_signal(&self, id: SignalRef) -> Option<&Signal> {
-        self.signals.get(&id)
+        let signal = self.signals.get(&id)?;
+        // accesses only need to be tracked if signals may be evicted
+        if self.cache.budget().is_some() {
+            self.cache.hit(id);
+        }
+        Some(signal)
     }
 
     pub fn print_backend_statistics(&self) {
         self.source.print_statistics();
     }
+
+    /// Converts the waveform into one that can be shared between threads.
+    /// Signals that are already loaded remain loaded.
+    pub fn into_shared(self) -> SharedWaveform {
+        SharedWaveform::new(self.hierarchy, self.source, self.time_table, self.signals)
+    }
+}
+
+/// Loads signals and computes the value of sliced signals from the signal they alias.
+pub(crate) fn load_signals_from_source(
+    hierarchy: &Hierarchy,
+    source: &(dyn SignalSource + Send + Sync),
+    ids: &[SignalRef],
+    multi_threaded: bool,
+) -> Result<Vec<(SignalRef, Signal)>, WellenError> {
+    // sort and dedup ids
+    let mut ids = Vec::from_iter(ids.iter().cloned());
+    ids.sort();
+    ids.dedup();
+
+    // replace any aliases by their source signal
+    let orig_ids = ids.clone();
+    let mut is_alias = vec![false; ids.len()];
+    for (ii, id) in ids.iter_mut().enumerate() {
+        if let Some(slice) = hierarchy.get_slice_info(*id) {
+            *id = slice.sliced_signal;
+            is_alias[ii] = true;
+        }
+    }
+
+    // signals that were filtered out when opening the file do not have any values
+    if let Some(mask) = source.signal_mask() {
+        if let Some(pos) = ids.iter().position(|id| !mask[id.index()]) {
+            let id = orig_ids[pos];
+            let name = match hierarchy.iter_vars().find(|v| v.signal_ref() == id) {
+                Some(var) => var.full_name(hierarchy),
+                None => format!("{id:?}"),
+            };
+            return Err(WellenError::SignalFiltered(name));
+        }
+    }
+
+    // collect meta data
+    let types: Vec<_> = ids
+        .iter()
+        .map(|i| hierarchy.get_signal_tpe(*i).unwrap())
+        .collect();
+    let signals = source.load_signals(&ids, &types, multi_threaded)?;
+    // the signal source must always return the correct number of signals!
+    assert_eq!(signals.len(), ids.len());
+    let out = orig_ids
+        .iter()
+        .zip(is_alias.iter())
+        .zip(signals)
+        .map(|((id, is_alias), signal)| {
+            if *is_alias {
+                let slice = hierarchy.get_slice_info(*id).unwrap();
+                (*id, slice_signal(*id, &signal, slice.msb, slice.lsb))
+            } else {
+                (*id, signal)
+            }
+        })
+        .collect();
+    Ok(out)
 }
 
 /// Finds the index that is the same or less than the needle and returns the position of it.
@@ -716,18 +1499,47 @@ impl SignalChangeData {
 }
 
 pub(crate) trait SignalSource {
-    /// Loads new signals.
+    /// Loads new signals. May be called by multiple threads at the same time.
     /// Many implementations take advantage of loading multiple signals at a time.
     fn load_signals(
-        &mut self,
+        &self,
         ids: &[SignalRef],
         types: &[SignalType],
         multi_threaded: bool,
-    ) -> Vec<Signal>;
+    ) -> Result<Vec<Signal>, WellenError>;
+    /// Adds value changes that were parsed after this source was created.
+    /// Only supported by sources that keep all values in memory.
+    fn append(&mut self, other: crate::wavemem::Reader) -> Result<(), WellenError>;
     /// Returns the global time table which stores the time at each value change.
-    fn get_time_table(&self) -> Vec<Time>;
+    /// The table is shared with the source in order to avoid copying it.
+    fn get_time_table(&self) -> Arc<[Time]>;
     /// Print memory size / speed statistics.
     fn print_statistics(&self);
+    /// Marks the signals whose values are available, `None` if no signal filter was used.
+    fn signal_mask(&self) -> Option<&[bool]>;
+}
+
+#[cfg(test)]
+impl Waveform {
+    /// Loads all signals and converts their changes to strings in order to make them easy
+    /// to compare in tests.
+    pub(crate) fn all_changes(&mut self) -> Vec<Vec<(TimeTableIdx, String)>> {
+        let ids: Vec<_> = self
+            .hierarchy()
+            .iter_vars()
+            .map(|v| v.signal_ref())
+            .collect();
+        self.load_signals(&ids).unwrap();
+        ids.iter()
+            .map(|id| {
+                let signal = self.get_signal(*id).unwrap();
+                signal
+                    .iter_changes()
+                    .map(|(t, v)| (t, v.to_string()))
+                    .collect()
+            })
+            .collect()
+    }
 }
 
 #[cfg(test)]
@@ -809,11 +1621,204 @@ mod tests {
         assert_eq!(out, expected);
     }
 
+    #[test]
+    fn test_to_bit_string_non_bit_vector() {
+        assert_eq!(SignalValue::Real(1.5).to_bit_string(), None);
+        assert_eq!(SignalValue::String("test").to_bit_string(), None);
+    }
+
     #[test]
     fn test_slice_signal() {
         let mut out = vec![];
         slice_n_states(States::Two, &[0b001001], &mut out, 3, 3, 7);
         assert_eq!(out[0], 1);
         out.clear();
+
+        // the first byte is only partially used
+        slice_n_states(States::Two, &[0b1010, 0b1100_0011], &mut out, 9, 2, 12);
+        assert_eq!(out, [0b1011_0000]);
+        out.clear();
+        slice_n_states(States::Nine, &[0x1, 0x23, 0x45], &mut out, 4, 1, 5);
+        assert_eq!(out, [0x12, 0x34]);
+        out.clear();
+    }
+
+    #[test]
+    fn test_value_at_idx_with_delta_cycles() {
+        let id = SignalRef::from_index(0).unwrap();
+        let strings = ["a", "b", "c"].map(String::from).to_vec();
+        let signal = Signal::new_var_len(id, vec![1, 1, 3], strings);
+        let value = |idx| signal.value_at_idx(idx).map(|v| v.to_string());
+        assert_eq!(value(0), None);
+        // the last delta cycle determines the value
+        assert_eq!(value(1).as_deref(), Some("b"));
+        assert_eq!(value(2).as_deref(), Some("b"));
+        assert_eq!(value(3).as_deref(), Some("c"));
+        assert_eq!(value(100).as_deref(), Some("c"));
+    }
+
+    fn load_all(vcd: &[u8]) -> Waveform {
+        let mut waves = crate::vcd::read_from_bytes(vcd).unwrap();
+        let ids: Vec<_> = waves
+            .hierarchy()
+            .iter_vars()
+            .map(|v| v.signal_ref())
+            .collect();
+        waves.load_signals(&ids).unwrap();
+        waves
+    }
+
+    #[test]
+    fn test_append_signal() {
+        let id = SignalRef::from_index(0).unwrap();
+        let header = "$var wire 2 ! bus $end\n$enddefinitions $end\n";
+        let signal = |body: &str| {
+            let mut waves = load_all(format!("{header}{body}").as_bytes());
+            waves.signals.remove(&id).unwrap()
+        };
+        let mut bus = signal("#0\nb01 !\n#1\nb10 !\n");
+        // the first change repeats the last value and the second one needs more states
+        bus.append(signal("#5\nb10 !\n#6\nbx0 !\n"), 2);
+        let changes: Vec<_> = bus
+            .iter_changes()
+            .map(|(t, v)| (t, v.to_string()))
+            .collect();
+        assert_eq!(
+            changes,
+            [(0, "01"), (1, "10"), (3, "x0")].map(|(t, v)| (t, v.to_string()))
+        );
+
+        let strings = |s: &[&str]| s.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        let mut text = Signal::new_var_len(id, vec![0, 1], strings(&["a", "b"]));
+        text.append(Signal::new_var_len(id, vec![0, 1], strings(&["b", "c"])), 2);
+        assert_eq!(text.time_indices(), [0, 1, 3]);
+    }
+
+    #[test]
+    fn test_edge_search() {
+        let waves = load_all(
+            b"$var wire 1 ! clk $end\n$enddefinitions $end\n\
+              #0\n0!\n#1\n1!\n#2\n0!\n#3\nx!\n#4\n1!\n#5\n0!\n",
+        );
+        let clk = waves.get_signal(SignalRef::from_index(0).unwrap()).unwrap();
+        assert_eq!(clk.next_change(2), Some(3));
+        assert_eq!(clk.next_change(5), None);
+        assert_eq!(clk.prev_change(0), None);
+        assert_eq!(clk.prev_change(3), Some(2));
+
+        assert_eq!(clk.next_edge(0, Edge::Rising), Some(1));
+        // 0 -> x and x -> 1 are both rising edges
+        assert_eq!(clk.next_edge(1, Edge::Rising), Some(3));
+        assert_eq!(clk.next_edge(3, Edge::Rising), Some(4));
+        assert_eq!(clk.next_edge(4, Edge::Rising), None);
+        assert_eq!(clk.next_edge(0, Edge::Falling), Some(2));
+        assert_eq!(clk.prev_edge(5, Edge::Falling), Some(2));
+        assert_eq!(clk.prev_edge(6, Edge::Falling), Some(5));
+        assert_eq!(clk.prev_edge(1, Edge::Rising), None);
+
+        // x-aware comparison
+        let x = BitPattern::from_bit_string("X");
+        assert_eq!(clk.next_match(0, &x), Some(3));
+        assert_eq!(clk.next_match(3, &x), None);
+    }
+
+    #[test]
+    fn test_value_search() {
+        let waves = load_all(
+            b"$var wire 8 ! bus $end\n$enddefinitions $end\n\
+              #0\nb0 !\n#1\nb10101101 !\n#2\nb11011110 !\n#3\nb10101101 !\n#4\nb1010x101 !\n",
+        );
+        let bus = waves.get_signal(SignalRef::from_index(0).unwrap()).unwrap();
+        let ad = BitPattern::from_value(0xad, 8);
+        assert_eq!(bus.next_match(0, &ad), Some(1));
+        assert_eq!(bus.next_match(1, &ad), Some(3));
+        assert_eq!(bus.prev_match(3, &ad), Some(1));
+        // only compare the lower nibble
+        let d = BitPattern::from_value_and_mask(0x0d, 0x0f, 8);
+        assert_eq!(bus.next_match(1, &d), Some(3));
+        let wildcard = BitPattern::from_bit_string("1010_?101");
+        assert_eq!(bus.next_match(3, &wildcard), Some(4));
+        // different width never matches
+        assert_eq!(bus.next_match(0, &BitPattern::from_value(0xad, 16)), None);
+    }
+
+    #[test]
+    fn test_numeric_conversion() {
+        let value = SignalValue::Binary(&[0xad], 8);
+        assert_eq!(value.to_u64(), Some(0xad));
+        assert_eq!(value.to_i64(), Some(-83));
+        assert_eq!(value.to_string_radix(Radix::Binary), "10101101");
+        assert_eq!(value.to_string_radix(Radix::Octal), "255");
+        assert_eq!(value.to_string_radix(Radix::Hex), "ad");
+        assert_eq!(value.to_string_radix(Radix::Unsigned), "173");
+        assert_eq!(value.to_string_radix(Radix::Signed), "-83");
+
+        let value = SignalValue::Binary(&[0x01, 0xff], 9);
+        assert_eq!(value.to_u64(), Some(511));
+        assert_eq!(value.to_i64(), Some(-1));
+        assert_eq!(value.to_string_radix(Radix::Hex), "1ff");
+        assert_eq!(value.to_string_radix(Radix::Signed), "-1");
+
+        // wider than 64 bits
+        let value = SignalValue::Binary(&[0xff; 9], 72);
+        assert_eq!(value.to_u64(), None);
+        assert_eq!(value.to_i64(), Some(-1));
+        assert_eq!(
+            value.to_string_radix(Radix::Unsigned),
+            "4722366482869645213695"
+        );
+        assert_eq!(value.to_string_radix(Radix::Signed), "-1");
+        assert_eq!(
+            SignalValue::Binary(&[0], 0).to_string_radix(Radix::Unsigned),
+            "0"
+        );
+
+        assert_eq!(SignalValue::Real(1.5).to_string_radix(Radix::Hex), "1.5");
+        assert_eq!(SignalValue::String("a").to_u64(), None);
+    }
+
+    #[test]
+    fn test_radix_with_unknown_bits() {
+        // 1010x101
+        let value = SignalValue::FourValue(&[0x44, 0x91], 8);
+        assert_eq!(value.to_string(), "1010x101");
+        assert_eq!(value.to_u64(), None);
+        assert_eq!(value.to_i64(), None);
+        assert_eq!(value.to_string_radix(Radix::Hex), "ax");
+        assert_eq!(value.to_string_radix(Radix::Octal), "2x5");
+        assert_eq!(value.to_string_radix(Radix::Unsigned), "x");
+
+        // zzzz
+        let value = SignalValue::FourValue(&[0xff], 4);
+        assert_eq!(value.to_string_radix(Radix::Hex), "z");
+        assert_eq!(value.to_string_radix(Radix::Signed), "z");
+    }
+
+    #[test]
+    fn test_ascii() {
+        let value = SignalValue::Binary(b"\0Hi\n", 32);
+        assert_eq!(value.to_string_radix(Radix::Ascii), "Hi.");
+    }
+
+    #[test]
+    fn test_enum_literal() {
+        let binary = [("00", "IDLE"), ("01", "RUN"), ("10", "DONE")];
+        let run = SignalValue::Binary(&[0b01], 2);
+        assert_eq!(run.to_enum_literal(&binary), Some("RUN"));
+        assert_eq!(
+            SignalValue::Binary(&[0b11], 2).to_enum_literal(&binary),
+            None
+        );
+        // keys without leading zeros
+        assert_eq!(
+            run.to_enum_literal(&[("0", "IDLE"), ("1", "RUN")]),
+            Some("RUN")
+        );
+        // hex keys
+        let hex = [("0x0", "IDLE"), ("0x1", "RUN"), ("a", "TEN")];
+        assert_eq!(run.to_enum_literal(&hex), Some("RUN"));
+        let ten = SignalValue::Binary(&[0b1010], 8);
+        assert_eq!(ten.to_enum_literal(&hex), Some("TEN"));
+        assert_eq!(SignalValue::Real(1.0).to_enum_literal(&hex), None);
     }
 }
@@ -0,0 +1,309 @@
+// Copyright 2024 The Regents of the University of California
+// released under BSD 3-Clause License
+// author: Kevin Laeufer <laeufer@berkeley.edu>
+//
+// Multi-resolution summaries of signal changes which allow a viewer to render a zoomed-out
+// signal without iterating over every single change.
+
+use crate::signals::{bit_value, Signal, SignalValue, Time};
+
+/// Number of nodes (or changes) that are combined into a single node of the next level.
+const FAN_IN: usize = 16;
+
+/// Summarizes the changes of a signal within a time range.
+#[derive(Debug, Clone)]
+pub struct SignalSummary<'a> {
+    /// Number of value changes within the time range.
+    pub transitions: usize,
+    /// Value at the start of the time range. `None` if the signal does not have a value yet.
+    pub first: Option<SignalValue<'a>>,
+    /// Value at the end of the time range. `None` if the signal does not have a value yet.
+    pub last: Option<SignalValue<'a>>,
+    /// Indicates that a bit vector had at least one unknown bit, e.g., `x` or `z`.
+    pub has_unknown: bool,
+    /// Smallest numeric value. Reals and bit vectors of up to 64 bits without unknown
+    /// bits are interpreted as numbers. All other values are ignored.
+    pub min: Option<f64>,
+    /// Largest numeric value.
+    pub max: Option<f64>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Node {
+    has_unknown: bool,
+    min: f64,
+    max: f64,
+}
+
+impl Node {
+    const EMPTY: Self = Node {
+        has_unknown: false,
+        min: f64::INFINITY,
+        max: f64::NEG_INFINITY,
+    };
+
+    fn from_value(value: SignalValue) -> Self {
+        let number = match value {
+            SignalValue::Real(value) => Some(value),
+            SignalValue::String(_) => None,
+            _ => value.to_u64().map(|v| v as f64),
+        };
+        let has_unknown = match value {
+            SignalValue::FourValue(..) | SignalValue::NineValue(..) => value
+                .to_bit_string()
+                .unwrap()
+                .chars()
+                .any(|c| bit_value(c).is_none()),
+            _ => false,
+        };
+        Node {
+            has_unknown,
+            min: number.unwrap_or(f64::INFINITY),
+            max: number.unwrap_or(f64::NEG_INFINITY),
+        }
+    }
+
+    fn join(&mut self, other: &Self) {
+        self.has_unknown |= other.has_unknown;
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+    }
+}
+
+/// Level `l` of the pyramid combines `FAN_IN^(l+1)` consecutive changes into a single node.
+/// Thus any range of changes can be summarized by looking at `O(FAN_IN * levels)` nodes.
+#[derive(Debug)]
+pub(crate) struct SummaryPyramid {
+    levels: Vec<Vec<Node>>,
+}
+
+impl SummaryPyramid {
+    pub(crate) fn new(signal: &Signal) -> Self {
+        let num_changes = signal.time_indices().len();
+        let mut levels: Vec<Vec<Node>> = vec![];
+        if num_changes > FAN_IN {
+            let leaves = (0..num_changes)
+                .map(|ii| Node::from_value(signal.change_value(ii)))
+                .collect::<Vec<_>>();
+            levels.push(combine(&leaves));
+        }
+        while levels.last().is_some_and(|l| l.len() > FAN_IN) {
+            let next = combine(levels.last().unwrap());
+            levels.push(next);
+        }
+        Self { levels }
+    }
+
+    pub(crate) fn size_in_memory(&self) -> usize {
+        let nodes: usize = self.levels.iter().map(|l| l.len()).sum();
+        std::mem::size_of::<Self>() + nodes * std::mem::size_of::<Node>()
+    }
+
+    /// Combines the changes `lo..hi`.
+    fn summarize(&self, signal: &Signal, mut lo: usize, mut hi: usize) -> Node {
+        let mut out = Node::EMPTY;
+        // level 0 refers to the changes themselves
+        let get = |level: usize, ii: usize| match level {
+            0 => Node::from_value(signal.change_value(ii)),
+            _ => self.levels[level - 1][ii],
+        };
+        let mut unit = 1;
+        for level in 0..=self.levels.len() {
+            if level == self.levels.len() {
+                for ii in lo / unit..hi / unit {
+                    out.join(&get(level, ii));
+                }
+                break;
+            }
+            let next_unit = unit * FAN_IN;
+            while lo < hi && !lo.is_multiple_of(next_unit) {
+                out.join(&get(level, lo / unit));
+                lo += unit;
+            }
+            while lo < hi && !hi.is_multiple_of(next_unit) {
+                hi -= unit;
+                out.join(&get(level, hi / unit));
+            }
+            if lo == hi {
+                break;
+            }
+            unit = next_unit;
+        }
+        out
+    }
+}
+
+fn combine(nodes: &[Node]) -> Vec<Node> {
+    nodes
+        .chunks(FAN_IN)
+        .map(|chunk| {
+            let mut node = Node::EMPTY;
+            chunk.iter().for_each(|n| node.join(n));
+            node
+        })
+        .collect()
+}
+
+/// Splits `start..end` into `buckets` equally sized time ranges and summarizes each of them.
+pub(crate) fn summarize<'a>(
+    signal: &'a Signal,
+    pyramid: &SummaryPyramid,
+    time_table: &[Time],
+    start: Time,
+    end: Time,
+    buckets: usize,
+) -> Vec<SignalSummary<'a>> {
+    let indices = signal.time_indices();
+    // index of the first change after `time`, or at `time` if `inclusive` is not set
+    let find = |time: Time, inclusive: bool| {
+        indices.partition_point(|ii| {
+            let t = time_table[*ii as usize];
+            t < time || (inclusive && t == time)
+        })
+    };
+    let end = end.max(start);
+    let duration = (end - start) as u128;
+    let bucket_start = |b: usize| start + (duration * b as u128 / buckets as u128) as Time;
+    (0..buckets)
+        .map(|b| {
+            let (t0, t1) = (bucket_start(b), bucket_start(b + 1));
+            let first = find(t0, true).checked_sub(1);
+            let lo = find(t0, false);
+            let hi = find(t1, false).max(lo);
+            let last = hi.checked_sub(1);
+            // include the value that was active when the bucket started
+            let node = pyramid.summarize(signal, first.unwrap_or(lo).min(lo), hi);
+            SignalSummary {
+                transitions: hi - lo,
+                first: first.map(|ii| signal.change_value(ii)),
+                last: last.map(|ii| signal.change_value(ii)),
+                has_unknown: node.has_unknown,
+                min: (node.min <= node.max).then_some(node.min),
+                max: (node.min <= node.max).then_some(node.max),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Waveform;
+
+    /// A counter that changes at `5, 15, 25, ...` and is unknown once.
+    fn load_counter(changes: usize) -> Waveform {
+        let mut vcd = "$var wire 8 ! cnt $end\n$enddefinitions $end\n".to_string();
+        for ii in 0..changes {
+            let value = if ii == 100 {
+                "x".to_string()
+            } else {
+                format!("{:b}", ii % 256)
+            };
+            vcd.push_str(&format!("#{}\nb{value} !\n", ii * 10 + 5));
+        }
+        let mut waves = crate::vcd::read_from_bytes(vcd.as_bytes()).unwrap();
+        let ids: Vec<_> = waves
+            .hierarchy()
+            .iter_vars()
+            .map(|v| v.signal_ref())
+            .collect();
+        waves.load_signals(&ids).unwrap();
+        waves
+    }
+
+    fn get_signal(waves: &Waveform) -> &Signal {
+        let id = waves.hierarchy().iter_vars().next().unwrap().signal_ref();
+        waves.get_signal(id).unwrap()
+    }
+
+    /// Computes the same summary as the pyramid by looking at every change.
+    fn reference(
+        waves: &Waveform,
+        start: Time,
+        end: Time,
+    ) -> (usize, bool, Option<f64>, Option<f64>) {
+        let signal = get_signal(waves);
+        let mut node = Node::EMPTY;
+        let mut transitions = 0;
+        if let Some(v) = signal.value_at_time(waves, start) {
+            node.join(&Node::from_value(v));
+        }
+        for (idx, value) in signal.iter_changes() {
+            let time = waves.time_table()[idx as usize];
+            if time >= start && time < end {
+                transitions += 1;
+                node.join(&Node::from_value(value));
+            }
+        }
+        let valid = node.min <= node.max;
+        (
+            transitions,
+            node.has_unknown,
+            valid.then_some(node.min),
+            valid.then_some(node.max),
+        )
+    }
+
+    #[test]
+    fn test_summary_matches_changes() {
+        let waves = load_counter(5000);
+        let signal = get_signal(&waves);
+        for (start, end, buckets) in [(0, 50_000, 7), (995, 1_005, 3), (333, 41_111, 100)] {
+            let summaries = signal.summarize(&waves, start, end, buckets);
+            assert_eq!(summaries.len(), buckets);
+            let mut total = 0;
+            for (b, summary) in summaries.iter().enumerate() {
+                let t0 = start + (b as Time * (end - start)) / buckets as Time;
+                let t1 = start + ((b + 1) as Time * (end - start)) / buckets as Time;
+                let expected = reference(&waves, t0, t1);
+                let actual = (
+                    summary.transitions,
+                    summary.has_unknown,
+                    summary.min,
+                    summary.max,
+                );
+                assert_eq!(actual, expected, "bucket {b}: {t0}..{t1}");
+                let first = signal.value_at_time(&waves, t0).map(|v| v.to_string());
+                assert_eq!(summary.first.map(|v| v.to_string()), first);
+                total += summary.transitions;
+            }
+            let (expected_total, ..) = reference(&waves, start, end);
+            assert_eq!(total, expected_total);
+        }
+    }
+
+    #[test]
+    fn test_summary_is_cached_until_unloaded() {
+        let mut waves = load_counter(5000);
+        let id = waves.hierarchy().iter_vars().next().unwrap().signal_ref();
+        let before = waves.cache_statistics().bytes_in_memory;
+        get_signal(&waves).summarize(&waves, 0, 50_000, 10);
+        let with_summary = waves.cache_statistics().bytes_in_memory;
+        assert!(with_summary > before);
+        get_signal(&waves).summarize(&waves, 0, 50_000, 10);
+        assert_eq!(waves.cache_statistics().bytes_in_memory, with_summary);
+        waves.unload_signals(&[id]);
+        assert_eq!(waves.cache_statistics().bytes_in_memory, 0);
+    }
+
+    #[test]
+    fn test_summary_of_few_changes() {
+        let waves = load_counter(3);
+        let signal = get_signal(&waves);
+        let summaries = signal.summarize(&waves, 0, 30, 3);
+        // no value before the first change
+        assert!(summaries[0].first.is_none());
+        assert_eq!(summaries[0].transitions, 1);
+        assert_eq!(summaries[0].last.unwrap().to_string(), "00000000");
+        assert_eq!(summaries[0].min, Some(0.0));
+        assert_eq!(summaries[2].first.unwrap().to_string(), "00000001");
+        assert_eq!(summaries[2].last.unwrap().to_string(), "00000010");
+        assert_eq!(summaries[2].min, Some(1.0));
+        assert_eq!(summaries[2].max, Some(2.0));
+
+        let summaries = signal.summarize(&waves, 0, 5, 1);
+        assert!(summaries[0].first.is_none() && summaries[0].last.is_none());
+        assert_eq!(summaries[0].transitions, 0);
+        assert!(summaries[0].min.is_none());
+    }
+}
@@ -4,30 +4,20 @@
 
 use crate::fst::{parse_scope_attributes, parse_var_attributes, Attribute};
 use crate::hierarchy::*;
-use crate::{FileFormat, Waveform, WellenError};
+use crate::loader::{BodyLoader, BodyLoaderKind, SharedBytes};
+use crate::progress::{Progress, ProgressReader, PROGRESS_INTERVAL};
+use crate::signals::{SignalValue, TimeTableIdx};
+use crate::{FileFormat, LoadPhase, Waveform, WellenError};
 use fst_native::{FstVhdlDataType, FstVhdlVarType};
 use num_enum::TryFromPrimitive;
 use rayon::prelude::*;
-use std::collections::HashMap;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
 use std::fmt::{Debug, Formatter};
-use std::io::{BufRead, Seek};
+use std::io::{BufRead, Read, Seek, Write};
 
-#[derive(Debug, Copy, Clone)]
-pub struct LoadOptions {
-    /// Indicates that VCD should be parsed using multiple threads.
-    pub multi_thread: bool,
-    /// Indicates that scopes with empty names should not be part of the hierarchy.
-    pub remove_scopes_with_empty_name: bool,
-}
-
-impl Default for LoadOptions {
-    fn default() -> Self {
-        Self {
-            multi_thread: true,
-            remove_scopes_with_empty_name: false,
-        }
-    }
-}
+/// Kept here for backwards compatibility, the same options are now used by all backends.
+pub use crate::LoadOptions;
 
 pub type Result<T> = std::result::Result<T, WellenError>;
 
@@ -39,23 +29,186 @@ pub fn read_from_bytes(bytes: &[u8]) -> Result<Waveform> {
 }
 
 pub fn read_with_options(filename: &str, options: LoadOptions) -> Result<Waveform> {
-    // load file into memory (lazily)
-    let input_file = std::fs::File::open(filename).expect("failed to open input file!");
-    let mmap = unsafe { memmap2::Mmap::map(&input_file).expect("failed to memory map file") };
-    let (header_len, hierarchy, lookup) =
-        read_hierarchy(&mut std::io::Cursor::new(&mmap[..]), &options)?;
-    let wave_mem = read_values(&mmap[header_len..], &options, &hierarchy, &lookup)?;
-    Ok(Waveform::new(hierarchy, wave_mem))
+    let (hierarchy, body) = read_header(filename, options)?;
+    let body = body.load(&hierarchy)?;
+    Ok(Waveform::from_parts(hierarchy, body))
 }
 
 pub fn read_from_bytes_with_options(bytes: &[u8], options: LoadOptions) -> Result<Waveform> {
-    let (header_len, hierarchy, lookup) =
-        read_hierarchy(&mut std::io::Cursor::new(&bytes), &options)?;
-    let wave_mem = read_values(&bytes[header_len..], &options, &hierarchy, &lookup)?;
+    options
+        .progress()
+        .map_result(read_from_bytes_internal(bytes, &options))
+}
+
+fn read_from_bytes_internal(bytes: &[u8], options: &LoadOptions) -> Result<Waveform> {
+    if bytes.starts_with(GZIP_MAGIC) {
+        return read_gzip(bytes, options, STREAM_CHUNK_SIZE);
+    }
+    let (header_len, hierarchy, lookup) = read_header_from_bytes(bytes, options)?;
+    let wave_mem = read_values(&bytes[header_len..], options, &hierarchy, &lookup)?;
     Ok(Waveform::new(hierarchy, wave_mem))
 }
 
-const FST_SUP_VAR_DATA_TYPE_BITS: u32 = 10;
+/// Only parses the hierarchy. The body can be parsed later, e.g., on a different thread.
+pub fn read_header(filename: &str, options: LoadOptions) -> Result<(Hierarchy, BodyLoader)> {
+    let bytes = SharedBytes::map_file(filename)?;
+    let progress = options.progress();
+    let (hierarchy, body) = progress.map_result(read_header_internal(bytes, options.clone()))?;
+    Ok((hierarchy, BodyLoader::new(BodyLoaderKind::Vcd(body))))
+}
+
+fn read_header_internal(bytes: SharedBytes, options: LoadOptions) -> Result<(Hierarchy, VcdBody)> {
+    if bytes.as_ref().starts_with(GZIP_MAGIC) {
+        let (hierarchy, lookup, input) = read_gzip_header(bytes, &options)?;
+        let input = VcdBodyInput::Gzip(Box::new(input));
+        return Ok((hierarchy, VcdBody::new(input, options, lookup)));
+    }
+    let (header_len, hierarchy, lookup) = read_header_from_bytes(bytes.as_ref(), &options)?;
+    let input = VcdBodyInput::Bytes(bytes, header_len);
+    Ok((hierarchy, VcdBody::new(input, options, lookup)))
+}
+
+/// The part of a VCD that still needs to be parsed after the hierarchy was read.
+pub(crate) struct VcdBody {
+    input: VcdBodyInput,
+    options: LoadOptions,
+    lookup: IdLookup,
+}
+
+enum VcdBodyInput {
+    /// uncompressed file and the length of its header
+    Bytes(SharedBytes, usize),
+    Gzip(Box<GzipInput<SharedBytes>>),
+}
+
+impl VcdBody {
+    fn new(input: VcdBodyInput, options: LoadOptions, lookup: IdLookup) -> Self {
+        Self {
+            input,
+            options,
+            lookup,
+        }
+    }
+
+    pub(crate) fn load(
+        self,
+        hierarchy: &Hierarchy,
+    ) -> Result<Box<dyn crate::signals::SignalSource + Send + Sync>> {
+        let Self {
+            input,
+            options,
+            lookup,
+        } = self;
+        let progress = options.progress();
+        let wave_mem = match input {
+            VcdBodyInput::Bytes(bytes, header_len) => progress.map_result(read_values(
+                &bytes.as_ref()[header_len..],
+                &options,
+                hierarchy,
+                &lookup,
+            ))?,
+            VcdBodyInput::Gzip(mut input) => progress.map_result(read_gzip_body(
+                &mut input,
+                &options,
+                hierarchy,
+                &lookup,
+                STREAM_CHUNK_SIZE,
+            ))?,
+        };
+        Ok(wave_mem)
+    }
+}
+
+/// Loads everything that was written to a VCD so far. New value changes can be
+/// loaded later through the returned [`TailReader`], e.g., while the simulator is still running.
+pub fn read_tail(filename: &str, options: LoadOptions) -> Result<(Waveform, TailReader)> {
+    let progress = options.progress();
+    progress.map_result(read_tail_internal(filename, options.clone()))
+}
+
+fn read_tail_internal(filename: &str, options: LoadOptions) -> Result<(Waveform, TailReader)> {
+    let bytes = std::fs::read(filename)?;
+    let (header_len, hierarchy, lookup) = read_header_from_bytes(&bytes, &options)?;
+    let body = &bytes[header_len..];
+    let complete = last_time_step_start(body).unwrap_or(0);
+    let wave_mem = read_values(&body[..complete], &options, &hierarchy, &lookup)?;
+    let mask = options.signal_mask(&hierarchy);
+    let tail = TailReader {
+        filename: filename.to_string(),
+        options,
+        lookup,
+        mask,
+        offset: (header_len + complete) as u64,
+        is_start_of_body: complete == 0,
+    };
+    Ok((Waveform::new(hierarchy, wave_mem), tail))
+}
+
+/// Parses value changes that were appended to a VCD file since the last poll.
+/// The values of a time step are only parsed once the next time step starts, since
+/// the simulator might not have written all of them yet.
+pub struct TailReader {
+    filename: String,
+    options: LoadOptions,
+    lookup: IdLookup,
+    mask: Option<Vec<bool>>,
+    /// position right after the last complete time step
+    offset: u64,
+    is_start_of_body: bool,
+}
+
+impl TailReader {
+    /// Appends all new value changes to `waveform` which needs to be the waveform that was
+    /// returned by [`read_tail`]. Signals that are already loaded are updated as well.
+    /// Returns `false` if there were no new complete time steps.
+    ///
+    /// Only the new changes are parsed and appended to the loaded signals.
+    /// Returns [`WellenError::NotChronological`] if the new changes start before the end
+    /// of the waveform, e.g., because the simulator was restarted.
+    pub fn poll(&mut self, waveform: &mut Waveform) -> Result<bool> {
+        let mut input = std::fs::File::open(&self.filename)?;
+        input.seek(std::io::SeekFrom::Start(self.offset))?;
+        let mut buf = Vec::new();
+        input.read_to_end(&mut buf)?;
+        let complete = match last_time_step_start(&buf) {
+            Some(pos) => pos,
+            None => return Ok(false),
+        };
+        let ids = IdMap::new(&self.lookup, self.mask.as_deref());
+        let encoder = encode_values(
+            &buf[..complete],
+            self.is_start_of_body,
+            &self.options,
+            Progress::default(),
+            waveform.hierarchy(),
+            ids,
+        )?;
+        waveform.append_values(encoder.finish())?;
+        self.offset += complete as u64;
+        self.is_start_of_body = false;
+        Ok(true)
+    }
+}
+
+/// Returns the position of the last time step, all values before it are complete.
+fn last_time_step_start(body: &[u8]) -> Option<usize> {
+    body.windows(2)
+        .rposition(|w| w == b"\n#")
+        .map(|pos| pos + 1)
+}
+
+fn read_header_from_bytes(
+    bytes: &[u8],
+    options: &LoadOptions,
+) -> Result<(usize, Hierarchy, IdLookup)> {
+    options
+        .progress()
+        .start_phase(LoadPhase::Header, bytes.len() as u64)?;
+    let mut input = ProgressReader::new(std::io::Cursor::new(bytes), options.progress.clone());
+    read_hierarchy(&mut input, options)
+}
+
+pub(crate) const FST_SUP_VAR_DATA_TYPE_BITS: u32 = 10;
 const FST_SUP_VAR_DATA_TYPE_MASK: u64 = (1 << FST_SUP_VAR_DATA_TYPE_BITS) - 1;
 
 // VCD attributes are a GTKWave extension which is also used by nvc
@@ -71,7 +224,7 @@ fn parse_attribute(
                 return Err(unexpected_n_tokens("attribute", &tokens));
             }
             let type_name = std::str::from_utf8(tokens[2])?.to_string();
-            let arg = u64::from_str_radix(std::str::from_utf8(tokens[3])?, 10)?;
+            let arg = std::str::from_utf8(tokens[3])?.parse::<u64>()?;
             let var_type =
                 FstVhdlVarType::try_from_primitive((arg >> FST_SUP_VAR_DATA_TYPE_BITS) as u8)?;
             let data_type =
@@ -86,7 +239,7 @@ fn parse_attribute(
                 return Err(unexpected_n_tokens("attribute", &tokens));
             }
             let path = std::str::from_utf8(tokens[2])?.to_string();
-            let id = u64::from_str_radix(std::str::from_utf8(tokens[3])?, 10)?;
+            let id = std::str::from_utf8(tokens[3])?.parse::<u64>()?;
             let string_ref = h.add_string(path);
             path_names.insert(id, string_ref);
             Ok(None)
@@ -98,8 +251,8 @@ fn parse_attribute(
                 //       instance of the normal source path
                 return Err(unexpected_n_tokens("attribute", &tokens));
             }
-            let path_id = u64::from_str_radix(std::str::from_utf8(tokens[2])?, 10)?;
-            let line = u64::from_str_radix(std::str::from_utf8(tokens[3])?, 10)?;
+            let path_id = std::str::from_utf8(tokens[2])?.parse::<u64>()?;
+            let line = std::str::from_utf8(tokens[3])?.parse::<u64>()?;
             let is_instance = false;
             Ok(Some(Attribute::SourceLoc(
                 path_names[&path_id],
@@ -115,11 +268,144 @@ fn parse_attribute(
 
 type IdLookup = Option<HashMap<Vec<u8>, SignalRef>>;
 
+/// Gzip compressed files start with these bytes.
+const GZIP_MAGIC: &[u8; 2] = &[0x1f, 0x8b];
+/// The body of a compressed or streamed VCD is parsed in chunks of roughly this size.
+const STREAM_CHUNK_SIZE: usize = 64 * 1024 * 1024;
+
+/// Decompressed VCD that is positioned right after the header.
+type GzipInput<T> =
+    std::io::BufReader<flate2::bufread::MultiGzDecoder<ProgressReader<std::io::Cursor<T>>>>;
+
+fn read_gzip(bytes: &[u8], options: &LoadOptions, chunk_size: usize) -> Result<Waveform> {
+    let (hierarchy, lookup, mut input) = read_gzip_header(bytes, options)?;
+    let wave_mem = read_gzip_body(&mut input, options, &hierarchy, &lookup, chunk_size)?;
+    Ok(Waveform::new(hierarchy, wave_mem))
+}
+
+/// Progress is measured in compressed bytes.
+fn read_gzip_header<T: AsRef<[u8]>>(
+    bytes: T,
+    options: &LoadOptions,
+) -> Result<(Hierarchy, IdLookup, GzipInput<T>)> {
+    let len = bytes.as_ref().len() as u64;
+    options.progress().start_phase(LoadPhase::Header, len)?;
+    let compressed = ProgressReader::new(std::io::Cursor::new(bytes), options.progress.clone());
+    let mut input = std::io::BufReader::new(flate2::bufread::MultiGzDecoder::new(compressed));
+    let (hierarchy, lookup) = read_hierarchy_from_stream(&mut input, options)?;
+    Ok((hierarchy, lookup, input))
+}
+
+/// Decompresses the VCD chunk by chunk, so that the whole decompressed file never needs to be
+/// kept in memory.
+fn read_gzip_body<T: AsRef<[u8]>>(
+    input: &mut GzipInput<T>,
+    options: &LoadOptions,
+    hierarchy: &Hierarchy,
+    lookup: &IdLookup,
+    chunk_size: usize,
+) -> Result<Box<crate::wavemem::Reader>> {
+    let compressed = input.get_ref().get_ref().get_ref();
+    let remaining = compressed.get_ref().as_ref().len() as u64 - compressed.position();
+    options.progress().start_phase(LoadPhase::Body, remaining)?;
+    read_values_in_chunks(input, options, hierarchy, lookup, chunk_size)
+}
+
+/// Parses a VCD from an input that does not support seeking, like stdin or a pipe.
+/// The body is read and parsed in chunks, thus the whole file never needs to be in memory.
+/// Gzip compressed input is decompressed on the fly.
+pub fn read_from_stream(input: impl Read, options: LoadOptions) -> Result<Waveform> {
+    options
+        .progress()
+        .map_result(read_stream(input, &options, STREAM_CHUNK_SIZE))
+}
+
+/// Progress is measured in bytes read from the (compressed) input. The total is unknown.
+fn read_stream(input: impl Read, options: &LoadOptions, chunk_size: usize) -> Result<Waveform> {
+    options.progress().start_phase(LoadPhase::Header, 0)?;
+    let input = ProgressReader::new(input, options.progress.clone());
+    let mut input = std::io::BufReader::new(input);
+    if input.fill_buf()?.starts_with(GZIP_MAGIC) {
+        let input = std::io::BufReader::new(flate2::bufread::MultiGzDecoder::new(input));
+        read_stream_values(input, options, chunk_size)
+    } else {
+        read_stream_values(input, options, chunk_size)
+    }
+}
+
+fn read_stream_values(
+    mut input: impl BufRead,
+    options: &LoadOptions,
+    chunk_size: usize,
+) -> Result<Waveform> {
+    let (hierarchy, lookup) = read_hierarchy_from_stream(&mut input, options)?;
+    options.progress().start_phase(LoadPhase::Body, 0)?;
+    let wave_mem = read_values_in_chunks(&mut input, options, &hierarchy, &lookup, chunk_size)?;
+    Ok(Waveform::new(hierarchy, wave_mem))
+}
+
+/// Reads the body chunk by chunk, every chunk is parsed with multiple threads.
+/// Progress needs to be tracked by the reader of the input.
+fn read_values_in_chunks(
+    input: &mut impl BufRead,
+    options: &LoadOptions,
+    hierarchy: &Hierarchy,
+    lookup: &IdLookup,
+    chunk_size: usize,
+) -> Result<Box<crate::wavemem::Reader>> {
+    let progress = options.progress();
+    let mask = options.signal_mask(hierarchy);
+    let ids = IdMap::new(lookup, mask.as_deref());
+    let mut encoder = crate::wavemem::Encoder::with_signal_mask(hierarchy, ids.mask);
+    let mut buf: Vec<u8> = Vec::with_capacity(chunk_size);
+    let mut is_first = true;
+    loop {
+        let read_len = (&mut *input)
+            .take(chunk_size as u64)
+            .read_to_end(&mut buf)?;
+        let done = read_len < chunk_size;
+        // chunks are split right before a time step
+        let chunk_end = if done {
+            buf.len()
+        } else {
+            match last_time_step_start(&buf) {
+                Some(pos) => pos,
+                None => continue, // no time step in the buffer, we need to read more
+            }
+        };
+        let chunk_encoder = encode_values(
+            &buf[..chunk_end],
+            is_first,
+            options,
+            Progress::default(),
+            hierarchy,
+            ids,
+        )?;
+        encoder.append(chunk_encoder);
+        progress.check_cancelled()?;
+        buf.drain(..chunk_end);
+        is_first = false;
+        if done {
+            break;
+        }
+    }
+    Ok(Box::new(encoder.finish()))
+}
+
 fn read_hierarchy(
     input: &mut (impl BufRead + Seek),
     options: &LoadOptions,
 ) -> Result<(usize, Hierarchy, IdLookup)> {
-    let start = input.stream_position().unwrap();
+    let start = input.stream_position()?;
+    let (hierarchy, lookup) = read_hierarchy_from_stream(input, options)?;
+    let end = input.stream_position()?;
+    Ok(((end - start) as usize, hierarchy, lookup))
+}
+
+fn read_hierarchy_from_stream(
+    input: &mut impl BufRead,
+    options: &LoadOptions,
+) -> Result<(Hierarchy, IdLookup)> {
     let mut h = HierarchyBuilder::new(FileFormat::Vcd);
     let mut attributes = Vec::new();
     let mut path_names = HashMap::new();
@@ -128,12 +414,12 @@ fn read_hierarchy(
     let mut use_id_map = false;
     let mut var_count = 0u64;
 
-    let mut id_to_signal_ref = |id: &[u8], var_count: u64| -> SignalRef {
+    let mut id_to_signal_ref = |id: &[u8], var_count: u64| -> Result<SignalRef> {
         // currently we only make a decision of whether to switch to a hash_map based lookup when we are at the first variable
         if var_count == 0 {
             if let Some(id_value) = id_to_int(id) {
                 if id_value < 1024 * 1024 {
-                    return SignalRef::from_index(id_value as usize).unwrap();
+                    return Ok(SignalRef::from_index(id_value as usize).unwrap());
                 } else {
                     use_id_map = true;
                 }
@@ -144,15 +430,17 @@ fn read_hierarchy(
 
         if use_id_map {
             match id_map.get(id) {
-                Some(signal_ref) => *signal_ref,
+                Some(signal_ref) => Ok(*signal_ref),
                 None => {
                     let signal_ref = SignalRef::from_index(id_map.len() + 1).unwrap();
                     id_map.insert(id.to_vec(), signal_ref);
-                    signal_ref
+                    Ok(signal_ref)
                 }
             }
         } else {
-            SignalRef::from_index(id_to_int(id).unwrap() as usize).unwrap()
+            id_to_int(id)
+                .and_then(|value| SignalRef::from_index(value as usize))
+                .ok_or_else(|| unknown_id(id))
         }
     };
 
@@ -177,6 +465,7 @@ fn read_hierarchy(
             Ok(())
         }
         HeaderCmd::ScalarVar(tpe, size, id, name) => {
+            let length = parse_var_length(size, name)?;
             // sometimes the index is not separated from the name (which is incorrect ... but alas!)
             let (var_name, index) = extract_index_from_name(name);
             let (type_name, var_type, enum_type) =
@@ -187,9 +476,9 @@ fn read_hierarchy(
                 name,
                 var_type,
                 VarDirection::vcd_default(),
-                u32::from_str_radix(std::str::from_utf8(size).unwrap(), 10).unwrap(),
+                length,
                 index,
-                id_to_signal_ref(id, var_count),
+                id_to_signal_ref(id, var_count)?,
                 enum_type,
                 type_name,
             );
@@ -197,18 +486,9 @@ fn read_hierarchy(
             Ok(())
         }
         HeaderCmd::VectorVar(tpe, size, id, name, index) => {
-            let length = match u32::from_str_radix(std::str::from_utf8(size).unwrap(), 10) {
-                Ok(len) => len,
-                Err(_) => {
-                    return Err(WellenError::VcdVarLengthParsing(
-                        String::from_utf8_lossy(size).to_string(),
-                        String::from_utf8_lossy(name).to_string(),
-                    ));
-                }
-            };
-
+            let length = parse_var_length(size, name)?;
             let index = parse_index(index);
-            let var_name = std::str::from_utf8(name).unwrap().to_string();
+            let var_name = std::str::from_utf8(name)?.to_string();
             let (type_name, var_type, enum_type) =
                 parse_var_attributes(&mut attributes, convert_var_tpe(tpe)?, &var_name)?;
             let name = h.add_string(var_name);
@@ -219,7 +499,7 @@ fn read_hierarchy(
                 VarDirection::vcd_default(),
                 length,
                 index,
-                id_to_signal_ref(id, var_count),
+                id_to_signal_ref(id, var_count)?,
                 enum_type,
                 type_name,
             );
@@ -239,7 +519,7 @@ fn read_hierarchy(
             Ok(())
         }
         HeaderCmd::Timescale(factor, unit) => {
-            let factor_int = u32::from_str_radix(std::str::from_utf8(factor).unwrap(), 10).unwrap();
+            let factor_int = std::str::from_utf8(factor)?.parse::<u32>()?;
             let value = Timescale::new(factor_int, convert_timescale_unit(unit));
             h.set_timescale(value);
             Ok(())
@@ -252,11 +532,27 @@ fn read_hierarchy(
         }
     };
 
-    read_header(input, foo).unwrap();
-    let end = input.stream_position().unwrap();
+    read_header_commands(input, foo).map_err(|e| match e {
+        WellenError::Io(io) if io.kind() == std::io::ErrorKind::UnexpectedEof => {
+            WellenError::TruncatedFile(FileFormat::Vcd)
+        }
+        other => other,
+    })?;
     let hierarchy = h.finish();
     let lookup = if use_id_map { Some(id_map) } else { None };
-    Ok(((end - start) as usize, hierarchy, lookup))
+    Ok((hierarchy, lookup))
+}
+
+fn parse_var_length(size: &[u8], name: &[u8]) -> Result<u32> {
+    std::str::from_utf8(size)
+        .ok()
+        .and_then(|s| s.parse::<u32>().ok())
+        .ok_or_else(|| {
+            WellenError::VcdVarLengthParsing(
+                String::from_utf8_lossy(size).to_string(),
+                String::from_utf8_lossy(name).to_string(),
+            )
+        })
 }
 
 /// Tries to see if the name contains an index that is not white-space separated.
@@ -266,12 +562,12 @@ pub(crate) fn extract_index_from_name(name: &[u8]) -> (String, Option<VarIndex>)
     if let Some(pos) = bracket_pos {
         if let Some(index) = parse_index(&name[pos..]) {
             return (
-                std::str::from_utf8(&name[..pos]).unwrap().to_string(),
+                String::from_utf8_lossy(&name[..pos]).to_string(),
                 Some(index),
             );
         }
     }
-    return (std::str::from_utf8(name).unwrap().to_string(), None);
+    (String::from_utf8_lossy(name).to_string(), None)
 }
 
 pub(crate) fn parse_index(index: &[u8]) -> Option<VarIndex> {
@@ -285,22 +581,22 @@ pub(crate) fn parse_index(index: &[u8]) -> Option<VarIndex> {
     match sep {
         None => {
             let inner = &index[1..(index.len() - 1)];
-            let inner_str = std::str::from_utf8(inner).unwrap();
-            let bit = i32::from_str_radix(inner_str, 10).unwrap();
+            let bit = parse_i32(inner)?;
             Some(VarIndex::new(bit, bit))
         }
         Some(pos) => {
-            let msb_bytes = &index[1..pos];
-            let msb_str = std::str::from_utf8(msb_bytes).unwrap();
-            let msb = i32::from_str_radix(msb_str, 10).unwrap();
-            let lsb_bytes = &index[(pos + 1)..(index.len() - 1)];
-            let lsb_str = std::str::from_utf8(lsb_bytes).unwrap();
-            let lsb = i32::from_str_radix(lsb_str, 10).unwrap();
+            let msb = parse_i32(&index[1..pos])?;
+            let lsb = parse_i32(&index[(pos + 1)..(index.len() - 1)])?;
             Some(VarIndex::new(msb, lsb))
         }
     }
 }
 
+#[inline]
+fn parse_i32(value: &[u8]) -> Option<i32> {
+    std::str::from_utf8(value).ok()?.parse::<i32>().ok()
+}
+
 fn convert_timescale_unit(name: &[u8]) -> TimescaleUnit {
     match name {
         b"fs" => TimescaleUnit::FemtoSeconds,
@@ -374,7 +670,8 @@ fn convert_var_tpe(tpe: &[u8]) -> Result<VarType> {
         b"longint" => Ok(VarType::LongInt),
         b"byte" => Ok(VarType::Byte),
         b"enum" => Ok(VarType::Enum),
-        b"shortread" => Ok(VarType::ShortReal),
+        // older versions of this library wrote `shortread`
+        b"shortreal" | b"shortread" => Ok(VarType::ShortReal),
         _ => Err(WellenError::VcdUnknownVarType(
             String::from_utf8_lossy(tpe).to_string(),
         )),
@@ -408,13 +705,18 @@ fn id_to_int(id: &[u8]) -> Option<u64> {
     Some(result - 1)
 }
 
+#[inline]
+fn unknown_id(id: &[u8]) -> WellenError {
+    WellenError::VcdUnknownId(String::from_utf8_lossy(id).to_string())
+}
+
 #[inline]
 fn unexpected_n_tokens(cmd: &str, tokens: &[&[u8]]) -> WellenError {
     WellenError::VcdUnexpectedNumberOfTokens(cmd.to_string(), iter_bytes_to_list_str(tokens.iter()))
 }
 
 /// very hacky read header implementation, will fail on a lot of valid headers
-fn read_header(
+fn read_header_commands(
     input: &mut impl BufRead,
     mut callback: impl FnMut(HeaderCmd) -> Result<()>,
 ) -> Result<()> {
@@ -555,30 +857,34 @@ impl VcdCmd {
         }
     }
 
-    fn from_bytes_or_panic(name: &[u8]) -> Self {
-        match Self::from_bytes(name) {
-            None => {
-                panic!(
-                    "Unexpected VCD command {}. Supported commands are: {:?}",
-                    String::from_utf8_lossy(name),
-                    get_vcd_command_str()
-                );
-            }
-            Some(cmd) => cmd,
-        }
+    fn from_bytes_or_error(name: &[u8]) -> Result<Self> {
+        Self::from_bytes(name).ok_or_else(|| {
+            WellenError::VcdUnknownCommand(
+                String::from_utf8_lossy(name).to_string(),
+                get_vcd_command_str(),
+            )
+        })
     }
 }
 
 /// Tries to guess whether this input could be a VCD by looking at the first token.
+/// Gzip compressed inputs are decompressed on the fly.
 pub(crate) fn is_vcd(input: &mut (impl BufRead + Seek)) -> bool {
-    let is_vcd = matches!(internal_is_vcd(input), Ok(true));
+    let is_gzip = matches!(input.fill_buf(), Ok(start) if start.starts_with(GZIP_MAGIC));
+    let is_vcd = if is_gzip {
+        let mut decompressed =
+            std::io::BufReader::new(flate2::bufread::MultiGzDecoder::new(&mut *input));
+        matches!(internal_is_vcd(&mut decompressed), Ok(true))
+    } else {
+        matches!(internal_is_vcd(input), Ok(true))
+    };
     // try to reset input
     let _ = input.seek(std::io::SeekFrom::Start(0));
     is_vcd
 }
 
 /// Returns an error or false if not a vcd. Returns Ok(true) only if we think it is a vcd.
-fn internal_is_vcd(input: &mut (impl BufRead + Seek)) -> Result<bool> {
+fn internal_is_vcd(input: &mut impl BufRead) -> Result<bool> {
     let mut buf = Vec::with_capacity(64);
     let (_cmd, _body) = read_command(input, &mut buf)?;
     Ok(true)
@@ -603,7 +909,7 @@ fn read_command<'a>(input: &mut impl BufRead, buf: &'a mut Vec<u8>) -> Result<(V
     read_token(input, buf)?;
 
     // check to see if this is a valid command
-    let cmd = VcdCmd::from_bytes_or_panic(&buf);
+    let cmd = VcdCmd::from_bytes_or_error(buf)?;
     buf.clear();
 
     // read until we find the end token
@@ -747,14 +1053,62 @@ fn read_values(
     hierarchy: &Hierarchy,
     lookup: &IdLookup,
 ) -> Result<Box<crate::wavemem::Reader>> {
+    options
+        .progress()
+        .start_phase(LoadPhase::Body, input.len() as u64)?;
+    let mask = options.signal_mask(hierarchy);
+    let ids = IdMap::new(lookup, mask.as_deref());
+    let encoder = encode_values(input, true, options, options.progress(), hierarchy, ids)?;
+    Ok(Box::new(encoder.finish()))
+}
+
+/// Translates VCD identifiers into signal indices.
+#[derive(Clone, Copy)]
+struct IdMap<'a> {
+    lookup: &'a IdLookup,
+    /// Only signals that are marked in the mask need to be parsed.
+    mask: Option<&'a [bool]>,
+}
+
+impl<'a> IdMap<'a> {
+    fn new(lookup: &'a IdLookup, mask: Option<&'a [bool]>) -> Self {
+        Self { lookup, mask }
+    }
+
+    /// Returns `None` for signals that were filtered out.
+    #[inline]
+    fn get(&self, id: &[u8]) -> Result<Option<u64>> {
+        let num_id = match self.lookup {
+            None => id_to_int(id),
+            Some(lookup) => lookup.get(id).map(|r| r.index() as u64),
+        }
+        .ok_or_else(|| unknown_id(id))?;
+        // unknown ids are never skipped, in order for the encoder to report them
+        let skip = self
+            .mask
+            .is_some_and(|m| m.get(num_id as usize) == Some(&false));
+        Ok((!skip).then_some(num_id))
+    }
+}
+
+/// Parses (a part of) the body of a VCD. Only the start of the body may contain
+/// values that are not preceded by a time step.
+fn encode_values(
+    input: &[u8],
+    is_start_of_body: bool,
+    options: &LoadOptions,
+    progress: Progress,
+    hierarchy: &Hierarchy,
+    ids: IdMap,
+) -> Result<crate::wavemem::Encoder> {
     if options.multi_thread {
         let chunks = determine_thread_chunks(input.len());
         let encoders: Vec<crate::wavemem::Encoder> = chunks
             .par_iter()
             .map(|(start, len)| {
-                let is_first = *start == 0;
+                let is_first = is_start_of_body && *start == 0;
                 // check to see if the chunk start on a new line
-                let starts_on_new_line = if is_first {
+                let starts_on_new_line = if *start == 0 {
                     true
                 } else {
                     let before = input[*start - 1];
@@ -766,23 +1120,36 @@ fn read_values(
                     *len - 1,
                     is_first,
                     starts_on_new_line,
+                    progress,
                     hierarchy,
-                    lookup,
+                    ids,
                 )
             })
-            .collect();
+            .collect::<Result<_>>()?;
 
         // combine encoders
+        progress.start_phase(LoadPhase::Merge, encoders.len() as u64)?;
         let mut encoder_iter = encoders.into_iter();
-        let mut encoder = encoder_iter.next().unwrap();
+        let mut encoder = match encoder_iter.next() {
+            Some(encoder) => encoder,
+            None => crate::wavemem::Encoder::with_signal_mask(hierarchy, ids.mask), // empty body
+        };
+        progress.add_done(1);
         for other in encoder_iter {
             encoder.append(other);
+            progress.add_done(1);
         }
-        Ok(Box::new(encoder.finish()))
+        Ok(encoder)
     } else {
-        let encoder =
-            read_single_stream_of_values(input, input.len() - 1, true, true, hierarchy, lookup);
-        Ok(Box::new(encoder.finish()))
+        read_single_stream_of_values(
+            input,
+            input.len().saturating_sub(1),
+            is_start_of_body,
+            true,
+            progress,
+            hierarchy,
+            ids,
+        )
     }
 }
 
@@ -791,10 +1158,14 @@ fn read_single_stream_of_values<'a>(
     stop_pos: usize,
     is_first: bool,
     starts_on_new_line: bool,
+    progress: Progress,
     hierarchy: &Hierarchy,
-    lookup: &IdLookup,
-) -> crate::wavemem::Encoder {
-    let mut encoder = crate::wavemem::Encoder::new(hierarchy);
+    ids: IdMap,
+) -> Result<crate::wavemem::Encoder> {
+    let mut encoder = crate::wavemem::Encoder::with_signal_mask(hierarchy, ids.mask);
+    // we are responsible for reporting the progress up to the stop position
+    let progress_end = std::cmp::min(stop_pos + 1, input.len());
+    let mut progress_reported = 0;
 
     let (input2, offset) = if starts_on_new_line {
         (input, 0)
@@ -805,7 +1176,8 @@ fn read_single_stream_of_values<'a>(
     // We only start recording once we have encountered out first time step
     let mut found_first_time_step = false;
     loop {
-        if let Some((pos, cmd)) = reader.next() {
+        if let Some(next) = reader.next() {
+            let (pos, cmd) = next?;
             if (pos + offset) > stop_pos {
                 if let BodyCmd::Time(_to) = cmd {
                     break; // stop before the next time value when we go beyond the stop position
@@ -813,9 +1185,14 @@ fn read_single_stream_of_values<'a>(
             }
             match cmd {
                 BodyCmd::Time(value) => {
+                    let progress_pos = std::cmp::min(pos + offset, progress_end);
+                    if progress_pos - progress_reported >= PROGRESS_INTERVAL as usize {
+                        progress.add_done((progress_pos - progress_reported) as u64);
+                        progress_reported = progress_pos;
+                        progress.check_cancelled()?;
+                    }
                     found_first_time_step = true;
-                    let int_value =
-                        u64::from_str_radix(std::str::from_utf8(value).unwrap(), 10).unwrap();
+                    let int_value = std::str::from_utf8(value)?.parse::<u64>()?;
                     encoder.time_change(int_value);
                 }
                 BodyCmd::Value(value, id) => {
@@ -826,11 +1203,9 @@ fn read_single_stream_of_values<'a>(
                         found_first_time_step = true;
                     }
                     if found_first_time_step {
-                        let num_id = match lookup {
-                            None => id_to_int(id).unwrap(),
-                            Some(lookup) => lookup[id].index() as u64,
-                        };
-                        encoder.vcd_value_change(num_id, value);
+                        if let Some(num_id) = ids.get(id)? {
+                            encoder.vcd_value_change(num_id, value)?;
+                        }
                     }
                 }
             };
@@ -838,8 +1213,9 @@ fn read_single_stream_of_values<'a>(
             break; // done, no more values to read
         }
     }
+    progress.add_done((progress_end - progress_reported) as u64);
 
-    encoder
+    Ok(encoder)
 }
 
 #[inline]
@@ -881,25 +1257,25 @@ impl<'a> BodyReader<'a> {
         token_start: &mut Option<usize>,
         prev_token: &mut Option<&'a [u8]>,
         search_for_end: &mut bool,
-    ) -> Option<BodyCmd<'a>> {
+    ) -> Result<Option<BodyCmd<'a>>> {
         match *token_start {
-            None => None,
+            None => Ok(None),
             Some(start) => {
                 let token = &self.input[start..pos];
                 if token.is_empty() {
-                    return None;
+                    return Ok(None);
                 }
                 if *search_for_end {
                     *search_for_end = token != b"$end";
                     // consume token and return
                     *token_start = None;
-                    return None;
+                    return Ok(None);
                 }
                 let ret = match *prev_token {
                     None => {
                         if token.len() == 1 {
                             // too short
-                            return None;
+                            return Ok(None);
                         }
                         // 1-token commands are binary changes or time commands
                         match token[0] {
@@ -911,7 +1287,7 @@ impl<'a> BodyReader<'a> {
                             _ => {
                                 if token == b"$dumpall" {
                                     // interpret dumpall as indicating timestep zero
-                                    return Some(BodyCmd::Time(ASCII_ZERO));
+                                    return Ok(Some(BodyCmd::Time(ASCII_ZERO)));
                                 }
                                 if token == b"$comment" {
                                     // drop token, but start searching for $end in order to skip the comment
@@ -933,12 +1309,11 @@ impl<'a> BodyReader<'a> {
                                 BodyCmd::Value(&first[0..], token)
                             }
                             _ => {
-                                panic!(
-                                    "Unexpected tokens: `{}` and `{}` ({} lines after header)",
-                                    String::from_utf8_lossy(first),
-                                    String::from_utf8_lossy(token),
-                                    self.lines_read
-                                );
+                                return Err(WellenError::VcdUnexpectedBodyTokens(
+                                    String::from_utf8_lossy(first).to_string(),
+                                    String::from_utf8_lossy(token).to_string(),
+                                    self.lines_read,
+                                ));
                             }
                         };
                         *prev_token = None;
@@ -946,18 +1321,18 @@ impl<'a> BodyReader<'a> {
                     }
                 };
                 *token_start = None;
-                ret
+                Ok(ret)
             }
         }
     }
 }
 
 impl<'a> Iterator for BodyReader<'a> {
-    type Item = (usize, BodyCmd<'a>);
+    type Item = Result<(usize, BodyCmd<'a>)>;
 
     /// returns the starting position and the body of the command
     #[inline]
-    fn next(&mut self) -> Option<(usize, BodyCmd<'a>)> {
+    fn next(&mut self) -> Option<Self::Item> {
         if self.pos >= self.input.len() {
             return None; // done!
         }
@@ -982,19 +1357,24 @@ impl<'a> Iterator for BodyReader<'a> {
                             &mut prev_token,
                             &mut search_for_end,
                         ) {
-                            None => {
+                            Err(e) => {
+                                // stop parsing after the first error
+                                self.pos = self.input.len();
+                                return Some(Err(e));
+                            }
+                            Ok(None) => {
                                 if *b == b'\n' {
                                     pending_lines += 1;
                                 }
                             }
-                            Some(cmd) => {
+                            Ok(Some(cmd)) => {
                                 // save state
                                 self.pos = pos;
                                 self.lines_read += pending_lines;
                                 if *b == b'\n' {
                                     self.lines_read += 1;
                                 }
-                                return Some((start_pos, cmd));
+                                return Some(Ok((start_pos, cmd)));
                             }
                         }
                     }
@@ -1014,19 +1394,14 @@ impl<'a> Iterator for BodyReader<'a> {
         // update final position
         self.pos = self.input.len();
         // check to see if there is a final token at the end
-        match self.try_finish_token(
+        self.try_finish_token(
             self.pos,
             &mut token_start,
             &mut prev_token,
             &mut search_for_end,
-        ) {
-            None => {}
-            Some(cmd) => {
-                return Some((start_pos, cmd));
-            }
-        }
-        // now we are done
-        None
+        )
+        .transpose()
+        .map(|res| res.map(|cmd| (start_pos, cmd)))
     }
 }
 
@@ -1053,6 +1428,295 @@ impl<'a> Debug for BodyCmd<'a> {
     }
 }
 
+/// Options for the VCD writer.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct WriteOptions {
+    /// Indicates that variables whose signal has not been loaded should be left out of the
+    /// output. By default, we return an error if a signal is missing.
+    pub skip_unloaded_signals: bool,
+}
+
+/// Serializes the hierarchy and all loaded signals of a waveform to VCD.
+/// Signals need to be loaded with `Waveform::load_signals` before calling this function.
+pub fn write(waveform: &Waveform, output: impl Write, options: WriteOptions) -> Result<()> {
+    let hierarchy = waveform.hierarchy();
+    let mut out = std::io::BufWriter::new(output);
+
+    // assign an identifier to every signal, vars that point to the same signal share an id
+    let mut ids: HashMap<SignalRef, Vec<u8>> = HashMap::new();
+    let mut signals = Vec::new();
+    for var in hierarchy.iter_vars() {
+        let signal_ref = var.signal_ref();
+        if ids.contains_key(&signal_ref) {
+            continue;
+        }
+        match waveform.get_signal(signal_ref) {
+            Some(signal) => {
+                let id = int_to_id(ids.len() as u64);
+                signals.push((id.clone(), signal));
+                ids.insert(signal_ref, id);
+            }
+            None if options.skip_unloaded_signals => {}
+            None => return Err(WellenError::SignalNotLoaded(var.full_name(hierarchy))),
+        }
+    }
+
+    // header
+    if !hierarchy.date().is_empty() {
+        writeln!(out, "$date\n\t{}\n$end", hierarchy.date())?;
+    }
+    if !hierarchy.version().is_empty() {
+        writeln!(out, "$version\n\t{}\n$end", hierarchy.version())?;
+    }
+    if let Some(timescale) = hierarchy.timescale() {
+        writeln!(
+            out,
+            "$timescale {}{} $end",
+            timescale.factor,
+            timescale_unit_to_str(timescale.unit)?
+        )?;
+    }
+    for item in hierarchy.items() {
+        write_hierarchy_item(&mut out, hierarchy, item, &ids)?;
+    }
+    writeln!(out, "$enddefinitions $end")?;
+
+    // merge value changes of all signals in time order
+    let time_table = waveform.time_table();
+    let mut changes: Vec<_> = signals
+        .iter()
+        .map(|(id, signal)| (id, signal.iter_changes().peekable()))
+        .collect();
+    let mut queue: BinaryHeap<Reverse<(TimeTableIdx, usize)>> = changes
+        .iter_mut()
+        .enumerate()
+        .flat_map(|(pos, (_, iter))| iter.peek().map(|(idx, _)| Reverse((*idx, pos))))
+        .collect();
+    let mut current_time_idx = None;
+    while let Some(Reverse((time_idx, pos))) = queue.pop() {
+        if current_time_idx != Some(time_idx) {
+            writeln!(out, "#{}", time_table[time_idx as usize])?;
+            current_time_idx = Some(time_idx);
+        }
+        let (id, iter) = &mut changes[pos];
+        while let Some((_, value)) = iter.next_if(|(idx, _)| *idx == time_idx) {
+            write_value_change(&mut out, value, id)?;
+        }
+        if let Some((next_idx, _)) = iter.peek() {
+            queue.push(Reverse((*next_idx, pos)));
+        }
+    }
+    out.flush()?;
+    Ok(())
+}
+
+fn write_hierarchy_item(
+    out: &mut impl Write,
+    hierarchy: &Hierarchy,
+    item: HierarchyItem,
+    ids: &HashMap<SignalRef, Vec<u8>>,
+) -> Result<()> {
+    match item {
+        HierarchyItem::Scope(scope) => {
+            writeln!(
+                out,
+                "$scope {} {} $end",
+                scope_tpe_to_str(scope.scope_type()),
+                scope.name(hierarchy)
+            )?;
+            for child in scope.items(hierarchy) {
+                write_hierarchy_item(out, hierarchy, child, ids)?;
+            }
+            writeln!(out, "$upscope $end")?;
+        }
+        HierarchyItem::Var(var) => {
+            let id = match ids.get(&var.signal_ref()) {
+                Some(id) => String::from_utf8_lossy(id),
+                None => return Ok(()), // skipped since the signal was not loaded
+            };
+            // VHDL type information is encoded as an attribute, the same way nvc does it
+            let vhdl_data_type = var_tpe_to_vhdl_data_type(var.var_type());
+            if var.vhdl_type_name(hierarchy).is_some() || vhdl_data_type.is_some() {
+                let type_name = var
+                    .vhdl_type_name(hierarchy)
+                    .unwrap_or_else(|| default_vhdl_type_name(var.var_type()));
+                let data_type = vhdl_data_type.unwrap_or(FstVhdlDataType::None);
+                let arg = ((FstVhdlVarType::Signal as u64) << FST_SUP_VAR_DATA_TYPE_BITS)
+                    | (data_type as u64);
+                writeln!(out, "$attrbegin misc 02 {type_name} {arg} $end")?;
+            }
+            let (tpe, length) = if var.var_type() == VarType::ShortReal {
+                ("shortreal", 32)
+            } else if var.is_real() {
+                ("real", 64)
+            } else if var.is_string() {
+                ("string", 1)
+            } else {
+                (var_tpe_to_str(var.var_type()), var.length().unwrap())
+            };
+            write!(out, "$var {tpe} {length} {id} {}", var.name(hierarchy))?;
+            if let Some(index) = var.index() {
+                if index.msb() == index.lsb() {
+                    write!(out, " [{}]", index.lsb())?;
+                } else {
+                    write!(out, " [{}:{}]", index.msb(), index.lsb())?;
+                }
+            }
+            writeln!(out, " $end")?;
+        }
+    }
+    Ok(())
+}
+
+fn write_value_change(out: &mut impl Write, value: SignalValue, id: &[u8]) -> Result<()> {
+    match value {
+        SignalValue::String(value) => {
+            // VCD values are separated by whitespace
+            if value.is_empty() || value.contains(char::is_whitespace) {
+                return Err(WellenError::UnsupportedFeature(
+                    FileFormat::Vcd,
+                    format!("string value {value:?} that is empty or contains whitespace"),
+                ));
+            }
+            write!(out, "s{value} ")?;
+        }
+        SignalValue::Real(value) => write!(out, "r{value} ")?,
+        other => {
+            let bits = other.to_bit_string().unwrap();
+            if bits.len() == 1 {
+                write!(out, "{bits}")?;
+            } else {
+                write!(out, "b{bits} ")?;
+            }
+        }
+    }
+    out.write_all(id)?;
+    writeln!(out)?;
+    Ok(())
+}
+
+/// Inverse of `id_to_int`.
+fn int_to_id(mut value: u64) -> Vec<u8> {
+    let mut out = Vec::with_capacity(4);
+    value += 1;
+    while value > 0 {
+        let digit = (value - 1) % NUM_ID_CHARS;
+        out.push(ID_CHAR_MIN + digit as u8);
+        value = (value - 1) / NUM_ID_CHARS;
+    }
+    out
+}
+
+fn timescale_unit_to_str(unit: TimescaleUnit) -> Result<&'static str> {
+    match unit {
+        TimescaleUnit::FemtoSeconds => Ok("fs"),
+        TimescaleUnit::PicoSeconds => Ok("ps"),
+        TimescaleUnit::NanoSeconds => Ok("ns"),
+        TimescaleUnit::MicroSeconds => Ok("us"),
+        TimescaleUnit::MilliSeconds => Ok("ms"),
+        TimescaleUnit::Seconds => Ok("s"),
+        TimescaleUnit::Unknown => Err(WellenError::UnsupportedFeature(
+            FileFormat::Vcd,
+            "timescale without a unit".to_string(),
+        )),
+    }
+}
+
+fn scope_tpe_to_str(tpe: ScopeType) -> &'static str {
+    match tpe {
+        ScopeType::Module => "module",
+        ScopeType::Task => "task",
+        ScopeType::Function => "function",
+        ScopeType::Begin => "begin",
+        ScopeType::Fork => "fork",
+        ScopeType::Generate => "generate",
+        ScopeType::Struct => "struct",
+        ScopeType::Union => "union",
+        ScopeType::Class => "class",
+        ScopeType::Interface => "interface",
+        ScopeType::Package => "package",
+        ScopeType::Program => "program",
+        ScopeType::VhdlArchitecture => "vhdl_architecture",
+        ScopeType::VhdlProcedure => "vhdl_procedure",
+        ScopeType::VhdlFunction => "vhdl_function",
+        ScopeType::VhdlRecord => "vhdl_record",
+        ScopeType::VhdlProcess => "vhdl_process",
+        ScopeType::VhdlBlock => "vhdl_block",
+        ScopeType::VhdlForGenerate => "vhdl_for_generate",
+        ScopeType::VhdlIfGenerate => "vhdl_if_generate",
+        ScopeType::VhdlGenerate => "vhdl_generate",
+        ScopeType::VhdlPackage => "vhdl_package",
+        // there is no VCD equivalent for these GHW scopes
+        ScopeType::GhwGeneric | ScopeType::VhdlArray => "module",
+    }
+}
+
+fn var_tpe_to_str(tpe: VarType) -> &'static str {
+    match tpe {
+        VarType::Event => "event",
+        VarType::Integer => "integer",
+        VarType::Parameter => "parameter",
+        VarType::Real => "real",
+        VarType::Reg => "reg",
+        VarType::Supply0 => "supply0",
+        VarType::Supply1 => "supply1",
+        VarType::Time => "time",
+        VarType::Tri => "tri",
+        VarType::TriAnd => "triand",
+        VarType::TriOr => "trior",
+        VarType::TriReg => "trireg",
+        VarType::Tri0 => "tri0",
+        VarType::Tri1 => "tri1",
+        VarType::WAnd => "wand",
+        VarType::Wire => "wire",
+        VarType::WOr => "wor",
+        VarType::String => "string",
+        VarType::Port => "port",
+        VarType::SparseArray => "sparray",
+        VarType::RealTime => "realtime",
+        VarType::Bit => "bit",
+        VarType::Logic => "logic",
+        VarType::Int => "int",
+        VarType::ShortInt => "shortint",
+        VarType::LongInt => "longint",
+        VarType::Byte => "byte",
+        VarType::Enum => "enum",
+        VarType::ShortReal => "shortreal",
+        // VHDL types are additionally annotated with an attribute
+        VarType::Boolean
+        | VarType::BitVector
+        | VarType::StdLogic
+        | VarType::StdLogicVector
+        | VarType::StdULogic
+        | VarType::StdULogicVector => "wire",
+    }
+}
+
+/// Returns the VHDL data type for var types that cannot be expressed in plain VCD.
+pub(crate) fn var_tpe_to_vhdl_data_type(tpe: VarType) -> Option<FstVhdlDataType> {
+    match tpe {
+        VarType::Boolean => Some(FstVhdlDataType::Boolean),
+        VarType::BitVector => Some(FstVhdlDataType::Vector),
+        VarType::StdLogic => Some(FstVhdlDataType::Logic),
+        VarType::StdLogicVector => Some(FstVhdlDataType::LogicVector),
+        VarType::StdULogic => Some(FstVhdlDataType::ULogic),
+        VarType::StdULogicVector => Some(FstVhdlDataType::ULogicVector),
+        _ => None,
+    }
+}
+
+pub(crate) fn default_vhdl_type_name(tpe: VarType) -> &'static str {
+    match tpe {
+        VarType::Boolean => "boolean",
+        VarType::BitVector => "bit_vector",
+        VarType::StdLogic => "std_logic",
+        VarType::StdLogicVector => "std_logic_vector",
+        VarType::StdULogic => "std_ulogic",
+        VarType::StdULogicVector => "std_ulogic_vector",
+        _ => "none",
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1060,7 +1724,7 @@ mod tests {
     fn read_body_to_vec(input: &[u8]) -> Vec<String> {
         let mut out = Vec::new();
         let reader = BodyReader::new(input);
-        for (_, cmd) in reader {
+        for (_, cmd) in reader.map(|r| r.unwrap()) {
             let desc = match cmd {
                 BodyCmd::Time(value) => {
                     format!("Time({})", std::str::from_utf8(value).unwrap())
@@ -1135,6 +1799,40 @@ x%i"
         assert!(body_1.is_empty());
     }
 
+    #[test]
+    fn test_int_to_id() {
+        for value in [
+            0,
+            1,
+            2,
+            93,
+            94,
+            95,
+            472,
+            94 * 94,
+            1024 * 1024,
+            u32::MAX as u64,
+        ] {
+            assert_eq!(id_to_int(&int_to_id(value)), Some(value));
+        }
+        assert_eq!(int_to_id(0), b"!");
+        assert_eq!(int_to_id(472), b"#%");
+    }
+
+    #[test]
+    fn test_write_unsupported_values() {
+        let mut out = Vec::new();
+        write_value_change(&mut out, SignalValue::String("idle"), b"!").unwrap();
+        assert_eq!(out, b"sidle !\n");
+        for value in ["", "two words", "tab\t"] {
+            assert!(matches!(
+                write_value_change(&mut out, SignalValue::String(value), b"!"),
+                Err(WellenError::UnsupportedFeature(FileFormat::Vcd, _))
+            ));
+        }
+        assert!(timescale_unit_to_str(TimescaleUnit::Unknown).is_err());
+    }
+
     #[test]
     fn test_id_to_int() {
         assert_eq!(id_to_int(b""), None);
@@ -1146,4 +1844,49 @@ x%i"
         assert_eq!(id_to_int(b"("), Some(7));
         assert_eq!(id_to_int(b")"), Some(8));
     }
+
+    fn gzip(bytes: &[u8]) -> Vec<u8> {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+        encoder.write_all(bytes).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn test_read_gzip_in_chunks() {
+        let bytes = std::fs::read("inputs/icarus/CPU.vcd").unwrap();
+        let mut expected = read_from_bytes(&bytes).unwrap();
+        let expected_changes = expected.all_changes();
+        let compressed = gzip(&bytes);
+        for multi_thread in [true, false] {
+            let options = LoadOptions {
+                multi_thread,
+                ..Default::default()
+            };
+            // small chunks to make sure that values are split across many chunks
+            let mut actual = read_gzip(&compressed, &options, 1000).unwrap();
+            assert_eq!(expected.time_table(), actual.time_table());
+            assert_eq!(expected_changes, actual.all_changes());
+        }
+    }
+
+    #[test]
+    fn test_read_gzip_without_body() {
+        let compressed = gzip(b"$var wire 1 ! a $end\n$enddefinitions $end\n");
+        let waves = read_gzip(&compressed, &LoadOptions::default(), 1000).unwrap();
+        assert_eq!(waves.hierarchy().iter_vars().count(), 1);
+        assert!(waves.time_table().is_empty());
+    }
+
+    #[test]
+    fn test_read_stream_in_chunks() {
+        let bytes = std::fs::read("inputs/icarus/CPU.vcd").unwrap();
+        let mut expected = read_from_bytes(&bytes).unwrap();
+        let expected_changes = expected.all_changes();
+        for input in [bytes.clone(), gzip(&bytes)] {
+            // small chunks to make sure that values are split across many chunks
+            let mut actual = read_stream(&input[..], &LoadOptions::default(), 1000).unwrap();
+            assert_eq!(expected.time_table(), actual.time_table());
+            assert_eq!(expected_changes, actual.all_changes());
+        }
+    }
 }
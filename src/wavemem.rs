@@ -8,26 +8,30 @@ use crate::fst::{get_bytes_per_entry, get_len_and_meta, push_zeros};
 use crate::hierarchy::{Hierarchy, SignalRef, SignalType};
 use crate::signals::{Real, Signal, SignalEncoding, SignalSource, Time, TimeTableIdx};
 use crate::vcd::{u32_div_ceil, usize_div_ceil};
+use crate::WellenError;
 use bytesize::ByteSize;
 use num_enum::TryFromPrimitive;
 use rayon::prelude::*;
 use std::borrow::Cow;
 use std::io::Read;
 use std::num::NonZeroU32;
+use std::sync::Arc;
 
 /// Holds queryable waveform data. Use the `Encoder` to generate.
 pub struct Reader {
     blocks: Vec<Block>,
+    /// Signals that are not marked were filtered out while parsing.
+    mask: Option<Vec<bool>>,
 }
 
 impl SignalSource for Reader {
     fn load_signals(
-        &mut self,
+        &self,
         ids: &[SignalRef],
         types: &[SignalType],
         multi_threaded: bool,
-    ) -> Vec<Signal> {
-        if multi_threaded {
+    ) -> Result<Vec<Signal>, WellenError> {
+        let signals = if multi_threaded {
             ids.par_iter()
                 .zip(types.par_iter())
                 .map(|(id, len)| self.load_signal(*id, *len))
@@ -37,10 +41,24 @@ impl SignalSource for Reader {
                 .zip(types.iter())
                 .map(|(id, len)| self.load_signal(*id, *len))
                 .collect::<Vec<_>>()
+        };
+        Ok(signals)
+    }
+
+    fn append(&mut self, mut other: Reader) -> Result<(), WellenError> {
+        if let (Some(last), Some(first)) = (self.blocks.last(), other.blocks.first()) {
+            if last.end_time() > first.start_time {
+                return Err(WellenError::NotChronological(
+                    last.end_time(),
+                    first.start_time,
+                ));
+            }
         }
+        self.blocks.append(&mut other.blocks);
+        Ok(())
     }
 
-    fn get_time_table(&self) -> Vec<Time> {
+    fn get_time_table(&self) -> Arc<[Time]> {
         // create a combined time table from all blocks
         let len = self
             .blocks
@@ -51,7 +69,7 @@ impl SignalSource for Reader {
         for block in self.blocks.iter() {
             table.extend_from_slice(&block.time_table);
         }
-        table
+        table.into()
     }
 
     fn print_statistics(&self) {
@@ -61,6 +79,10 @@ impl SignalSource for Reader {
         );
         self.print_statistics();
     }
+
+    fn signal_mask(&self) -> Option<&[bool]> {
+        self.mask.as_deref()
+    }
 }
 
 impl Reader {
@@ -447,6 +469,8 @@ pub struct Encoder {
     skipping_time_step: bool,
     /// Finished blocks
     blocks: Vec<Block>,
+    /// Changes of signals that are not marked are dropped.
+    mask: Option<Vec<bool>>,
 }
 
 /// Indexes the time table inside a block.
@@ -455,12 +479,12 @@ type BlockTimeIdx = u16;
 impl Encoder {
     pub fn new(hierarchy: &Hierarchy) -> Self {
         let mut signals = Vec::with_capacity(hierarchy.num_unique_signals());
-        for var in hierarchy.get_unique_signals_vars() {
-            let tpe = match var {
-                None => SignalType::String, // we do not know!
-                Some(var) => var.signal_tpe(),
-            };
-            let pos = signals.len();
+        for pos in 0..hierarchy.num_unique_signals() {
+            let signal_ref = SignalRef::from_index(pos).unwrap();
+            // we do not know the type if no variable refers to the signal
+            let tpe = hierarchy
+                .get_signal_tpe(signal_ref)
+                .unwrap_or(SignalType::String);
             signals.push(SignalEncoder::new(tpe, pos));
         }
 
@@ -470,9 +494,23 @@ impl Encoder {
             has_new_data: false,
             skipping_time_step: false,
             blocks: Vec::default(),
+            mask: None,
         }
     }
 
+    /// Only keeps the changes of signals that are marked in `mask`.
+    pub(crate) fn with_signal_mask(hierarchy: &Hierarchy, mask: Option<&[bool]>) -> Self {
+        Self {
+            mask: mask.map(|m| m.to_vec()),
+            ..Self::new(hierarchy)
+        }
+    }
+
+    #[inline]
+    fn is_masked(&self, id: SignalRef) -> bool {
+        self.mask.as_ref().is_some_and(|m| !m[id.index()])
+    }
+
     pub fn time_change(&mut self, time: u64) {
         // sanity check to make sure that time is increasing
         if let Some(prev_time) = self.time_table.last() {
@@ -497,16 +535,21 @@ impl Encoder {
     }
 
     /// Call with an unaltered VCD value.
-    pub fn vcd_value_change(&mut self, id: u64, value: &[u8]) {
+    pub fn vcd_value_change(&mut self, id: u64, value: &[u8]) -> Result<(), WellenError> {
         assert!(
             !self.time_table.is_empty(),
             "We need a call to time_change first!"
         );
         if !self.skipping_time_step {
             let time_idx = (self.time_table.len() - 1) as u16;
-            self.signals[id as usize].add_vcd_change(time_idx, value);
+            let signal = self
+                .signals
+                .get_mut(id as usize)
+                .ok_or_else(|| WellenError::VcdUnknownId(id.to_string()))?;
+            signal.add_vcd_change(time_idx, value)?;
             self.has_new_data = true;
         }
+        Ok(())
     }
 
     /// Call with a value that is already encoded in our internal format.
@@ -515,7 +558,7 @@ impl Encoder {
             !self.time_table.is_empty(),
             "We need a call to time_change first!"
         );
-        if !self.skipping_time_step {
+        if !self.skipping_time_step && !self.is_masked(id) {
             let time_idx = (self.time_table.len() - 1) as u16;
             self.signals[id.index()].add_n_bit_change(time_idx, value, states);
             self.has_new_data = true;
@@ -527,7 +570,7 @@ impl Encoder {
             !self.time_table.is_empty(),
             "We need a call to time_change first!"
         );
-        if !self.skipping_time_step {
+        if !self.skipping_time_step && !self.is_masked(id) {
             let time_idx = (self.time_table.len() - 1) as u16;
             self.signals[id.index()].add_real_change(time_idx, value);
             self.has_new_data = true;
@@ -540,6 +583,7 @@ impl Encoder {
         // create a new reader with the blocks that we have
         Reader {
             blocks: self.blocks,
+            mask: self.mask,
         }
     }
 
@@ -552,12 +596,12 @@ impl Encoder {
         // if the other encoder has no blocks, there is nothing for us to do
         if let Some(other_first_block) = other.blocks.first() {
             // make sure the timeline fits
-            let us_end_time = self.blocks.last().unwrap().end_time();
-            let other_start = other_first_block.start_time;
-            assert!(
-                us_end_time <= other_start,
-                "Can only append encoders in chronological order!"
-            );
+            if let Some(us_last_block) = self.blocks.last() {
+                assert!(
+                    us_last_block.end_time() <= other_first_block.start_time,
+                    "Can only append encoders in chronological order!"
+                );
+            }
             // append all blocks from the other encoder
             self.blocks.append(&mut other.blocks);
         }
@@ -689,6 +733,14 @@ impl SignalEncoder {
     }
 }
 
+#[inline]
+fn invalid_vcd_value(value: &[u8], reason: &str) -> WellenError {
+    WellenError::VcdInvalidValue(
+        String::from_utf8_lossy(value).to_string(),
+        reason.to_string(),
+    )
+}
+
 /// Minimum number of bytes for a signal to warrant an attempt at LZ4 compression.
 const MIN_SIZE_TO_COMPRESS: usize = 32;
 /// Flag to turn off compression.
@@ -761,8 +813,11 @@ impl SignalEncoder {
     }
 
     /// Adds a change from a VCD string.
-    fn add_vcd_change(&mut self, time_index: u16, value: &[u8]) {
+    fn add_vcd_change(&mut self, time_index: u16, value: &[u8]) -> Result<(), WellenError> {
         let time_idx_delta = time_index - self.prev_time_idx;
+        if value.is_empty() {
+            return Err(invalid_vcd_value(value, "empty value"));
+        }
         match self.tpe {
             SignalType::BitVector(len, _) => {
                 let value_bits: &[u8] = match value[0] {
@@ -779,22 +834,23 @@ impl SignalEncoder {
                     }
                 };
                 if len.get() == 1 {
-                    let states =
-                        try_write_1_bit_9_state(time_idx_delta, value_bits[0], &mut self.data)
-                            .unwrap_or_else(|| {
-                                panic!(
-                                    "Failed to parse four state value: {} for signal of size 1",
-                                    String::from_utf8_lossy(value)
-                                )
-                            });
+                    let states = value_bits
+                        .first()
+                        .and_then(|v| try_write_1_bit_9_state(time_idx_delta, *v, &mut self.data))
+                        .ok_or_else(|| {
+                            invalid_vcd_value(
+                                value,
+                                "not a nine state value for a signal of size 1",
+                            )
+                        })?;
                     self.max_states = States::join(self.max_states, states);
                 } else {
-                    let states = check_states(value_bits).unwrap_or_else(|| {
-                        panic!(
-                            "Bit-vector contains invalid character. Only 2, 4 and 9-state signals are supported: {}",
-                            String::from_utf8_lossy(value)
+                    let states = check_states(value_bits).ok_or_else(|| {
+                        invalid_vcd_value(
+                            value,
+                            "bit-vector contains invalid character, only 2, 4 and 9-state signals are supported",
                         )
-                    });
+                    })?;
                     self.max_states = States::join(self.max_states, states);
 
                     // write time delta + num-states meta-data
@@ -805,48 +861,44 @@ impl SignalEncoder {
                     let data_to_write = if value_bits.len() == bits {
                         Cow::Borrowed(value_bits)
                     } else {
-                        let expanded = expand_special_vector_cases(value_bits, bits)
-                            .unwrap_or_else(|| {
-                                panic!(
-                                    "Failed to parse four state value: {} for signal of size {}",
-                                    String::from_utf8_lossy(value),
-                                    bits
+                        let expanded =
+                            expand_special_vector_cases(value_bits, bits).ok_or_else(|| {
+                                invalid_vcd_value(
+                                    value,
+                                    &format!("does not fit into a signal of size {bits}"),
                                 )
-                            });
-                        assert_eq!(expanded.len(), bits);
+                            })?;
+                        debug_assert_eq!(expanded.len(), bits);
                         Cow::Owned(expanded)
                     };
                     write_n_state(states, &data_to_write, &mut self.data, None);
                 }
             }
             SignalType::String => {
-                assert!(
-                    matches!(value[0], b's' | b'S'),
-                    "expected a string, not {}",
-                    String::from_utf8_lossy(value)
-                );
+                if !matches!(value[0], b's' | b'S') {
+                    return Err(invalid_vcd_value(value, "expected a string"));
+                }
                 // string: var-length time index + var-len length + content
                 leb128::write::unsigned(&mut self.data, time_idx_delta as u64).unwrap();
                 leb128::write::unsigned(&mut self.data, (value.len() - 1) as u64).unwrap();
                 self.data.extend_from_slice(&value[1..]);
             }
             SignalType::Real => {
-                assert!(
-                    matches!(value[0], b'r' | b'R'),
-                    "expected a real, not {}",
-                    String::from_utf8_lossy(value)
-                );
+                if !matches!(value[0], b'r' | b'R') {
+                    return Err(invalid_vcd_value(value, "expected a real"));
+                }
                 // parse float
                 let float_value: Real = std::str::from_utf8(&value[1..])
-                    .unwrap()
-                    .parse::<Real>()
-                    .unwrap();
+                    .ok()
+                    .and_then(|v| v.parse::<Real>().ok())
+                    .ok_or_else(|| invalid_vcd_value(value, "expected a real"))?;
                 // write var-length time index + fixed little endian float bytes
                 leb128::write::unsigned(&mut self.data, time_idx_delta as u64).unwrap();
                 self.data.extend_from_slice(&float_value.to_le_bytes());
             }
         }
         self.prev_time_idx = time_index;
+        Ok(())
     }
 
     /// returns a compressed signal representation
@@ -0,0 +1,43 @@
+// Copyright 2024 The Regents of the University of California
+// released under BSD 3-Clause License
+// author: Kevin Laeufer <laeufer@berkeley.edu>
+//
+// helpers that are shared by several integration tests
+
+// every test binary only uses some of the helpers
+#![allow(dead_code)]
+
+use wellen::*;
+
+/// Returns the signals of all variables in the hierarchy.
+pub fn all_signals(waves: &Waveform) -> Vec<SignalRef> {
+    waves
+        .hierarchy()
+        .iter_vars()
+        .map(|v| v.signal_ref())
+        .collect()
+}
+
+/// Converts all value changes to strings in order to make them easy to compare.
+pub fn changes(signal: &Signal) -> Vec<(TimeTableIdx, String)> {
+    signal
+        .iter_changes()
+        .map(|(t, v)| (t, v.to_string()))
+        .collect()
+}
+
+/// Checks that both waveforms contain the same changes for all `ids`, which need to be loaded.
+pub fn assert_same_changes(expected: &Waveform, actual: &Waveform, ids: &[SignalRef]) {
+    for id in ids.iter() {
+        let (e, a) = (expected.get_signal(*id), actual.get_signal(*id));
+        assert_eq!(changes(e.unwrap()), changes(a.unwrap()), "{id:?}");
+    }
+}
+
+/// Loads all signals of both waveforms and checks that they contain the same changes.
+pub fn assert_same_signals(expected: &mut Waveform, actual: &mut Waveform) {
+    let ids = all_signals(expected);
+    expected.load_signals(&ids).unwrap();
+    actual.load_signals(&ids).unwrap();
+    assert_same_changes(expected, actual, &ids);
+}
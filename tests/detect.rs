@@ -33,13 +33,19 @@ fn test_detect_file_format() {
         let filename_str = filename.to_str().unwrap();
         match format {
             FileFormat::Vcd => {
-                assert!(filename_str.ends_with(".vcd"), "{filename_str}");
+                let is_vcd = [".vcd", ".vcd.gz"]
+                    .iter()
+                    .any(|ext| filename_str.ends_with(ext));
+                assert!(is_vcd, "{filename_str}");
             }
             FileFormat::Fst => {
                 assert!(filename_str.ends_with(".fst"), "{filename_str}");
             }
             FileFormat::Ghw => {
-                assert!(filename_str.ends_with(".ghw"), "{filename_str}");
+                let is_ghw = [".ghw", ".ghw.gz", ".ghw.bz2"]
+                    .iter()
+                    .any(|ext| filename_str.ends_with(ext));
+                assert!(is_ghw, "{filename_str}");
             }
             FileFormat::Unknown => {
                 // this file ends in fst, but does not seem to be a valid fst
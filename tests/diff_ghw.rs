@@ -122,7 +122,7 @@ fn get_all_signals(w: &Waveform) -> Vec<SignalRef> {
 
 fn load_all_signals(our: &mut Waveform) {
     let all_signals = get_all_signals(our);
-    our.load_signals(&all_signals);
+    our.load_signals(&all_signals).unwrap();
 }
 
 fn diff_signals(ghw: &mut Waveform, fst: &mut Waveform, time_factor: u64) {
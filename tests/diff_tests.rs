@@ -235,7 +235,7 @@ fn load_all_signals(our: &mut Waveform) {
         .flatten()
         .map(|v| v.signal_ref())
         .collect();
-    our.load_signals(&all_signals);
+    our.load_signals(&all_signals).unwrap();
 }
 
 fn diff_signals<R: BufRead>(
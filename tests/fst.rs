@@ -4,6 +4,8 @@
 //
 // test fst specific meta data
 
+mod common;
+
 use std::collections::HashSet;
 use wellen::*;
 
@@ -175,3 +177,106 @@ fn test_scope_merging() {
         ]
     )
 }
+
+/// A FST file that was cut short should result in an error instead of a panic.
+#[test]
+fn test_fst_truncated() {
+    let bytes = std::fs::read("inputs/gtkwave-analyzer/des.fst").unwrap();
+    for len in [0, 100, bytes.len() / 2] {
+        assert!(
+            fst::read_from_bytes(bytes[..len].to_vec()).is_err(),
+            "truncated to {len} bytes"
+        );
+    }
+}
+
+#[test]
+fn test_fst_multi_threaded_loading() {
+    // use more threads than cores to make sure that the signals are split into several groups
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(8)
+        .build()
+        .unwrap();
+    for filename in [
+        "inputs/gtkwave-analyzer/des.fst",
+        "inputs/icarus/CPU.vcd.fst",
+        "inputs/ghdl/oscar/ghdl.fst",
+    ] {
+        let mut expected = fst::read(filename).expect("failed to parse");
+        let bytes = std::fs::read(filename).unwrap();
+        let mut actual = fst::read_from_bytes(bytes).expect("failed to parse");
+        let h = expected.hierarchy();
+        let ids: Vec<_> = h.iter_vars().map(|v| v.signal_ref()).collect();
+        expected.load_signals(&ids).unwrap();
+        pool.install(|| actual.load_signals_multi_threaded(&ids))
+            .unwrap();
+        assert_eq!(expected.time_table(), actual.time_table());
+        common::assert_same_changes(&expected, &actual, &ids);
+    }
+}
+
+/// Files with many value change blocks are split by block instead of by signal.
+#[test]
+fn test_fst_multi_threaded_loading_of_many_blocks() {
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(8)
+        .build()
+        .unwrap();
+    let mut vcd = vcd::read("inputs/icarus/CPU.vcd").expect("failed to parse");
+    let ids: Vec<_> = vcd
+        .hierarchy()
+        .iter_vars()
+        .map(|v| v.signal_ref())
+        .collect();
+    vcd.load_signals(&ids).unwrap();
+    let options = fst::WriteOptions {
+        max_changes_per_block: 50,
+        ..Default::default()
+    };
+    let mut bytes = vec![];
+    fst::write(&vcd, &mut bytes, options).unwrap();
+
+    let mut expected = fst::read_from_bytes(bytes.clone()).expect("failed to parse");
+    let mut actual = fst::read_from_bytes(bytes).expect("failed to parse");
+    let h = expected.hierarchy();
+    let ids: Vec<_> = h.iter_vars().map(|v| v.signal_ref()).collect();
+    expected.load_signals(&ids).unwrap();
+    pool.install(|| actual.load_signals_multi_threaded(&ids))
+        .unwrap();
+    assert_eq!(vcd.time_table(), actual.time_table());
+    common::assert_same_changes(&expected, &actual, &ids);
+}
+
+#[test]
+fn test_fst_signal_filter() {
+    let filename = "inputs/gtkwave-analyzer/des.fst";
+    let mut expected = fst::read(filename).expect("failed to parse");
+    let h = expected.hierarchy();
+    let ids: Vec<_> = h.iter_vars().map(|v| v.signal_ref()).collect();
+    let kept: Vec<_> = ids.iter().step_by(3).copied().collect();
+    expected.load_signals(&ids).unwrap();
+
+    for multi_thread in [true, false] {
+        let options = LoadOptions {
+            multi_thread,
+            ..Default::default()
+        }
+        .with_signal_filter(SignalFilter::Signals(kept.clone()));
+        let mut waves = fst::read_with_options(filename, options).unwrap();
+        if multi_thread {
+            waves.load_signals_multi_threaded(&kept).unwrap();
+        } else {
+            waves.load_signals(&kept).unwrap();
+        }
+        for id in ids.iter() {
+            if kept.contains(id) {
+                let changes = waves.get_signal(*id).unwrap().time_indices();
+                let orig = expected.get_signal(*id).unwrap().time_indices();
+                assert_eq!(changes, orig);
+            } else {
+                let res = waves.load_signals(&[*id]);
+                assert!(matches!(res, Err(WellenError::SignalFiltered(_))));
+            }
+        }
+    }
+}
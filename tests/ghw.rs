@@ -4,6 +4,8 @@
 //
 // test fst specific meta data
 
+mod common;
+
 use wellen::*;
 
 #[test]
@@ -61,7 +63,7 @@ fn test_ghw_enum_encoding() {
 
     // make sure that all enum values are binary
     let ee_signal_ref = h.get(ee).signal_ref();
-    waves.load_signals(&[ee_signal_ref]);
+    waves.load_signals(&[ee_signal_ref]).unwrap();
     let ee_signal = waves.get_signal(ee_signal_ref).unwrap();
     for id in 0..waves.time_table().len() {
         let off = ee_signal.get_offset(id as TimeTableIdx).unwrap();
@@ -69,6 +71,18 @@ fn test_ghw_enum_encoding() {
         assert_eq!(value.to_bit_string().unwrap().len(), 2);
         assert!(matches!(value, SignalValue::Binary(_, 2)));
     }
+
+    // all values translate into one of the enum literals
+    for id in 0..waves.time_table().len() {
+        let literal = waves
+            .enum_value_at(ee, id as TimeTableIdx)
+            .unwrap()
+            .unwrap();
+        assert!(
+            ["foo", "bar", "tada"].contains(&literal.as_str()),
+            "{literal}"
+        );
+    }
 }
 
 #[test]
@@ -77,3 +91,134 @@ fn test_ghdl_issue_538_ghw() {
     let filename = "inputs/ghdl/ghdl_issue_538.ghw";
     let _waves = ghw::read(filename).expect("failed to parse");
 }
+
+fn check_same_signals(expected: &mut Waveform, actual: &mut Waveform) {
+    assert_eq!(expected.time_table(), actual.time_table());
+    let (he, ha) = (expected.hierarchy(), actual.hierarchy());
+    let names_e: Vec<_> = he.iter_vars().map(|v| v.full_name(he)).collect();
+    let names_a: Vec<_> = ha.iter_vars().map(|v| v.full_name(ha)).collect();
+    assert_eq!(names_e, names_a);
+    common::assert_same_signals(expected, actual);
+}
+
+#[test]
+fn test_compressed_ghw() {
+    let mut expected = ghw::read("inputs/ghdl/tb_recv.ghw").unwrap();
+    for filename in ["inputs/ghdl/tb_recv.ghw.gz", "inputs/ghdl/tb_recv.ghw.bz2"] {
+        assert_eq!(open_and_detect_file_format(filename), FileFormat::Ghw);
+        let mut waves = ghw::read(filename).expect("failed to parse");
+        check_same_signals(&mut expected, &mut waves);
+
+        let bytes = std::fs::read(filename).unwrap();
+        let mut waves = ghw::read_from_bytes(bytes).expect("failed to parse");
+        check_same_signals(&mut expected, &mut waves);
+
+        let mut waves = open(filename, LoadOptions::default()).expect("failed to open");
+        check_same_signals(&mut expected, &mut waves);
+    }
+}
+
+#[test]
+fn test_multi_threaded_ghw() {
+    let single_threaded = LoadOptions {
+        multi_thread: false,
+        ..Default::default()
+    };
+    // use more threads than cores to make sure that the values are split into several chunks
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(8)
+        .build()
+        .unwrap();
+    for filename in [
+        "inputs/ghdl/tb_recv.ghw",
+        "inputs/ghdl/oscar/ali.ghw",
+        "inputs/ghdl/oscar/test.ghw",
+        "inputs/ghdl/oscar/test2.ghw",
+    ] {
+        let mut expected = ghw::read_with_options(filename, single_threaded.clone()).unwrap();
+        let mut waves = pool
+            .install(|| ghw::read_with_options(filename, LoadOptions::default()))
+            .expect("failed to parse");
+        check_same_signals(&mut expected, &mut waves);
+    }
+}
+
+/// Returns the last value of the signal at the time table index, if there is any.
+fn last_value_at(waves: &Waveform, id: SignalRef, idx: TimeTableIdx) -> Option<String> {
+    let signal = waves.get_signal(id).unwrap();
+    let offset = signal.get_offset(idx)?;
+    Some(
+        signal
+            .get_value_at(&offset, offset.elements - 1)
+            .to_string(),
+    )
+}
+
+#[test]
+fn test_ghw_time_range() {
+    for filename in ["inputs/ghdl/tb_recv.ghw", "inputs/ghdl/tb_recv.ghw.gz"] {
+        let mut full = ghw::read(filename).unwrap();
+        let full_times = full.time_table().to_vec();
+        let range = full_times[full_times.len() / 3]..full_times[2 * full_times.len() / 3];
+        let mut waves = ghw::read_time_range(filename, LoadOptions::default(), range.clone())
+            .expect("failed to parse");
+
+        // the range is preceded by the time step that holds the initial state
+        let first = full_times.iter().position(|t| *t >= range.start).unwrap();
+        let last = full_times.iter().position(|t| *t >= range.end).unwrap();
+        assert_eq!(waves.time_table(), &full_times[first - 1..last]);
+
+        let hierarchy = full.hierarchy();
+        let ids: Vec<_> = hierarchy.iter_vars().map(|v| v.signal_ref()).collect();
+        full.load_signals(&ids).unwrap();
+        waves.load_signals(&ids).unwrap();
+        for id in ids {
+            for idx in 0..waves.time_table().len() {
+                let full_idx = (first - 1 + idx) as TimeTableIdx;
+                assert_eq!(
+                    last_value_at(&full, id, full_idx),
+                    last_value_at(&waves, id, idx as TimeTableIdx),
+                    "{id:?} @ {}",
+                    waves.time_table()[idx]
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn test_ghw_signal_filter() {
+    let filename = "inputs/ghdl/tb_recv.ghw";
+    let mut expected = ghw::read(filename).unwrap();
+    let h = expected.hierarchy();
+    let ids: Vec<_> = h.iter_vars().map(|v| v.signal_ref()).collect();
+    let kept: Vec<_> = ids.iter().step_by(2).copied().collect();
+    expected.load_signals(&ids).unwrap();
+
+    for multi_thread in [true, false] {
+        let options = LoadOptions {
+            multi_thread,
+            ..Default::default()
+        }
+        .with_signal_filter(SignalFilter::Signals(kept.clone()));
+        let mut waves = ghw::read_with_options(filename, options).unwrap();
+        waves.load_signals(&kept).unwrap();
+        let mut skipped = 0;
+        for id in ids.iter() {
+            match waves.load_signals(&[*id]) {
+                Ok(()) => {
+                    let changes = waves.get_signal(*id).unwrap().time_indices();
+                    let orig = expected.get_signal(*id).unwrap().time_indices();
+                    assert_eq!(changes, orig);
+                }
+                Err(WellenError::SignalFiltered(_)) => {
+                    // signals that others are sliced from are always kept
+                    assert!(!kept.contains(id));
+                    skipped += 1;
+                }
+                Err(e) => panic!("{e}"),
+            }
+        }
+        assert!(skipped > 0);
+    }
+}
@@ -0,0 +1,246 @@
+// Copyright 2024 The Regents of the University of California
+// released under BSD 3-Clause License
+// author: Kevin Laeufer <laeufer@berkeley.edu>
+//
+// test the file format independent entry points
+
+mod common;
+
+use wellen::*;
+
+fn check_same_hierarchy(a: &Waveform, b: &Waveform) {
+    let (ha, hb) = (a.hierarchy(), b.hierarchy());
+    let names_a: Vec<_> = ha.iter_vars().map(|v| v.full_name(ha)).collect();
+    let names_b: Vec<_> = hb.iter_vars().map(|v| v.full_name(hb)).collect();
+    assert_eq!(names_a, names_b);
+}
+
+#[test]
+fn test_open_all_formats() {
+    for filename in [
+        "inputs/icarus/CPU.vcd",
+        "inputs/icarus/CPU.vcd.fst",
+        "inputs/ghdl/tb_recv.ghw",
+        "inputs/icarus/CPU.vcd.gz",
+        "inputs/ghdl/tb_recv.ghw.gz",
+    ] {
+        let waves = open(filename, LoadOptions::default())
+            .unwrap_or_else(|e| panic!("failed to open {filename}: {e}"));
+        assert!(waves.hierarchy().iter_vars().count() > 0);
+
+        let f = std::fs::File::open(filename).unwrap();
+        let from_reader = open_from_reader(std::io::BufReader::new(f), LoadOptions::default())
+            .unwrap_or_else(|e| panic!("failed to open {filename} from reader: {e}"));
+        check_same_hierarchy(&waves, &from_reader);
+    }
+}
+
+#[test]
+fn test_open_unknown_format() {
+    let result = open("inputs/sources.csv", LoadOptions::default());
+    assert!(matches!(result, Err(WellenError::UnknownFileFormat)));
+}
+
+#[test]
+fn test_load_progress() {
+    for filename in [
+        "inputs/icarus/CPU.vcd",
+        "inputs/icarus/CPU.vcd.fst",
+        "inputs/ghdl/tb_recv.ghw",
+    ] {
+        let progress = std::sync::Arc::new(LoadProgress::new());
+        let options = LoadOptions {
+            multi_thread: false,
+            ..Default::default()
+        }
+        .with_progress(progress.clone());
+        let waves = open(filename, options).unwrap();
+        let (done, total) = progress.progress();
+        assert!(done > 0 && total > 0, "{filename}: {done} / {total}");
+        if waves.hierarchy().file_format() == FileFormat::Fst {
+            // FST signal values are only read on demand and thus not part of the progress
+            assert_eq!(progress.phase(), LoadPhase::Header, "{filename}");
+        } else {
+            assert_eq!(progress.phase(), LoadPhase::Body, "{filename}");
+            assert_eq!(done, total, "{filename}");
+        }
+    }
+}
+
+/// Cancelling after a file was opened must not affect signals that are loaded later.
+#[test]
+fn test_cancel_after_open() {
+    for filename in ["inputs/icarus/CPU.vcd", "inputs/icarus/CPU.vcd.fst"] {
+        let progress = std::sync::Arc::new(LoadProgress::new());
+        let options = LoadOptions::default().with_progress(progress.clone());
+        let mut waves = open(filename, options).unwrap();
+        let before = progress.progress();
+        progress.cancel();
+        let ids: Vec<_> = waves
+            .hierarchy()
+            .iter_vars()
+            .map(|v| v.signal_ref())
+            .collect();
+        waves.load_signals(&ids).unwrap();
+        waves.unload_signals(&ids);
+        waves.load_signals_multi_threaded(&ids).unwrap();
+        assert_eq!(progress.progress(), before, "{filename}");
+    }
+}
+
+#[test]
+fn test_load_cancelled() {
+    for filename in [
+        "inputs/icarus/CPU.vcd",
+        "inputs/icarus/CPU.vcd.gz",
+        "inputs/icarus/CPU.vcd.fst",
+        "inputs/ghdl/tb_recv.ghw",
+        "inputs/ghdl/tb_recv.ghw.gz",
+    ] {
+        let progress = std::sync::Arc::new(LoadProgress::new());
+        progress.cancel();
+        let options = LoadOptions::default().with_progress(progress);
+        let result = open(filename, options);
+        assert!(
+            matches!(result, Err(WellenError::Cancelled)),
+            "{filename}: {result:?}"
+        );
+    }
+}
+
+#[test]
+fn test_load_header_then_body() {
+    for filename in [
+        "inputs/icarus/CPU.vcd",
+        "inputs/icarus/CPU.vcd.fst",
+        "inputs/ghdl/tb_recv.ghw",
+        "inputs/icarus/CPU.vcd.gz",
+        "inputs/ghdl/tb_recv.ghw.gz",
+    ] {
+        let mut expected = open(filename, LoadOptions::default()).unwrap();
+        let (hierarchy, body) = open_header(filename, LoadOptions::default()).unwrap();
+        assert_eq!(body.file_format(), hierarchy.file_format());
+        // the body can be parsed while the hierarchy is already in use
+        let body = std::thread::scope(|s| {
+            let loader = s.spawn(|| body.load(&hierarchy));
+            assert!(hierarchy.iter_vars().count() > 0);
+            loader.join().unwrap().unwrap()
+        });
+        assert_eq!(body.time_table(), expected.time_table(), "{filename}");
+        let mut actual = Waveform::from_parts(hierarchy, body);
+        check_same_hierarchy(&expected, &actual);
+        common::assert_same_signals(&mut expected, &mut actual);
+    }
+}
+
+#[test]
+fn test_memory_budget() {
+    let mut waves = open("inputs/icarus/CPU.vcd", LoadOptions::default()).unwrap();
+    let mut ids: Vec<_> = waves
+        .hierarchy()
+        .iter_vars()
+        .map(|v| v.signal_ref())
+        .collect();
+    ids.sort();
+    ids.dedup();
+    let (a, b, c) = (ids[0], ids[1], ids[2]);
+    waves.load_signals(&[a, b, c]).unwrap();
+    let size = |w: &Waveform, id| w.get_signal(id).unwrap().size_in_memory();
+    let (size_a, size_b, size_c) = (size(&waves, a), size(&waves, b), size(&waves, c));
+    let changes_b: Vec<_> = waves
+        .get_signal(b)
+        .unwrap()
+        .iter_changes()
+        .map(|(t, v)| (t, v.to_string()))
+        .collect();
+    waves.unload_signals(&[a, b, c]);
+
+    // all three signals do not fit
+    waves.set_memory_budget(Some(size_a + size_b + size_c - 1));
+    waves.load_signals(&[a]).unwrap();
+    waves.load_signals(&[b]).unwrap();
+    assert!(waves.get_signal(a).is_some());
+    waves.load_signals(&[c]).unwrap();
+    // b is the least recently used signal
+    assert!(waves.get_signal(b).is_none());
+    assert!(waves.get_signal(c).is_some());
+
+    // b is reloaded on access, which evicts a
+    let reloaded: Vec<_> = waves
+        .get_or_load_signal(b)
+        .unwrap()
+        .iter_changes()
+        .map(|(t, v)| (t, v.to_string()))
+        .collect();
+    assert_eq!(reloaded, changes_b);
+    assert!(waves.get_signal(a).is_none());
+
+    let stats = waves.cache_statistics();
+    assert_eq!(stats.misses, 3 + 4);
+    assert_eq!(stats.evictions, 2);
+    assert_eq!(stats.loaded_signals, 2);
+    assert_eq!(stats.bytes_in_memory, size_b + size_c);
+    // accesses before the budget was set are not tracked
+    assert_eq!(stats.hits, 2, "{stats:?}");
+
+    // accessors that take `&mut self` reload evicted signals as well
+    let last = *waves.time_table().last().unwrap();
+    let sampled = waves.sample(&[a], last).unwrap()[0].map(|v| v.to_string());
+    assert!(sampled.is_some());
+    assert!(waves.get_signal(a).is_some());
+    assert_eq!(waves.cache_statistics().loaded_signals, 2);
+
+    // removing the limit does not unload anything
+    waves.set_memory_budget(None);
+    waves.load_signals(&[a, b, c]).unwrap();
+    assert_eq!(waves.cache_statistics().loaded_signals, 3);
+}
+
+#[test]
+fn test_shared_waveform() {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<SharedWaveform>();
+
+    for filename in [
+        "inputs/icarus/CPU.vcd",
+        "inputs/icarus/CPU.vcd.fst",
+        "inputs/ghdl/tb_recv.ghw",
+    ] {
+        let mut expected = open(filename, LoadOptions::default()).unwrap();
+        let ids = common::all_signals(&expected);
+        expected.load_signals(&ids).unwrap();
+
+        let shared = open(filename, LoadOptions::default())
+            .unwrap()
+            .into_shared();
+        assert_eq!(shared.time_table()[..], expected.time_table()[..]);
+        // every thread loads a different subset of the signals
+        let chunks: Vec<_> = ids.chunks(ids.len().div_ceil(4)).collect();
+        let loaded: Vec<Vec<_>> = std::thread::scope(|s| {
+            let threads: Vec<_> = chunks
+                .iter()
+                .map(|chunk| s.spawn(|| shared.load_signals(chunk).unwrap()))
+                .collect();
+            threads.into_iter().map(|t| t.join().unwrap()).collect()
+        });
+        for (chunk, signals) in chunks.iter().zip(loaded) {
+            for (id, signal) in chunk.iter().zip(signals) {
+                let expected_signal = expected.get_signal(*id).unwrap();
+                assert_eq!(
+                    common::changes(&signal),
+                    common::changes(expected_signal),
+                    "{filename}"
+                );
+            }
+        }
+
+        // unloaded signals remain valid for threads that still use them
+        let signal = shared.get_signal(ids[0]).unwrap();
+        shared.unload_signals(&ids);
+        assert!(shared.get_signal(ids[0]).is_none());
+        assert_eq!(
+            signal.time_indices(),
+            expected.get_signal(ids[0]).unwrap().time_indices()
+        );
+    }
+}
@@ -2,6 +2,9 @@
 // released under BSD 3-Clause License
 // author: Kevin Laeufer <laeufer@berkeley.edu>
 
+mod common;
+
+use std::io::Write;
 use wellen::*;
 
 #[test]
@@ -28,7 +31,9 @@ fn test_vcd_not_starting_at_zero() {
     };
 
     // querying a signal before it has a value should return none
-    waves.load_signals(&[pc.signal_ref(), sp.signal_ref()]);
+    waves
+        .load_signals(&[pc.signal_ref(), sp.signal_ref()])
+        .unwrap();
 
     // pc is fine since it changes at 4 which is time_table idx 0
     let pc_signal = waves.get_signal(pc.signal_ref()).unwrap();
@@ -64,7 +69,7 @@ fn check_no_fake_changes(mut waves: Waveform) {
     };
     assert_eq!(data.full_name(waves.hierarchy()), "logic.data");
 
-    waves.load_signals(&[data.signal_ref()]);
+    waves.load_signals(&[data.signal_ref()]).unwrap();
     let signal = waves.get_signal(data.signal_ref()).unwrap();
 
     assert_eq!(
@@ -86,3 +91,261 @@ fn test_vcd_with_decreasing_time() {
     let waves = vcd::read(filename).expect("failed to parse");
     assert_eq!(waves.time_table(), [4, 5]);
 }
+
+/// A VCD that ends in the middle of the header should result in an error instead of a panic.
+#[test]
+fn test_vcd_truncated_header() {
+    let bytes = std::fs::read("inputs/icarus/CPU.vcd").unwrap();
+    let result = vcd::read_from_bytes(&bytes[..200]);
+    assert!(matches!(
+        result,
+        Err(WellenError::TruncatedFile(FileFormat::Vcd))
+    ));
+}
+
+#[test]
+fn test_vcd_invalid_body() {
+    let input = b"$timescale 1ns $end
+$scope module top $end
+$var wire 1 ! a $end
+$var wire 4 \" b $end
+$upscope $end
+$enddefinitions $end
+#0
+0!
+b0000 \"
+#1
+1!
+b01q0 \"
+";
+    let result = vcd::read_from_bytes_with_options(
+        input,
+        LoadOptions {
+            multi_thread: false,
+            ..Default::default()
+        },
+    );
+    assert!(matches!(result, Err(WellenError::VcdInvalidValue(_, _))));
+
+    let unknown_id = b"$scope module top $end
+$var wire 1 ! a $end
+$upscope $end
+$enddefinitions $end
+#0
+0!
+1$
+";
+    let result = vcd::read_from_bytes(unknown_id);
+    assert!(matches!(result, Err(WellenError::VcdUnknownId(_))));
+}
+
+#[test]
+fn test_vcd_gzip() {
+    let mut expected = vcd::read("inputs/icarus/CPU.vcd").unwrap();
+    let mut waves = vcd::read("inputs/icarus/CPU.vcd.gz").expect("failed to parse");
+    assert_eq!(expected.time_table(), waves.time_table());
+    common::assert_same_signals(&mut expected, &mut waves);
+}
+
+#[test]
+fn test_vcd_signal_filter() {
+    let filename = "inputs/gameroy/trace_prefix.vcd";
+    let mut expected = vcd::read(filename).unwrap();
+    let h = expected.hierarchy();
+    let cpu = h.lookup_scope(&["gameroy", "cpu"]).unwrap();
+    let pc = h.lookup_var(&["gameroy", "cpu"], &"pc").unwrap();
+    let pc = h.get(pc).signal_ref();
+    let ids: Vec<_> = h.iter_vars().map(|v| v.signal_ref()).collect();
+    let in_cpu: Vec<_> = h
+        .iter_vars()
+        .filter(|v| v.full_name(h).starts_with("gameroy.cpu."))
+        .map(|v| v.signal_ref())
+        .collect();
+    expected.load_signals(&ids).unwrap();
+
+    let filters = [
+        (SignalFilter::Signals(vec![pc]), vec![pc]),
+        (SignalFilter::Scopes(vec![cpu]), in_cpu),
+        (
+            SignalFilter::Predicate(std::sync::Arc::new(|h: &Hierarchy, v: &Var| {
+                v.name(h) == "pc"
+            })),
+            vec![pc],
+        ),
+    ];
+    for (filter, kept) in filters {
+        for multi_thread in [true, false] {
+            let options = LoadOptions {
+                multi_thread,
+                ..Default::default()
+            }
+            .with_signal_filter(filter.clone());
+            let mut waves = vcd::read_with_options(filename, options).unwrap();
+            assert_eq!(waves.time_table(), expected.time_table());
+            waves.load_signals(&kept).unwrap();
+            for id in ids.iter() {
+                if kept.contains(id) {
+                    let changes = waves.get_signal(*id).unwrap().time_indices();
+                    let orig = expected.get_signal(*id).unwrap().time_indices();
+                    assert_eq!(changes, orig, "{filter:?}");
+                } else {
+                    let res = waves.load_signals(&[*id]);
+                    assert!(
+                        matches!(res, Err(WellenError::SignalFiltered(_))),
+                        "{filter:?}"
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_vcd_tail() {
+    let bytes = std::fs::read("inputs/icarus/CPU.vcd").unwrap();
+    let filename = std::env::temp_dir().join(format!("wellen_tail_{}.vcd", std::process::id()));
+    let filename = filename.to_str().unwrap();
+    // the simulator has only written parts of the file
+    let (first, second) = bytes.split_at(bytes.len() / 2);
+    std::fs::write(filename, first).unwrap();
+    let (mut waves, mut tail) = vcd::read_tail(filename, LoadOptions::default()).unwrap();
+    let partial_len = waves.time_table().len();
+    assert!(partial_len > 0);
+    let ids: Vec<_> = waves
+        .hierarchy()
+        .iter_vars()
+        .map(|v| v.signal_ref())
+        .collect();
+    waves.load_signals(&ids).unwrap();
+    assert!(!tail.poll(&mut waves).unwrap(), "no new data");
+
+    // the simulation finishes, the values of the last time step are complete once the
+    // next time step starts
+    let mut rest = second.to_vec();
+    rest.extend_from_slice(b"#999999999\n");
+    std::fs::OpenOptions::new()
+        .append(true)
+        .open(filename)
+        .unwrap()
+        .write_all(&rest)
+        .unwrap();
+    assert!(tail.poll(&mut waves).unwrap());
+    let full = std::fs::read(filename).unwrap();
+    std::fs::remove_file(filename).unwrap();
+
+    let mut expected = vcd::read_from_bytes(&full).unwrap();
+    let expected_times = expected.time_table();
+    assert_eq!(
+        waves.time_table(),
+        &expected_times[..expected_times.len() - 1]
+    );
+    assert!(waves.time_table().len() > partial_len);
+    expected.load_signals(&ids).unwrap();
+    for id in ids {
+        // signals that were already loaded are updated
+        let actual = waves.get_signal(id).unwrap().time_indices();
+        assert_eq!(actual, expected.get_signal(id).unwrap().time_indices());
+    }
+}
+
+/// Signals grow whenever new changes are appended, which may exceed the memory budget.
+#[test]
+fn test_vcd_tail_memory_budget() {
+    let bytes = std::fs::read("inputs/icarus/CPU.vcd").unwrap();
+    let filename = std::env::temp_dir().join(format!("wellen_budget_{}.vcd", std::process::id()));
+    let filename = filename.to_str().unwrap();
+    let (first, second) = bytes.split_at(bytes.len() / 2);
+    std::fs::write(filename, first).unwrap();
+    let (mut waves, mut tail) = vcd::read_tail(filename, LoadOptions::default()).unwrap();
+    let ids: Vec<_> = waves
+        .hierarchy()
+        .iter_vars()
+        .map(|v| v.signal_ref())
+        .collect();
+    waves.load_signals(&ids).unwrap();
+    let budget = waves.cache_statistics().bytes_in_memory;
+    waves.set_memory_budget(Some(budget));
+
+    let mut rest = second.to_vec();
+    rest.extend_from_slice(b"#999999999\n");
+    std::fs::OpenOptions::new()
+        .append(true)
+        .open(filename)
+        .unwrap()
+        .write_all(&rest)
+        .unwrap();
+    let result = tail.poll(&mut waves);
+    std::fs::remove_file(filename).unwrap();
+    assert!(result.unwrap());
+    let stats = waves.cache_statistics();
+    assert!(stats.bytes_in_memory <= budget, "{stats:?}");
+    assert!(stats.evictions > 0, "{stats:?}");
+}
+
+/// A simulator that restarts and overwrites its VCD file produces changes that go back in time.
+#[test]
+fn test_vcd_tail_not_chronological() {
+    let filename = std::env::temp_dir().join(format!("wellen_restart_{}.vcd", std::process::id()));
+    let filename = filename.to_str().unwrap();
+    let header = "$var wire 1 ! a $end\n$enddefinitions $end\n";
+    std::fs::write(filename, format!("{header}#10\n1!\n#20\n0!\n")).unwrap();
+    let (mut waves, mut tail) = vcd::read_tail(filename, LoadOptions::default()).unwrap();
+    assert_eq!(waves.time_table(), [10]);
+    std::fs::write(filename, format!("{header}#10\n1!\n#3\n0!\n#4\n1!\n#5\n")).unwrap();
+    let result = tail.poll(&mut waves);
+    std::fs::remove_file(filename).unwrap();
+    assert!(
+        matches!(result, Err(WellenError::NotChronological(10, 3))),
+        "{result:?}"
+    );
+    assert_eq!(waves.time_table(), [10]);
+}
+
+#[test]
+#[cfg(unix)]
+fn test_vcd_from_stream() {
+    let filename = "inputs/icarus/CPU.vcd";
+    let expected = vcd::read(filename).unwrap();
+    // a child process stands in for a simulator that writes its VCD to stdout
+    let mut child = std::process::Command::new("cat")
+        .arg(filename)
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .unwrap();
+    let stdout = child.stdout.take().unwrap();
+    let actual = vcd::read_from_stream(stdout, LoadOptions::default()).unwrap();
+    child.wait().unwrap();
+    assert_eq!(expected.time_table(), actual.time_table());
+    assert_eq!(
+        expected.hierarchy().iter_vars().count(),
+        actual.hierarchy().iter_vars().count()
+    );
+}
+
+#[test]
+fn test_vcd_value_at_time() {
+    let mut waves = vcd::read("inputs/gameroy/trace_prefix.vcd").unwrap();
+    let h = waves.hierarchy();
+    let pc = h.lookup_var(&["gameroy", "cpu"], &"pc").unwrap();
+    let pc = h.get(pc).signal_ref();
+    let sp = h.lookup_var(&["gameroy", "cpu"], &"sp").unwrap();
+    let sp = h.get(sp).signal_ref();
+    waves.load_signals(&[pc, sp]).unwrap();
+
+    // the first time step is at 4
+    assert_eq!(waves.time_table_idx_at(3), None);
+    assert_eq!(waves.time_table_idx_at(4), Some(0));
+    assert_eq!(waves.time_table_idx_at(15), Some(0));
+    assert_eq!(waves.time_table_idx_at(16), Some(1));
+
+    let signal = waves.get_signal(pc).unwrap();
+    assert!(signal.value_at_time(&waves, 3).is_none());
+    let at_4 = signal.value_at_time(&waves, 4).unwrap().to_string();
+    assert_eq!(signal.value_at_time(&waves, 15).unwrap().to_string(), at_4);
+
+    // sp only changes at 16
+    let values = waves.sample(&[pc, sp], 15).unwrap();
+    assert!(values[0].is_some() && values[1].is_none());
+    let values = waves.sample(&[pc, sp], 16).unwrap();
+    assert!(values[0].is_some() && values[1].is_some());
+}
@@ -0,0 +1,193 @@
+// Copyright 2024 The Regents of the University of California
+// released under BSD 3-Clause License
+// author: Kevin Laeufer <laeufer@berkeley.edu>
+//
+// writes waveforms and makes sure that we can read them back
+
+use wellen::*;
+
+fn load_all_signals(filename: &str) -> Waveform {
+    let mut waves = open(filename, LoadOptions::default()).expect("failed to load");
+    let all_signals: Vec<_> = waves
+        .hierarchy()
+        .get_unique_signals_vars()
+        .iter()
+        .flatten()
+        .map(|v| v.signal_ref())
+        .collect();
+    waves.load_signals(&all_signals).unwrap();
+    waves
+}
+
+/// Compares the hierarchy and all signal values of two waveforms.
+fn check_same(expected: &mut Waveform, actual: &mut Waveform) {
+    let (he, ha) = (expected.hierarchy(), actual.hierarchy());
+    assert_eq!(he.timescale(), ha.timescale());
+    let vars_e: Vec<_> = he.iter_vars().collect();
+    let vars_a: Vec<_> = ha.iter_vars().collect();
+    assert_eq!(vars_e.len(), vars_a.len());
+    let mut to_load = Vec::new();
+    for (e, a) in vars_e.iter().zip(vars_a.iter()) {
+        assert_eq!(e.full_name(he), a.full_name(ha));
+        assert_eq!(e.var_type(), a.var_type(), "{}", e.full_name(he));
+        assert_eq!(e.length(), a.length(), "{}", e.full_name(he));
+        assert_eq!(e.index(), a.index(), "{}", e.full_name(he));
+        to_load.push((e.signal_ref(), a.signal_ref()));
+    }
+    let actual_ids: Vec<_> = to_load.iter().map(|(_, a)| *a).collect();
+    actual.load_signals(&actual_ids).unwrap();
+
+    for (e, a) in to_load {
+        let changes_e = signal_changes(expected, e);
+        let changes_a = signal_changes(actual, a);
+        assert_eq!(changes_e, changes_a);
+    }
+}
+
+fn signal_changes(waves: &Waveform, signal: SignalRef) -> Vec<(Time, String)> {
+    let time_table = waves.time_table();
+    let mut out: Vec<(Time, String)> = Vec::new();
+    for (idx, value) in waves.get_signal(signal).unwrap().iter_changes() {
+        let value = value.to_string();
+        // multiple changes in the same time step are collapsed into the last one
+        if let Some(last) = out.last_mut() {
+            if last.0 == time_table[idx as usize] {
+                last.1 = value;
+                continue;
+            }
+        }
+        out.push((time_table[idx as usize], value));
+    }
+    out.dedup_by(|a, b| a.1 == b.1);
+    out
+}
+
+fn run_vcd_round_trip(filename: &str) {
+    let mut waves = load_all_signals(filename);
+    let mut out = Vec::new();
+    vcd::write(&waves, &mut out, vcd::WriteOptions::default()).expect("failed to write VCD");
+    let mut read_back = vcd::read_from_bytes(&out).expect("failed to parse generated VCD");
+    check_same(&mut waves, &mut read_back);
+}
+
+#[test]
+fn test_vcd_round_trip_vcd() {
+    run_vcd_round_trip("inputs/icarus/CPU.vcd");
+}
+
+#[test]
+fn test_vcd_round_trip_fst() {
+    run_vcd_round_trip("inputs/gtkwave-analyzer/des.fst");
+}
+
+#[test]
+fn test_vcd_round_trip_ghw() {
+    run_vcd_round_trip("inputs/ghdl/tb_recv.ghw");
+}
+
+#[test]
+fn test_vcd_write_unloaded_signals() {
+    let waves = open("inputs/icarus/CPU.vcd", LoadOptions::default()).unwrap();
+    let mut out = Vec::new();
+    let result = vcd::write(&waves, &mut out, vcd::WriteOptions::default());
+    assert!(matches!(result, Err(WellenError::SignalNotLoaded(_))));
+
+    let options = vcd::WriteOptions {
+        skip_unloaded_signals: true,
+    };
+    out.clear();
+    vcd::write(&waves, &mut out, options).unwrap();
+    let read_back = vcd::read_from_bytes(&out).unwrap();
+    assert_eq!(read_back.hierarchy().iter_vars().count(), 0);
+}
+
+/// Compares meta-data that only some formats can represent.
+fn check_same_attributes(expected: &Waveform, actual: &Waveform) {
+    let (he, ha) = (expected.hierarchy(), actual.hierarchy());
+    for (e, a) in he.iter_vars().zip(ha.iter_vars()) {
+        let name = e.full_name(he);
+        assert_eq!(e.vhdl_type_name(he), a.vhdl_type_name(ha), "{name}");
+        assert_eq!(e.enum_type(he), a.enum_type(ha), "{name}");
+    }
+    let scopes_e = he.iter_scopes().collect::<Vec<_>>();
+    let scopes_a = ha.iter_scopes().collect::<Vec<_>>();
+    assert_eq!(scopes_e.len(), scopes_a.len());
+    for (e, a) in scopes_e.into_iter().zip(scopes_a) {
+        let name = e.full_name(he);
+        assert_eq!(e.scope_type(), a.scope_type(), "{name}");
+        assert_eq!(e.source_loc(he), a.source_loc(ha), "{name}");
+        assert_eq!(
+            e.instantiation_source_loc(he),
+            a.instantiation_source_loc(ha),
+            "{name}"
+        );
+    }
+}
+
+fn run_fst_round_trip(filename: &str, options: fst::WriteOptions) {
+    let mut waves = load_all_signals(filename);
+    let mut out = Vec::new();
+    fst::write(&waves, &mut out, options).expect("failed to write FST");
+    let mut read_back = fst::read_from_bytes(out).expect("failed to parse generated FST");
+    check_same_attributes(&waves, &read_back);
+    check_same(&mut waves, &mut read_back);
+}
+
+#[test]
+fn test_fst_round_trip_vcd() {
+    run_fst_round_trip("inputs/icarus/CPU.vcd", fst::WriteOptions::default());
+}
+
+#[test]
+fn test_fst_round_trip_fst() {
+    run_fst_round_trip(
+        "inputs/gtkwave-analyzer/des.fst",
+        fst::WriteOptions::default(),
+    );
+}
+
+#[test]
+fn test_fst_round_trip_vhdl_fst() {
+    run_fst_round_trip("inputs/ghdl/oscar/ghdl.fst", fst::WriteOptions::default());
+    run_fst_round_trip("inputs/ghdl/oscar/vhdl3.fst", fst::WriteOptions::default());
+}
+
+#[test]
+fn test_fst_round_trip_ghw() {
+    run_fst_round_trip("inputs/ghdl/tb_recv.ghw", fst::WriteOptions::default());
+    // contains enum types
+    run_fst_round_trip("inputs/ghdl/oscar/test.ghw", fst::WriteOptions::default());
+}
+
+#[test]
+fn test_fst_round_trip_small_blocks() {
+    let options = fst::WriteOptions {
+        max_changes_per_block: 100,
+        ..Default::default()
+    };
+    run_fst_round_trip("inputs/icarus/CPU.vcd", options);
+    run_fst_round_trip("inputs/ghdl/tb_recv.ghw", options);
+}
+
+#[test]
+fn test_vcd_round_trip_shortreal() {
+    let input = b"$var shortreal 32 ! r $end\n$enddefinitions $end\n#0\nr1.5 !\n#10\nr-2.25 !\n";
+    let mut waves = vcd::read_from_bytes(input).unwrap();
+    let id = waves.hierarchy().iter_vars().next().unwrap().signal_ref();
+    waves.load_signals(&[id]).unwrap();
+    let mut out = Vec::new();
+    vcd::write(&waves, &mut out, vcd::WriteOptions::default()).unwrap();
+    assert!(String::from_utf8_lossy(&out).contains("$var shortreal "));
+    let mut read_back = vcd::read_from_bytes(&out).unwrap();
+    let var = read_back.hierarchy().iter_vars().next().unwrap();
+    assert_eq!(var.var_type(), VarType::ShortReal);
+    check_same(&mut waves, &mut read_back);
+
+    // files written with the misspelled type can still be read
+    let old = String::from_utf8(out)
+        .unwrap()
+        .replace("shortreal", "shortread");
+    let read_old = vcd::read_from_bytes(old.as_bytes()).unwrap();
+    let var = read_old.hierarchy().iter_vars().next().unwrap();
+    assert_eq!(var.var_type(), VarType::ShortReal);
+}